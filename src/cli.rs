@@ -0,0 +1,79 @@
+use crate::core::AppState;
+use crate::error::{Result, TaskVimError};
+use crate::core::transfer::Format;
+use chrono::Utc;
+
+const USAGE: &str = "Usage: taskvim [gc | export [format] [--filter <expr>] [path] | import [format] <path>]
+Formats: json (default), taskwarrior, todotxt, md";
+
+/// Takes `--filter <expr>` (or `--filter=<expr>`) out of `args`.
+fn take_filter(args: &[String]) -> Result<(Vec<String>, Option<String>)> {
+    let mut rest = Vec::new();
+    let mut filter = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--filter" {
+            let expr = args.next().ok_or_else(|| TaskVimError::Validation(format!("--filter needs an expression\n{}", USAGE)))?;
+            filter = Some(expr.clone());
+        } else if let Some(expr) = arg.strip_prefix("--filter=") {
+            filter = Some(expr.to_string());
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    Ok((rest, filter))
+}
+
+/// Splits `[format] [path]`.
+fn format_and_path(args: &[String]) -> (Format, Option<&String>) {
+    match args.first().and_then(|name| Format::from_name(name)) {
+        Some(format) => (format, args.get(1)),
+        None => (Format::Json, args.first()),
+    }
+}
+
+/// Runs `taskvim <command>` without the TUI. Returns `false` when there is
+/// no command, so the TUI starts.
+pub fn run(args: &[String], state: &mut AppState) -> Result<bool> {
+    let Some(command) = args.first() else {
+        return Ok(false);
+    };
+    match command.as_str() {
+        "gc" => {
+            let now = Utc::now();
+            let (archived, purged) = state.collect_garbage(now)?;
+            let expired = state.expire_trash(now)?;
+            println!("Archived {} and purged {} tasks, {} expired from the trash", archived, purged, expired);
+        }
+        "export" => {
+            let (args, filter) = take_filter(&args[1..])?;
+            let (format, path) = format_and_path(&args);
+            // Like `:export`: the filtered view, and for Markdown the view
+            // even without a filter; otherwise the whole database
+            let ids = if filter.is_some() || format == Format::Markdown {
+                state.filter_string = filter;
+                state.reload_tasks()?;
+                Some(state.tasks.iter().map(|t| t.id).collect::<Vec<_>>())
+            } else {
+                None
+            };
+            let text = format.render(&state.export_document(ids.as_deref())?)?;
+            match path {
+                Some(path) => std::fs::write(path, text)?,
+                None => println!("{}", text),
+            }
+        }
+        "import" => {
+            let (format, path) = format_and_path(&args[1..]);
+            let path = path.ok_or_else(|| TaskVimError::Validation(format!("import needs a file\n{}", USAGE)))?;
+            let summary = state.import_text(format, &std::fs::read_to_string(path)?)?;
+            for title in &summary.conflicts {
+                println!("Conflict: kept the newer local copy of {}", title);
+            }
+            println!("{}", summary.describe());
+        }
+        "-h" | "--help" | "help" => println!("{}", USAGE),
+        _ => return Err(TaskVimError::Validation(format!("Unknown command: {}\n{}", command, USAGE))),
+    }
+    Ok(true)
+}
@@ -5,7 +5,80 @@ use crate::core::state::Mode;
 use mlua::Lua;
 use std::sync::{Arc, Mutex};
 use std::str::FromStr;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlight {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reversed: bool,
+}
+
+/// A table column as written in the Lua config. Field names and widths are
+/// resolved by `ui::columns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    pub field: String,
+    pub width: Option<String>,
+    pub title: Option<String>,
+    pub wrap: bool,
+}
+
+impl ColumnSpec {
+    pub fn new(field: &str, width: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            width: Some(width.to_string()),
+            title: None,
+            wrap: false,
+        }
+    }
+
+    fn from_lua(value: mlua::Value) -> mlua::Result<Self> {
+        let spec = match value {
+            mlua::Value::String(s) => Self {
+                field: s.to_str()?.to_string(),
+                width: None,
+                title: None,
+                wrap: false,
+            },
+            mlua::Value::Table(t) => Self {
+                field: t.get(1)?,
+                width: match t.get::<_, mlua::Value>("width")? {
+                    mlua::Value::Nil => None,
+                    mlua::Value::Integer(n) => Some(n.to_string()),
+                    mlua::Value::Number(n) => Some(n.to_string()),
+                    mlua::Value::String(s) => Some(s.to_str()?.to_string()),
+                    _ => return Err(mlua::Error::RuntimeError("column width must be a number or string".into())),
+                },
+                title: t.get("title")?,
+                wrap: t.get::<_, Option<bool>>("wrap")?.unwrap_or(false),
+            },
+            _ => return Err(mlua::Error::RuntimeError("column must be a field name or table".into())),
+        };
+
+        if crate::ui::columns::Column::from_spec(&spec).is_none() {
+            return Err(mlua::Error::RuntimeError(format!(
+                "invalid column '{}' (width {:?})", spec.field, spec.width
+            )));
+        }
+        Ok(spec)
+    }
+}
+
+pub fn default_columns() -> Vec<ColumnSpec> {
+    vec![
+        ColumnSpec::new("id", "10"),
+        ColumnSpec::new("status", "10"),
+        ColumnSpec::new("priority", "10"),
+        ColumnSpec::new("title", "50%"),
+        ColumnSpec::new("project", "20%"),
+        ColumnSpec::new("tags", "*"),
+    ]
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -13,7 +86,62 @@ pub struct Config {
     pub default_priority: i32,
     pub show_sidebar: bool,
     pub keymap: Keymap,
-    pub hooks: HashMap<String, Vec<String>>,
+    pub highlights: HashMap<String, Highlight>,
+    pub columns: Vec<ColumnSpec>,
+    pub view_columns: HashMap<String, Vec<ColumnSpec>>,
+    /// User commands defined with `command()`, name to description.
+    pub commands: BTreeMap<String, String>,
+    /// Start a timer when a task moves to Doing and stop it when it leaves.
+    pub auto_time_tracking: bool,
+    pub pomodoro: PomodoroConfig,
+    pub archive: ArchivePolicy,
+    /// List Archived tasks with the others instead of only in `:archived`
+    /// and in filters on `status`.
+    pub show_archived: bool,
+    /// Days deleted tasks stay in the trash. `None` keeps them until purged.
+    pub trash_days: Option<u32>,
+    /// A todo.txt file kept in sync with the database.
+    pub todotxt: Option<String>,
+}
+
+/// When `:gc` (and startup, with `on_startup`) archives Done tasks and
+/// purges Archived ones, in days. `None` never does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePolicy {
+    pub archive_done_after: Option<u32>,
+    pub purge_archived_after: Option<u32>,
+    pub on_startup: bool,
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        Self {
+            archive_done_after: None,
+            purge_archived_after: None,
+            on_startup: true,
+        }
+    }
+}
+
+/// Pomodoro lengths in minutes, set with `set.pomodoro{...}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PomodoroConfig {
+    pub work: u32,
+    pub short_break: u32,
+    pub long_break: u32,
+    /// Every this many work periods the break is a long one.
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_every: 4,
+        }
+    }
 }
 
 impl Default for Config {
@@ -23,7 +151,16 @@ impl Default for Config {
             default_priority: 3,
             show_sidebar: true,
             keymap: Keymap::new(),
-            hooks: HashMap::new(),
+            highlights: HashMap::new(),
+            columns: default_columns(),
+            view_columns: HashMap::new(),
+            commands: BTreeMap::new(),
+            auto_time_tracking: true,
+            pomodoro: PomodoroConfig::default(),
+            archive: ArchivePolicy::default(),
+            show_archived: false,
+            trash_days: Some(30),
+            todotxt: None,
         }
     }
 }
@@ -56,7 +193,7 @@ impl LuaConfig {
                 }
             }
         }
-        Ok(Default::default())
+        Ok(())
     }
 
     fn init_api(&self) -> Result<()> {
@@ -66,6 +203,7 @@ impl LuaConfig {
         // Internal hooks table
         let hooks_table = self.lua.create_table()?;
         globals.set("__hooks", hooks_table)?;
+        globals.set("__commands", self.lua.create_table()?)?;
 
         // set table
         let set = self.lua.create_table()?;
@@ -91,6 +229,82 @@ impl LuaConfig {
             Ok(())
         })?)?;
 
+        let c_tracking = Arc::clone(&config_arc);
+        set.set("time_tracking", self.lua.create_function(move |_, enabled: bool| {
+            let mut c = c_tracking.lock().unwrap();
+            c.auto_time_tracking = enabled;
+            Ok(())
+        })?)?;
+
+        let c_pomodoro = Arc::clone(&config_arc);
+        set.set("pomodoro", self.lua.create_function(move |_, opts: mlua::Table| {
+            let mut c = c_pomodoro.lock().unwrap();
+            let current = c.pomodoro.clone();
+            let minutes = |key: &str, default: u32| -> mlua::Result<u32> {
+                match opts.get::<_, Option<u32>>(key)? {
+                    Some(0) => Err(mlua::Error::RuntimeError(format!("pomodoro {} must be at least 1", key))),
+                    Some(n) => Ok(n),
+                    None => Ok(default),
+                }
+            };
+            c.pomodoro = PomodoroConfig {
+                work: minutes("work", current.work)?,
+                short_break: minutes("short_break", current.short_break)?,
+                long_break: minutes("long_break", current.long_break)?,
+                long_break_every: minutes("long_break_every", current.long_break_every)?,
+            };
+            Ok(())
+        })?)?;
+
+        let c_archive = Arc::clone(&config_arc);
+        set.set("archive", self.lua.create_function(move |_, opts: mlua::Table| {
+            let mut c = c_archive.lock().unwrap();
+            // `false` turns a rule off, a missing key keeps it
+            let days = |key: &str, current: Option<u32>| -> mlua::Result<Option<u32>> {
+                match opts.get::<_, mlua::Value>(key)? {
+                    mlua::Value::Nil => Ok(current),
+                    mlua::Value::Boolean(false) => Ok(None),
+                    mlua::Value::Integer(n) if n >= 0 => Ok(Some(n as u32)),
+                    _ => Err(mlua::Error::RuntimeError(format!("archive {} must be a number of days or false", key))),
+                }
+            };
+            let policy = ArchivePolicy {
+                archive_done_after: days("done_after", c.archive.archive_done_after)?,
+                purge_archived_after: days("purge_after", c.archive.purge_archived_after)?,
+                on_startup: opts.get::<_, Option<bool>>("on_startup")?.unwrap_or(c.archive.on_startup),
+            };
+            c.archive = policy;
+            Ok(())
+        })?)?;
+
+        let c_show_archived = Arc::clone(&config_arc);
+        set.set("show_archived", self.lua.create_function(move |_, show: bool| {
+            c_show_archived.lock().unwrap().show_archived = show;
+            Ok(())
+        })?)?;
+
+        let c_trash = Arc::clone(&config_arc);
+        set.set("trash_days", self.lua.create_function(move |_, days: mlua::Value| {
+            let days = match days {
+                mlua::Value::Boolean(false) => None,
+                mlua::Value::Integer(n) if n >= 0 => Some(n as u32),
+                _ => return Err(mlua::Error::RuntimeError("trash_days must be a number of days or false".to_string())),
+            };
+            c_trash.lock().unwrap().trash_days = days;
+            Ok(())
+        })?)?;
+
+        let c_todotxt = Arc::clone(&config_arc);
+        set.set("todotxt", self.lua.create_function(move |_, path: mlua::Value| {
+            let path = match path {
+                mlua::Value::Boolean(false) | mlua::Value::Nil => None,
+                mlua::Value::String(path) => Some(path.to_str()?.to_string()),
+                _ => return Err(mlua::Error::RuntimeError("todotxt must be a file path or false".to_string())),
+            };
+            c_todotxt.lock().unwrap().todotxt = path;
+            Ok(())
+        })?)?;
+
         globals.set("set", set)?;
 
         // hook function
@@ -107,6 +321,12 @@ impl LuaConfig {
         // map function
         let c_map = Arc::clone(&config_arc);
         globals.set("map", self.lua.create_function(move |_, (mode_str, key_str, action_str): (String, String, String)| {
+            if mode_str == "sidebar" {
+                if let (Some(combo), Ok(action)) = (KeyCombination::from_str(&key_str), Action::from_str(&action_str)) {
+                    c_map.lock().unwrap().keymap.sidebar.insert(combo, action);
+                }
+                return Ok(());
+            }
             let mode = match mode_str.as_str() {
                 "n" | "normal" => Mode::Normal,
                 "v" | "visual" => Mode::Visual,
@@ -123,6 +343,56 @@ impl LuaConfig {
             Ok(())
         })?)?;
 
+        // command function: command("standup", function(args) ... end, "Plan the day")
+        // defines `:standup [args]`
+        let c_cmd = Arc::clone(&config_arc);
+        globals.set("command", self.lua.create_function(move |lua, (name, func, desc): (String, mlua::Function, Option<String>)| {
+            let commands: mlua::Table = lua.globals().get("__commands")?;
+            commands.set(name.clone(), func)?;
+            let mut c = c_cmd.lock().unwrap();
+            c.commands.insert(name, desc.unwrap_or_default());
+            Ok(())
+        })?)?;
+
+        // highlight function: highlight("StatusDone", { fg = "green", bold = true })
+        let c_hl = Arc::clone(&config_arc);
+        globals.set("highlight", self.lua.create_function(move |_, (group, opts): (String, mlua::Table)| {
+            let hl = Highlight {
+                fg: opts.get("fg")?,
+                bg: opts.get("bg")?,
+                bold: opts.get::<_, Option<bool>>("bold")?.unwrap_or(false),
+                italic: opts.get::<_, Option<bool>>("italic")?.unwrap_or(false),
+                underline: opts.get::<_, Option<bool>>("underline")?.unwrap_or(false),
+                reversed: opts.get::<_, Option<bool>>("reversed")?.unwrap_or(false),
+            };
+            let mut c = c_hl.lock().unwrap();
+            c.highlights.insert(group, hl);
+            Ok(())
+        })?)?;
+
+        // columns function: columns({ "id", { "title", width = "50%", wrap = true } })
+        // or columns("view_name", { ... }) for a per-view column set
+        let c_cols = Arc::clone(&config_arc);
+        globals.set("columns", self.lua.create_function(move |_, args: mlua::Variadic<mlua::Value>| {
+            let (view, list) = match (args.first(), args.get(1)) {
+                (Some(mlua::Value::Table(t)), None) => (None, t.clone()),
+                (Some(mlua::Value::String(name)), Some(mlua::Value::Table(t))) => (Some(name.to_str()?.to_string()), t.clone()),
+                _ => return Err(mlua::Error::RuntimeError("usage: columns([view,] { ... })".into())),
+            };
+
+            let specs = list
+                .sequence_values::<mlua::Value>()
+                .map(|v| ColumnSpec::from_lua(v?))
+                .collect::<mlua::Result<Vec<_>>>()?;
+
+            let mut c = c_cols.lock().unwrap();
+            match view {
+                Some(name) => { c.view_columns.insert(name, specs); }
+                None => c.columns = specs,
+            }
+            Ok(())
+        })?)?;
+
         Ok(())
     }
 
@@ -140,6 +410,19 @@ impl LuaConfig {
         Ok(())
     }
 
+    /// Runs the user command `name` with the rest of the command line.
+    /// Returns false when no such command is defined.
+    pub fn run_command(&self, name: &str, args: &str) -> Result<bool> {
+        let commands: mlua::Table = self.lua.globals().get("__commands")?;
+        match commands.get::<_, Option<mlua::Function>>(name)? {
+            Some(func) => {
+                func.call::<_, ()>(args.to_string())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub fn run_code(&self, code: &str) -> Result<()> {
         self.lua.load(code).exec()?;
         Ok(())
@@ -26,7 +26,36 @@ pub enum Action {
     PrevProject,
     Yank,
     Paste,
+    PasteAbove,
     EnterSearch,
+    FocusSidebar,
+    FocusTasks,
+    SidebarApply,
+    Indent,
+    Outdent,
+    MoveTaskDown,
+    MoveTaskUp,
+    EnterVisualLine,
+    ToggleMark,
+    PromptStatus,
+    PromptProject,
+    PromptTags,
+    PromptDue,
+    RecordMacro,
+    PlayMacro,
+    RepeatChange,
+    EnterSearchBackward,
+    SearchNext,
+    SearchPrev,
+    SearchProject,
+    SearchProjectBackward,
+    EnterFilterSearch,
+    FindTasks,
+    CommandPalette,
+    StartTimer,
+    StopTimer,
+    TogglePomodoro,
+    ShowDetail,
 }
 
 impl FromStr for Action {
@@ -58,8 +87,76 @@ impl FromStr for Action {
             "prev_project" => Ok(Action::PrevProject),
             "yank" => Ok(Action::Yank),
             "paste" => Ok(Action::Paste),
+            "paste_above" => Ok(Action::PasteAbove),
             "search" => Ok(Action::EnterSearch),
+            "focus_sidebar" => Ok(Action::FocusSidebar),
+            "focus_tasks" => Ok(Action::FocusTasks),
+            "sidebar_apply" => Ok(Action::SidebarApply),
+            "indent" => Ok(Action::Indent),
+            "outdent" => Ok(Action::Outdent),
+            "move_task_down" => Ok(Action::MoveTaskDown),
+            "move_task_up" => Ok(Action::MoveTaskUp),
+            "visual_line" => Ok(Action::EnterVisualLine),
+            "toggle_mark" | "mark" => Ok(Action::ToggleMark),
+            "set_status" => Ok(Action::PromptStatus),
+            "set_project" => Ok(Action::PromptProject),
+            "edit_tags" => Ok(Action::PromptTags),
+            "set_due" => Ok(Action::PromptDue),
+            "record_macro" => Ok(Action::RecordMacro),
+            "play_macro" => Ok(Action::PlayMacro),
+            "repeat" | "repeat_change" => Ok(Action::RepeatChange),
+            "search_backward" => Ok(Action::EnterSearchBackward),
+            "search_next" => Ok(Action::SearchNext),
+            "search_prev" => Ok(Action::SearchPrev),
+            "search_project" => Ok(Action::SearchProject),
+            "search_project_backward" => Ok(Action::SearchProjectBackward),
+            "filter_search" => Ok(Action::EnterFilterSearch),
+            "find_tasks" => Ok(Action::FindTasks),
+            "command_palette" => Ok(Action::CommandPalette),
+            "start_timer" => Ok(Action::StartTimer),
+            "stop_timer" => Ok(Action::StopTimer),
+            "toggle_pomodoro" => Ok(Action::TogglePomodoro),
+            "show_detail" => Ok(Action::ShowDetail),
             _ => Err(()),
         }
     }
 }
+
+impl Action {
+    /// Whether a count prefix (`3j`, `5+`) repeats the action. `3d` deletes
+    /// three tasks instead.
+    pub fn takes_count(&self) -> bool {
+        matches!(
+            self,
+            Action::MoveDown
+                | Action::MoveUp
+                | Action::PageDown
+                | Action::PageUp
+                | Action::MoveTaskDown
+                | Action::MoveTaskUp
+                | Action::IncreasePriority
+                | Action::DecreasePriority
+                | Action::CycleStatus
+                | Action::Delete
+                | Action::Paste
+                | Action::PasteAbove
+                | Action::Undo
+                | Action::Redo
+                | Action::SearchNext
+                | Action::SearchPrev
+        )
+    }
+
+    /// Whether the action modifies tasks and is remembered for `.`.
+    pub fn is_change(&self) -> bool {
+        matches!(
+            self,
+            Action::Delete
+                | Action::CycleStatus
+                | Action::IncreasePriority
+                | Action::DecreasePriority
+                | Action::Paste
+                | Action::PasteAbove
+        )
+    }
+}
@@ -0,0 +1,214 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::query::Filter;
+use crate::domain::timeline::{completed_at, StatusEvent};
+use crate::domain::{Task, TaskStatus};
+use crate::error::{Result, TaskVimError};
+use chrono::{DateTime, Duration, Utc};
+use crossterm::event::{KeyCode, KeyEvent};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The Archived tasks listed by `:archived`.
+#[derive(Debug, Clone)]
+pub struct ArchiveView {
+    pub tasks: Vec<Task>,
+    pub selected: usize,
+}
+
+/// When the task last moved to `status`, or its last update for changes from
+/// before status events were recorded.
+fn entered(events: &[StatusEvent], task: &Task, status: TaskStatus) -> DateTime<Utc> {
+    let at = match status {
+        TaskStatus::Done => completed_at(events, task.id),
+        _ => events.iter().filter(|e| e.task_id == task.id && e.to == status).map(|e| e.at).max(),
+    };
+    at.unwrap_or(task.updated_at)
+}
+
+/// The status an archived task had before it was archived: Done unless its
+/// events say otherwise.
+fn status_before_archiving(events: &[StatusEvent], id: Uuid) -> TaskStatus {
+    events
+        .iter()
+        .rev()
+        .find(|e| e.task_id == id && e.to == TaskStatus::Archived)
+        .and_then(|e| e.from)
+        .filter(|s| *s != TaskStatus::Archived)
+        .unwrap_or(TaskStatus::Done)
+}
+
+impl AppState {
+    /// Whether the task list shows Archived tasks: with `set.show_archived`,
+    /// or when the filter has a `status` condition Archived tasks meet.
+    pub fn shows_archived(&self) -> bool {
+        let archived = TaskStatus::Archived.to_string();
+        self.config.show_archived
+            || self
+                .filter_string
+                .as_deref()
+                .and_then(|f| Filter::parse(f).ok())
+                .map(|filters| filters.iter().any(|f| f.field == "status" && f.matches_text(&archived)))
+                .unwrap_or(false)
+    }
+
+    /// `:gc`: archives Done tasks and purges Archived ones older than the
+    /// policy allows. Archiving is one undo step; purging is permanent.
+    /// Returns how many tasks were archived and purged.
+    pub fn collect_garbage(&mut self, now: DateTime<Utc>) -> Result<(usize, usize)> {
+        let policy = self.config.archive.clone();
+        let events = self.storage.status_events(None)?;
+        let tasks = self.storage.get_tasks(None)?;
+        let older_than = |task: &Task, status: TaskStatus, days: Option<u32>| {
+            let Some(days) = days else {
+                return false;
+            };
+            task.status == status && entered(&events, task, status) <= now - Duration::days(days as i64)
+        };
+
+        let stale: Vec<Uuid> = tasks.iter().filter(|t| older_than(t, TaskStatus::Archived, policy.purge_archived_after)).map(|t| t.id).collect();
+        let expired: Vec<Uuid> = tasks.iter().filter(|t| older_than(t, TaskStatus::Done, policy.archive_done_after)).map(|t| t.id).collect();
+        let purged = self.purge_tasks(&stale)?;
+        let archived = if expired.is_empty() {
+            0
+        } else {
+            self.update_tasks(&expired, "on_status_change", |task| {
+                task.status = TaskStatus::Archived;
+                true
+            })?
+        };
+        Ok((archived, purged))
+    }
+
+    /// Deletes Archived tasks and their Archived subtasks for good, without
+    /// undo: their history goes with them. Subtasks in any other status are
+    /// kept: they move up to take the place of the purged task they were
+    /// under.
+    pub fn purge_tasks(&mut self, ids: &[Uuid]) -> Result<usize> {
+        let all = self.storage.get_tasks(None)?;
+        let mut doomed: Vec<Task> = all.iter().filter(|t| ids.contains(&t.id) && t.status == TaskStatus::Archived).cloned().collect();
+        let mut orphans: Vec<Task> = Vec::new();
+        let mut i = 0;
+        while i < doomed.len() {
+            let parent = doomed[i].id;
+            for child in all.iter().filter(|t| t.parent_id == Some(parent)) {
+                if child.status != TaskStatus::Archived {
+                    orphans.push(child.clone());
+                } else if !doomed.iter().any(|t| t.id == child.id) {
+                    doomed.push(child.clone());
+                }
+            }
+            i += 1;
+        }
+
+        // Where the last orphan of each purged task went, to keep their order
+        let mut placed: HashMap<Uuid, Uuid> = HashMap::new();
+        for mut orphan in orphans {
+            // The outermost purged ancestor, whose place the orphan takes
+            let mut top = doomed.iter().find(|t| Some(t.id) == orphan.parent_id).expect("orphan of a purged task");
+            while let Some(parent) = doomed.iter().find(|t| Some(t.id) == top.parent_id) {
+                top = parent;
+            }
+            let (parent, anchor) = (top.parent_id, placed.get(&top.id).copied().unwrap_or(top.id));
+            placed.insert(top.id, orphan.id);
+            orphan.position = self.slots_after(parent, Some(anchor), 1)?[0];
+            orphan.parent_id = parent;
+            self.storage.save_task(&orphan)?;
+        }
+        for task in &doomed {
+            self.storage.delete_task(task.id)?;
+            self.storage.forget_history(task.id)?;
+            self.marked_tasks.remove(&task.id);
+            self.folded_tasks.remove(&task.id);
+        }
+        self.write_todotxt()?;
+        self.reload_tasks()?;
+        Ok(doomed.len())
+    }
+
+    /// `:gc` / `:archive gc`, reporting what it did. Also expires the trash.
+    pub fn run_gc(&mut self) -> Result<()> {
+        let now = Utc::now();
+        let (archived, purged) = self.collect_garbage(now)?;
+        let expired = self.expire_trash(now)?;
+        self.info(format!("Archived {} and purged {} tasks, {} expired from the trash", archived, purged, expired));
+        Ok(())
+    }
+
+    /// `:archived`: browses the Archived tasks.
+    pub fn open_archive(&mut self) -> Result<()> {
+        self.archive_view = Some(ArchiveView { tasks: Vec::new(), selected: 0 });
+        self.reload_archive()?;
+        self.mode = Mode::Archive;
+        Ok(())
+    }
+
+    fn reload_archive(&mut self) -> Result<()> {
+        let mut tasks: Vec<Task> = self.storage.get_tasks(None)?.into_iter().filter(|t| t.status == TaskStatus::Archived).collect();
+        tasks.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+        if let Some(view) = self.archive_view.as_mut() {
+            view.selected = view.selected.min(tasks.len().saturating_sub(1));
+            view.tasks = tasks;
+        }
+        Ok(())
+    }
+
+    fn selected_archived(&self) -> Option<Uuid> {
+        let view = self.archive_view.as_ref()?;
+        view.tasks.get(view.selected).map(|t| t.id)
+    }
+
+    /// Moves an archived task back to the status it had before archiving.
+    pub fn restore_archived(&mut self, id: Uuid) -> Result<()> {
+        let status = status_before_archiving(&self.storage.status_events(Some(id))?, id);
+        self.update_tasks(&[id], "on_status_change", |task| {
+            task.status = status;
+            true
+        })?;
+        Ok(())
+    }
+
+    pub fn handle_archive_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(view) = self.archive_view.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        let last = view.tasks.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => view.selected = (view.selected + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('G') => view.selected = last,
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('r') | KeyCode::Enter => {
+                if let Some(id) = self.selected_archived() {
+                    self.restore_archived(id)?;
+                    self.reload_archive()?;
+                    self.info("Restored 1 task");
+                }
+            }
+            KeyCode::Char('D') => {
+                if let Some(id) = self.selected_archived() {
+                    let deleted = self.collect_subtrees(&[id])?.len();
+                    self.delete_tasks(&[id])?;
+                    self.reload_archive()?;
+                    self.info(format!("Moved {} tasks to the trash", deleted));
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.archive_view = None;
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `:archive gc` and `:archive list`; a bare `:archive` archives the
+    /// selection.
+    pub fn archive_command(&mut self, args: &str) -> Result<()> {
+        match args {
+            "gc" => self.run_gc(),
+            "list" => self.open_archive(),
+            _ => Err(TaskVimError::Validation(format!("Invalid archive argument: {} (gc, list)", args))),
+        }
+    }
+}
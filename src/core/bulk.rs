@@ -0,0 +1,320 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::date::parse_date;
+use crate::domain::modify::{parse_modifications, Modification};
+use crate::domain::{Task, TaskStatus};
+use crate::error::{Result, TaskVimError};
+use chrono::Utc;
+use uuid::Uuid;
+
+impl AppState {
+    /// The ids of the tasks an operator acts on, in display order: the
+    /// Visual range (plus all subtasks in Visual-Line mode), the marked
+    /// tasks, or the selected task.
+    pub fn selected_ids(&self) -> Vec<Uuid> {
+        if let Some(ids) = &self.command_targets {
+            return ids.clone();
+        }
+        match (self.mode, self.selection_anchor) {
+            (Mode::Visual | Mode::VisualLine, Some(anchor)) if !self.tasks.is_empty() => {
+                let start = anchor.min(self.selected_index);
+                let end = anchor.max(self.selected_index).min(self.tasks.len() - 1);
+                let mut ids: Vec<Uuid> = self.tasks[start..=end].iter().map(|t| t.id).collect();
+                if self.mode == Mode::VisualLine {
+                    for id in ids.clone() {
+                        for child in self.descendant_ids(id).unwrap_or_default() {
+                            if !ids.contains(&child) {
+                                ids.push(child);
+                            }
+                        }
+                    }
+                }
+                ids
+            }
+            _ if !self.marked_tasks.is_empty() => self
+                .tasks
+                .iter()
+                .filter(|t| self.marked_tasks.contains(&t.id))
+                .map(|t| t.id)
+                .collect(),
+            _ => self.tasks.get(self.selected_index).map(|t| t.id).into_iter().collect(),
+        }
+    }
+
+    /// Whether row `index` is inside the Visual range.
+    pub fn in_visual_range(&self, index: usize) -> bool {
+        match (self.mode, self.selection_anchor) {
+            (Mode::Visual | Mode::VisualLine, Some(anchor)) => {
+                index >= anchor.min(self.selected_index) && index <= anchor.max(self.selected_index)
+            }
+            _ => false,
+        }
+    }
+
+    /// Operators drop back to Normal mode afterwards, like Vim.
+    pub fn end_visual(&mut self) {
+        if matches!(self.mode, Mode::Visual | Mode::VisualLine) {
+            self.remember_visual();
+            self.mode = Mode::Normal;
+            self.selection_anchor = None;
+        }
+    }
+
+    /// Keeps the ends of the Visual selection for the `'<` and `'>`
+    /// addresses.
+    pub fn remember_visual(&mut self) {
+        let (Mode::Visual | Mode::VisualLine, Some(anchor)) = (self.mode, self.selection_anchor) else {
+            return;
+        };
+        let (start, end) = (anchor.min(self.selected_index), anchor.max(self.selected_index));
+        if let (Some(first), Some(last)) = (self.tasks.get(start), self.tasks.get(end)) {
+            self.last_visual = Some((first.id, last.id));
+        }
+    }
+
+    /// `v` / `V`: enters the given Visual mode, switches between the two, or
+    /// leaves Visual mode when it is already active.
+    pub fn toggle_visual(&mut self, mode: Mode) {
+        if self.mode == mode {
+            self.end_visual();
+            return;
+        }
+        if self.selection_anchor.is_none() || !matches!(self.mode, Mode::Visual | Mode::VisualLine) {
+            self.selection_anchor = Some(self.selected_index);
+        }
+        self.mode = mode;
+    }
+
+    /// `m`: toggles the mark on the selected task, or on every task of the
+    /// Visual range. Operators act on all marked tasks until `Esc`.
+    pub fn toggle_mark(&mut self) {
+        let ids = match self.mode {
+            Mode::Visual | Mode::VisualLine => self.selected_ids(),
+            _ => self.tasks.get(self.selected_index).map(|t| t.id).into_iter().collect(),
+        };
+        for id in ids {
+            if !self.marked_tasks.remove(&id) {
+                self.marked_tasks.insert(id);
+            }
+        }
+        self.end_visual();
+    }
+
+    /// Applies `f` to each task in `ids` as a single undo step. `f` returns
+    /// whether it changed the task; untouched tasks are not saved. Returns
+    /// the number of tasks changed.
+    pub fn update_tasks(&mut self, ids: &[Uuid], hook: &str, mut f: impl FnMut(&mut Task) -> bool) -> Result<usize> {
+        let now = Utc::now();
+        let mut changed = Vec::new();
+        let mut transitions = Vec::new();
+        self.with_history_group(|state| {
+            for &id in ids {
+                let Some(original) = state.storage.get_task(id)? else {
+                    continue;
+                };
+                let mut task = original.clone();
+                if !f(&mut task) {
+                    continue;
+                }
+                state.storage.push_history(&original)?;
+                task.updated_at = now;
+                state.storage.save_task(&task)?;
+                transitions.push((id, original.status, task.status));
+                changed.push(task);
+            }
+            Ok(())
+        })?;
+
+        if !changed.is_empty() {
+            self.storage.clear_redo()?;
+            self.write_todotxt()?;
+        }
+        self.track_status_changes(&transitions)?;
+        self.end_pomodoro_on_status_changes(&transitions);
+        for task in &changed {
+            self.run_hook(hook, Some(task));
+        }
+        self.end_visual();
+        self.reload_tasks()?;
+        Ok(changed.len())
+    }
+
+    pub fn set_status(&mut self, status: TaskStatus) -> Result<usize> {
+        let ids = self.selected_ids();
+        self.update_tasks(&ids, "on_status_change", |task| {
+            let changed = task.status != status;
+            task.status = status;
+            changed
+        })
+    }
+
+    /// `Enter`: moves the tasks on to the status following the first one's
+    /// (Todo -> Doing -> Done -> Archived -> Todo).
+    pub fn cycle_status(&mut self) -> Result<()> {
+        let Some(first) = self.selected_ids().first().and_then(|id| self.tasks.iter().find(|t| t.id == *id)) else {
+            return Ok(());
+        };
+        let next = match first.status {
+            TaskStatus::Todo => TaskStatus::Doing,
+            TaskStatus::Doing => TaskStatus::Done,
+            TaskStatus::Done => TaskStatus::Archived,
+            TaskStatus::Archived => TaskStatus::Todo,
+        };
+        self.set_status(next)?;
+        Ok(())
+    }
+
+    /// `+` / `-`: shifts priority by `delta`, within 1..=5.
+    pub fn shift_priority(&mut self, delta: i32) -> Result<()> {
+        let ids = self.selected_ids();
+        self.update_tasks(&ids, "on_task_update", |task| {
+            let priority = (task.priority + delta).clamp(1, 5);
+            let changed = priority != task.priority;
+            task.priority = priority;
+            changed
+        })?;
+        Ok(())
+    }
+
+    pub fn increase_priority(&mut self) -> Result<()> {
+        self.shift_priority(1)
+    }
+
+    pub fn decrease_priority(&mut self) -> Result<()> {
+        self.shift_priority(-1)
+    }
+
+    pub fn set_project(&mut self, project: Option<String>) -> Result<usize> {
+        let ids = self.selected_ids();
+        self.update_tasks(&ids, "on_task_update", |task| {
+            let changed = task.project != project;
+            task.project = project.clone();
+            changed
+        })
+    }
+
+    /// Edits tags from a spec like `+work -later urgent`: `-tag` removes,
+    /// `+tag` or a bare name adds.
+    pub fn edit_tags(&mut self, spec: &str) -> Result<usize> {
+        let mut add = Vec::new();
+        let mut remove = Vec::new();
+        for word in spec.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(tag) if !tag.is_empty() => remove.push(tag.to_string()),
+                _ => {
+                    let tag = word.trim_start_matches('+');
+                    if !tag.is_empty() {
+                        add.push(tag.to_string());
+                    }
+                }
+            }
+        }
+
+        let ids = self.selected_ids();
+        self.update_tasks(&ids, "on_task_update", |task| {
+            let before = task.tags.clone();
+            task.tags.retain(|t| !remove.contains(t));
+            for tag in &add {
+                if !task.tags.contains(tag) {
+                    task.tags.push(tag.clone());
+                }
+            }
+            task.tags != before
+        })
+    }
+
+    /// Sets the due date from a date expression (see `parse_date`); an empty
+    /// value or `none` clears it.
+    pub fn set_due(&mut self, value: &str) -> Result<usize> {
+        let value = value.trim();
+        let due = match value {
+            "" | "none" => None,
+            _ => Some(
+                parse_date(value, Utc::now())
+                    .ok_or_else(|| TaskVimError::Validation(format!("Invalid date: {}", value)))?,
+            ),
+        };
+        let ids = self.selected_ids();
+        self.update_tasks(&ids, "on_task_update", |task| {
+            let changed = task.due_date != due;
+            task.due_date = due;
+            changed
+        })
+    }
+
+    /// `:modify`: applies a spec such as `project=Work +tag due:tomorrow` to
+    /// the targets as one undo step. The whole spec is validated first.
+    pub fn modify_selection(&mut self, spec: &str) -> Result<usize> {
+        let mods = parse_modifications(spec, Utc::now())?;
+        let hook = if mods.iter().any(|m| matches!(m, Modification::Status(_))) {
+            "on_status_change"
+        } else {
+            "on_task_update"
+        };
+        let ids = self.selected_ids();
+        let count = self.update_tasks(&ids, hook, |task| {
+            let mut changed = false;
+            for modification in &mods {
+                changed |= modification.apply(task);
+            }
+            changed
+        })?;
+        self.info(format!("Modified {} of {} tasks", count, ids.len()));
+        Ok(count)
+    }
+
+    /// Opens the command line prefilled with `prefix`, keeping the current
+    /// targets so the command applies to them.
+    pub fn prompt_command(&mut self, prefix: &str) {
+        let targeted = matches!(self.mode, Mode::Visual | Mode::VisualLine) || !self.marked_tasks.is_empty();
+        self.command_targets = targeted.then(|| self.selected_ids());
+        self.remember_visual();
+        self.selection_anchor = None;
+        self.set_command_line(prefix);
+    }
+
+    /// Runs the task-editing ex commands (`status`, `priority`, `project`,
+    /// `tag`, `due`, `export`). Returns false for anything else.
+    pub fn execute_bulk_command(&mut self, cmd: &str) -> Result<bool> {
+        let (name, arg) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        let arg = arg.trim();
+        match name {
+            "status" => {
+                let status = match arg.to_lowercase().as_str() {
+                    "todo" => TaskStatus::Todo,
+                    "doing" => TaskStatus::Doing,
+                    "done" => TaskStatus::Done,
+                    "archived" => TaskStatus::Archived,
+                    _ => return Err(TaskVimError::Validation(format!("Invalid status: {}", arg))),
+                };
+                self.set_status(status)?;
+            }
+            "priority" => {
+                let priority: i32 = arg
+                    .parse()
+                    .ok()
+                    .filter(|p| (1..=5).contains(p))
+                    .ok_or_else(|| TaskVimError::Validation(format!("Invalid priority: {}", arg)))?;
+                let ids = self.selected_ids();
+                self.update_tasks(&ids, "on_task_update", |task| {
+                    let changed = task.priority != priority;
+                    task.priority = priority;
+                    changed
+                })?;
+            }
+            "project" => {
+                self.set_project((!arg.is_empty()).then(|| arg.to_string()))?;
+            }
+            "tag" | "tags" => {
+                self.edit_tags(arg)?;
+            }
+            "due" => {
+                self.set_due(arg)?;
+            }
+            "export" if !arg.is_empty() => {
+                self.export_file(arg)?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,111 @@
+use crate::error::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Backs the `+` register.
+pub trait Clipboard {
+    fn set_text(&mut self, text: &str) -> Result<()>;
+    fn get_text(&mut self) -> Result<Option<String>>;
+
+    /// An input event that arrived while waiting on the terminal and still
+    /// has to be handled, oldest first.
+    fn take_pending_event(&mut self) -> Option<Event> {
+        None
+    }
+}
+
+/// Talks to the terminal's clipboard with OSC 52 escape sequences, which
+/// terminals forward to the local clipboard even over SSH.
+///
+/// Many terminals only allow writes. When a read query goes unanswered the
+/// last text written in this session is returned instead. Keys typed while
+/// waiting for the reply are kept for the event loop.
+#[derive(Debug, Default)]
+pub struct Osc52Clipboard {
+    last_written: Option<String>,
+    pending: VecDeque<Event>,
+}
+
+const READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+impl Clipboard for Osc52Clipboard {
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(osc52_copy_sequence(text).as_bytes())?;
+        stdout.flush()?;
+        self.last_written = Some(text.to_string());
+        Ok(())
+    }
+
+    fn get_text(&mut self) -> Result<Option<String>> {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(b"\x1b]52;c;?\x07")?;
+        stdout.flush()?;
+
+        // crossterm does not understand OSC replies and hands them over as
+        // key events: Alt+] starts the reply, then plain characters, ending
+        // with BEL (Ctrl+G) or ST (Alt+\).
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let mut reply: Option<String> = None;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if !event::poll(remaining)? {
+                break;
+            }
+            let event = event::read()?;
+            let Event::Key(key) = event else {
+                self.pending.push_back(event);
+                continue;
+            };
+            match (key.code, key.modifiers.contains(KeyModifiers::ALT), &mut reply) {
+                (KeyCode::Char(']'), true, None) => reply = Some(String::from("\x1b]")),
+                (KeyCode::Char('g'), _, Some(_)) if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                (KeyCode::Char('\\'), true, Some(_)) => break,
+                (KeyCode::Char(c), _, Some(buf)) => buf.push(c),
+                (_, _, None) => self.pending.push_back(Event::Key(key)),
+                _ => {}
+            }
+        }
+
+        let text = reply.and_then(|r| parse_osc52_reply(&r));
+        Ok(text.or_else(|| self.last_written.clone()))
+    }
+
+    fn take_pending_event(&mut self) -> Option<Event> {
+        self.pending.pop_front()
+    }
+}
+
+/// An in-process clipboard, used when stdout is not a terminal (and in tests).
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    pub text: Option<String>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        self.text = Some(text.to_string());
+        Ok(())
+    }
+
+    fn get_text(&mut self) -> Result<Option<String>> {
+        Ok(self.text.clone())
+    }
+}
+
+pub fn osc52_copy_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+/// Extracts the text from an `ESC ] 52 ; <sel> ; <base64>` reply (the
+/// terminator already stripped or not).
+pub fn parse_osc52_reply(reply: &str) -> Option<String> {
+    let body = reply.strip_prefix("\x1b]52;")?;
+    let (_, data) = body.split_once(';')?;
+    let data = data.trim_end_matches(['\x07', '\\']).trim_end_matches('\x1b');
+    let bytes = STANDARD.decode(data).ok()?;
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,346 @@
+use crate::core::commands::EX_COMMANDS;
+use crate::core::state::{AppState, Mode};
+use crate::domain::query::{FIELDS, OPERATORS};
+use crate::error::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// How many ex commands the history keeps.
+pub const COMMAND_HISTORY_LIMIT: usize = 200;
+
+const STATUSES: [&str; 4] = ["todo", "doing", "done", "archived"];
+
+const MODIFY_FIELDS: [&str; 8] = ["title", "description", "status", "priority", "project", "tags", "due", "recur"];
+
+/// An active `Tab` completion: the candidates for the word starting at char
+/// `start`, and which one is on the command line (`None` for what was typed).
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub start: usize,
+    pub typed: String,
+    pub candidates: Vec<String>,
+    pub index: Option<usize>,
+}
+
+impl Completion {
+    fn current(&self) -> &str {
+        self.index.map(|i| self.candidates[i].as_str()).unwrap_or(&self.typed)
+    }
+}
+
+fn byte_index(s: &str, char_idx: usize) -> usize {
+    s.char_indices().nth(char_idx).map(|(i, _)| i).unwrap_or(s.len())
+}
+
+/// Chars of a leading range such as `1,5` or `'<,'>`.
+fn is_range_char(c: char) -> bool {
+    c.is_ascii_digit() || ".,$%'<>+-".contains(c)
+}
+
+impl AppState {
+    /// Opens the command line with `text` and the cursor at its end.
+    pub fn set_command_line(&mut self, text: &str) {
+        self.mode = Mode::Command;
+        self.command_buffer = text.to_string();
+        self.command_cursor = text.chars().count();
+        self.completion = None;
+        self.history_index = None;
+    }
+
+    /// Handles the editing keys of the command line. Returns false for keys
+    /// it does not handle.
+    pub fn edit_command_line(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let len = self.command_buffer.chars().count();
+        let cursor = self.command_cursor.min(len);
+        match key.code {
+            KeyCode::Left if ctrl => self.command_cursor = self.word_start_before(cursor),
+            KeyCode::Right if ctrl => self.command_cursor = self.word_end_after(cursor),
+            KeyCode::Left => self.command_cursor = cursor.saturating_sub(1),
+            KeyCode::Right => self.command_cursor = (cursor + 1).min(len),
+            KeyCode::Home => self.command_cursor = 0,
+            KeyCode::Char('b') if ctrl => self.command_cursor = 0,
+            KeyCode::End => self.command_cursor = len,
+            KeyCode::Char('e') if ctrl => self.command_cursor = len,
+            KeyCode::Char('w') if ctrl => {
+                let start = self.word_start_before(cursor);
+                self.delete_command_chars(start, cursor);
+            }
+            KeyCode::Char('u') if ctrl => self.delete_command_chars(0, cursor),
+            KeyCode::Backspace if cursor > 0 => self.delete_command_chars(cursor - 1, cursor),
+            KeyCode::Char('h') if ctrl && cursor > 0 => self.delete_command_chars(cursor - 1, cursor),
+            KeyCode::Delete if cursor < len => self.delete_command_chars(cursor, cursor + 1),
+            KeyCode::Char(c) if !ctrl => {
+                let at = byte_index(&self.command_buffer, cursor);
+                self.command_buffer.insert(at, c);
+                self.command_cursor = cursor + 1;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn delete_command_chars(&mut self, from: usize, to: usize) {
+        let (from_byte, to_byte) = (byte_index(&self.command_buffer, from), byte_index(&self.command_buffer, to));
+        self.command_buffer.replace_range(from_byte..to_byte, "");
+        self.command_cursor = from;
+    }
+
+    /// Where `Ctrl-w` deletes back to: the start of the word before the
+    /// cursor, skipping the spaces in between.
+    fn word_start_before(&self, cursor: usize) -> usize {
+        let chars: Vec<char> = self.command_buffer.chars().collect();
+        let mut i = cursor;
+        while i > 0 && chars[i - 1] == ' ' {
+            i -= 1;
+        }
+        while i > 0 && chars[i - 1] != ' ' {
+            i -= 1;
+        }
+        i
+    }
+
+    fn word_end_after(&self, cursor: usize) -> usize {
+        let chars: Vec<char> = self.command_buffer.chars().collect();
+        let mut i = cursor;
+        while i < chars.len() && chars[i] == ' ' {
+            i += 1;
+        }
+        while i < chars.len() && chars[i] != ' ' {
+            i += 1;
+        }
+        i
+    }
+
+    pub fn load_command_history(&mut self) -> Result<()> {
+        self.command_history = self.storage.load_command_history()?;
+        Ok(())
+    }
+
+    /// Adds `cmd` to the end of the history, moving an earlier copy.
+    pub fn push_command_history(&mut self, cmd: &str) -> Result<()> {
+        let cmd = cmd.trim();
+        if cmd.is_empty() {
+            return Ok(());
+        }
+        self.command_history.retain(|c| c != cmd);
+        self.command_history.push(cmd.to_string());
+        let excess = self.command_history.len().saturating_sub(COMMAND_HISTORY_LIMIT);
+        self.command_history.drain(..excess);
+        self.storage.add_command_history(cmd, COMMAND_HISTORY_LIMIT)
+    }
+
+    /// `Up` (and `Down` with `older` false): recalls the previous (next)
+    /// history entry starting with what was typed before the first recall.
+    pub fn recall_command_history(&mut self, older: bool) {
+        let current = match self.history_index {
+            Some(i) => i,
+            None => {
+                self.history_prefix = self.command_buffer.clone();
+                self.command_history.len()
+            }
+        };
+        let prefix = self.history_prefix.clone();
+        let found = if older {
+            (0..current).rev().find(|&i| self.command_history[i].starts_with(&prefix))
+        } else {
+            (current + 1..self.command_history.len()).find(|&i| self.command_history[i].starts_with(&prefix))
+        };
+        let line = match found {
+            Some(i) => {
+                self.history_index = Some(i);
+                self.command_history[i].clone()
+            }
+            // Nothing older: stay put. Nothing newer: back to what was typed.
+            None if older => return,
+            None => {
+                self.history_index = None;
+                prefix
+            }
+        };
+        let index = self.history_index;
+        self.set_command_line(&line);
+        self.history_index = index;
+    }
+
+    /// `Tab` (and `Shift-Tab` with `forward` false): completes the word
+    /// before the cursor, cycling through the candidates on repeated presses
+    /// and back to what was typed.
+    pub fn complete_command_line(&mut self, forward: bool) -> Result<()> {
+        let mut completion = match self.completion.take() {
+            Some(completion) => completion,
+            None => {
+                let cursor = self.command_cursor.min(self.command_buffer.chars().count());
+                let before: String = self.command_buffer.chars().take(cursor).collect();
+                let range = before.chars().take_while(|&c| is_range_char(c)).count();
+                let start = before.rfind(' ').map(|i| before[..i].chars().count() + 1).unwrap_or(0).max(range);
+                let typed: String = before.chars().skip(start).collect();
+                let candidates = self.completion_candidates(&before, &typed)?;
+                if candidates.is_empty() {
+                    return Ok(());
+                }
+                Completion { start, typed, candidates, index: None }
+            }
+        };
+
+        let replaced = completion.current().chars().count();
+        let n = completion.candidates.len();
+        completion.index = match (completion.index, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(n - 1),
+            (Some(i), true) if i + 1 < n => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+        let text = completion.current().to_string();
+        let from = byte_index(&self.command_buffer, completion.start);
+        let to = byte_index(&self.command_buffer, completion.start + replaced);
+        self.command_buffer.replace_range(from..to, &text);
+        self.command_cursor = completion.start + text.chars().count();
+        // A single candidate is final; the next Tab completes from there
+        if n > 1 {
+            self.completion = Some(completion);
+        }
+        Ok(())
+    }
+
+    /// Candidates for `word`, the last word of `line` (the command line up
+    /// to the cursor).
+    fn completion_candidates(&self, line: &str, word: &str) -> Result<Vec<String>> {
+        let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let line = line.trim_start_matches(is_range_char);
+        let command = line.split_whitespace().next().unwrap_or("");
+        let candidates = if !line.contains(' ') {
+            let mut names: Vec<String> = EX_COMMANDS
+                .iter()
+                .map(|(cmd, _)| cmd.split_whitespace().next().unwrap_or("").to_string())
+                .chain(self.config.commands.keys().cloned())
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        } else {
+            match command {
+                "sort" => owned(&["priority", "created", "position"]),
+                "view" => self.config.view_columns.keys().cloned().collect(),
+                "find" => owned(&["tasks", "projects", "tags", "views", "commands"]),
+                "report" => owned(&["project", "tag", "day", "task"]),
+                "pomodoro" => owned(&["start", "stop", "skip"]),
+                "trash" => owned(&["empty"]),
+                "export" | "import" => owned(&["json", "taskwarrior", "todotxt", "md"]),
+                "status" => owned(&STATUSES),
+                "priority" => owned(&["1", "2", "3", "4", "5"]),
+                "due" => owned(&["today", "tomorrow", "yesterday", "none"]),
+                "project" => self.projects()?,
+                "tag" | "tags" => {
+                    let sign = word.chars().next().filter(|c| matches!(c, '+' | '-'));
+                    let sign = sign.map(String::from).unwrap_or_default();
+                    self.tags()?.into_iter().map(|t| format!("{}{}", sign, t)).collect()
+                }
+                "filter" => self.filter_candidates(word)?,
+                "modify" | "mod" | "set" => self.modify_candidates(word)?,
+                _ => Vec::new(),
+            }
+        };
+        let lower = word.to_lowercase();
+        let mut candidates: Vec<String> = candidates.into_iter().filter(|c| c.to_lowercase().starts_with(&lower)).collect();
+        candidates.sort();
+        candidates.dedup();
+        Ok(candidates)
+    }
+
+    /// Filter words complete to a field, then `field` plus each operator,
+    /// then values for the fields with a known set of them.
+    fn filter_candidates(&self, word: &str) -> Result<Vec<String>> {
+        if let Some(op) = OPERATORS.iter().find(|op| word.contains(*op)) {
+            let (field, _) = word.split_once(op).unwrap_or((word, ""));
+            let values = match field {
+                "status" => STATUSES.iter().map(|s| s.to_string()).collect(),
+                "priority" => (1..=5).map(|p| p.to_string()).collect(),
+                "project" => self.projects()?,
+                "tag" => self.tags()?,
+                _ => Vec::new(),
+            };
+            return Ok(values.into_iter().map(|v| format!("{}{}{}", field, op, v)).collect());
+        }
+        if FIELDS.contains(&word) {
+            return Ok(OPERATORS.iter().map(|op| format!("{}{}", word, op)).collect());
+        }
+        Ok(FIELDS.iter().map(|f| f.to_string()).collect())
+    }
+
+    /// `:modify` words complete to `field=`, then to values, and `+`/`-`
+    /// words to tags.
+    fn modify_candidates(&self, word: &str) -> Result<Vec<String>> {
+        if let Some(sign @ ('+' | '-')) = word.chars().next() {
+            return Ok(self.tags()?.into_iter().map(|t| format!("{}{}", sign, t)).collect());
+        }
+        let Some(split) = word.find(['=', ':']) else {
+            return Ok(MODIFY_FIELDS.iter().map(|f| format!("{}=", f)).collect());
+        };
+        let (field, sep) = (&word[..split], &word[split..split + 1]);
+        let values = match field {
+            "status" => STATUSES.iter().map(|s| s.to_string()).collect(),
+            "priority" => (1..=5).map(|p| p.to_string()).collect(),
+            "project" => self.projects()?,
+            "due" => ["today", "tomorrow", "none"].iter().map(|s| s.to_string()).collect(),
+            "recur" => ["daily", "weekdays", "weekly", "monthly", "yearly", "none"].iter().map(|s| s.to_string()).collect(),
+            _ => Vec::new(),
+        };
+        Ok(values.into_iter().map(|v| format!("{}{}{}", field, sep, v)).collect())
+    }
+
+    fn projects(&self) -> Result<Vec<String>> {
+        Ok(self.storage.project_counts(true)?.into_iter().map(|(p, _)| p).collect())
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        Ok(self.storage.tag_counts(true)?.into_iter().map(|(t, _)| t).collect())
+    }
+
+    /// Runs a command line typed or picked by the user and records it in the
+    /// history.
+    pub fn run_command_line(&mut self, cmd: &str) -> Result<()> {
+        self.push_command_history(cmd)?;
+        self.mode = self.command_line_return_mode();
+        self.execute_command(cmd)
+    }
+
+    /// The mode leaving the command line returns to: the entries view when
+    /// `e` or `a` there opened it, Normal otherwise.
+    pub fn command_line_return_mode(&self) -> Mode {
+        if self.time_entries_view.is_some() {
+            Mode::TimeEntries
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// `q:` and `Ctrl-f` on the command line: lists the command history,
+    /// newest at the bottom.
+    pub fn open_command_window(&mut self) {
+        self.command_window_index = self.command_history.len().saturating_sub(1);
+        self.mode = Mode::CommandWindow;
+    }
+
+    pub fn handle_command_window_key(&mut self, key: KeyEvent) -> Result<()> {
+        let last = self.command_history.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.mode = Mode::Normal,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.mode = Mode::Normal,
+            KeyCode::Char('j') | KeyCode::Down => self.command_window_index = (self.command_window_index + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.command_window_index = self.command_window_index.saturating_sub(1),
+            KeyCode::Char('G') => self.command_window_index = last,
+            KeyCode::Char('g') => self.command_window_index = 0,
+            KeyCode::Enter => match self.command_history.get(self.command_window_index).cloned() {
+                Some(cmd) => self.run_command_line(&cmd)?,
+                None => self.mode = Mode::Normal,
+            },
+            KeyCode::Char('i' | 'e') => {
+                let cmd = self.command_history.get(self.command_window_index).cloned().unwrap_or_default();
+                self.set_command_line(&cmd);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+/// The built-in ex commands with a short description, as offered by the
+/// command palette. A trailing space means the command takes an argument.
+pub const EX_COMMANDS: &[(&str, &str)] = &[
+    ("w", "Save changes"),
+    ("q", "Quit"),
+    ("wq", "Save and quit"),
+    ("sort priority", "Sort tasks by priority"),
+    ("sort created", "Sort tasks by creation date"),
+    ("sort position", "Sort tasks by manual position"),
+    ("stats", "Show statistics"),
+    ("filter ", "Filter tasks, e.g. status=todo priority>=3"),
+    ("filter", "Clear the filter"),
+    ("view ", "Switch to a column view"),
+    ("view", "Switch back to the default columns"),
+    ("noh", "Clear search highlighting"),
+    ("find ", "Fuzzy-find tasks, projects, tags, views or commands"),
+    ("status ", "Set the status of the selection"),
+    ("priority ", "Set the priority of the selection"),
+    ("project ", "Set the project of the selection"),
+    ("tag ", "Add (+tag) or remove (-tag) tags on the selection"),
+    ("due ", "Set the due date of the selection"),
+    ("modify ", "Change fields of the selection, e.g. project=Work +tag due:tomorrow recur:weekly"),
+    ("set ", "Same as modify"),
+    ("done", "Mark the selection done"),
+    ("doing", "Mark the selection in progress"),
+    ("todo", "Mark the selection to do"),
+    ("archive", "Archive the selection"),
+    ("archived", "Browse and restore archived tasks"),
+    ("gc", "Archive old Done tasks and purge old Archived ones"),
+    ("delete", "Move the selection to the trash"),
+    ("trash", "Browse, restore and purge deleted tasks"),
+    ("trash ", "empty: purge every deleted task"),
+    ("export ", "Export the database (or the selection) as json, taskwarrior, todotxt or md"),
+    ("import ", "Merge a json, taskwarrior, todotxt or md file into the database"),
+    ("messages", "Show the message history"),
+    ("info", "Show the selected task with its status timeline"),
+    ("start", "Start the timer on the selected task"),
+    ("stop", "Stop the running timer"),
+    ("log ", "Log time spent on the selected task, e.g. 1h30m"),
+    ("entries", "List the time entries of the selected task"),
+    ("report", "Show time spent by project"),
+    ("report ", "Show time spent by project, tag, day or task"),
+    ("pomodoro", "Start a pomodoro on the selected task"),
+    ("pomodoro ", "Start, stop or skip ahead in a pomodoro"),
+    ("lua ", "Run Lua code"),
+];
@@ -0,0 +1,33 @@
+use crate::core::state::{AppState, Mode};
+use crate::error::{Result, TaskVimError};
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl AppState {
+    /// `Tab` / `:info`: shows every field of the selected task with its
+    /// status timeline.
+    pub fn open_detail(&mut self) -> Result<()> {
+        let id = self
+            .selected_ids()
+            .first()
+            .copied()
+            .ok_or_else(|| TaskVimError::Validation("No task selected".to_string()))?;
+        self.detail_task = Some(id);
+        self.detail_scroll = 0;
+        self.mode = Mode::Detail;
+        Ok(())
+    }
+
+    pub fn handle_detail_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.detail_scroll += 1,
+            KeyCode::Char('k') | KeyCode::Up => self.detail_scroll = self.detail_scroll.saturating_sub(1),
+            KeyCode::Char('g') => self.detail_scroll = 0,
+            KeyCode::Esc | KeyCode::Tab | KeyCode::Char('q') => {
+                self.detail_task = None;
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
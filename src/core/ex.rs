@@ -0,0 +1,407 @@
+use crate::core::picker::PickerSource;
+use crate::core::repeat::Change;
+use crate::core::search::Search;
+use crate::core::state::{AppState, SortBy};
+use crate::error::{Result, TaskVimError};
+use uuid::Uuid;
+
+/// What a line address counts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressBase {
+    /// `.`, or a bare `+N` / `-N`.
+    Current,
+    /// `$`
+    Last,
+    /// A 1-based line number.
+    Line(usize),
+    /// `'<`: the first task of the last Visual selection.
+    VisualStart,
+    /// `'>`: the last task of the last Visual selection.
+    VisualEnd,
+}
+
+/// A line of the displayed task list, e.g. `.+2` or `$-1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub base: AddressBase,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    /// `%`: every displayed task.
+    All,
+    /// `a,b`, or a single address for both ends.
+    Lines(Address, Address),
+}
+
+/// `:g/pattern/ command` (or `:v`, `:g!` with `invert`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    pub pattern: String,
+    pub invert: bool,
+    pub command: String,
+}
+
+/// A parsed command line: `[range] name [args]` or `[range] g/pattern/ command`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExCommand {
+    pub range: Option<Range>,
+    pub name: String,
+    pub args: String,
+    pub global: Option<Global>,
+}
+
+/// Commands that act on the tasks of a range; the rest refuse one.
+const RANGE_COMMANDS: &[&str] = &[
+    "status", "priority", "project", "tag", "tags", "due", "export", "set", "modify", "mod", "done", "doing", "todo",
+    "archive", "d", "delete", "mark",
+];
+
+fn invalid(msg: impl Into<String>) -> TaskVimError {
+    TaskVimError::Validation(msg.into())
+}
+
+struct Cursor<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    input: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { chars: input.chars().collect(), pos: 0, input }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    fn address(&mut self) -> Result<Option<Address>> {
+        let base = match self.peek() {
+            Some('.') => {
+                self.pos += 1;
+                Some(AddressBase::Current)
+            }
+            Some('$') => {
+                self.pos += 1;
+                Some(AddressBase::Last)
+            }
+            Some(c) if c.is_ascii_digit() => self.number().map(AddressBase::Line),
+            Some('\'') => {
+                self.pos += 1;
+                match self.peek() {
+                    Some('<') => {
+                        self.pos += 1;
+                        Some(AddressBase::VisualStart)
+                    }
+                    Some('>') => {
+                        self.pos += 1;
+                        Some(AddressBase::VisualEnd)
+                    }
+                    _ => return Err(invalid(format!("Unknown mark in: {}", self.input))),
+                }
+            }
+            _ => None,
+        };
+
+        let mut offset = 0;
+        let mut has_offset = false;
+        while let Some(sign @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            let n = self.number().unwrap_or(1) as i64;
+            offset += if sign == '+' { n } else { -n };
+            has_offset = true;
+        }
+        Ok(match (base, has_offset) {
+            (Some(base), _) => Some(Address { base, offset }),
+            (None, true) => Some(Address { base: AddressBase::Current, offset }),
+            (None, false) => None,
+        })
+    }
+
+    fn range(&mut self) -> Result<Option<Range>> {
+        if self.eat('%') {
+            return Ok(Some(Range::All));
+        }
+        let first = self.address()?;
+        if self.eat(',') {
+            let current = Address { base: AddressBase::Current, offset: 0 };
+            let second = self.address()?;
+            return Ok(Some(Range::Lines(first.unwrap_or(current), second.unwrap_or(current))));
+        }
+        Ok(first.map(|a| Range::Lines(a, a)))
+    }
+
+    /// The pattern of `:g` up to the closing `delimiter`; `\delimiter`
+    /// stands for the delimiter itself.
+    fn pattern(&mut self, delimiter: char) -> String {
+        let mut pattern = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == delimiter {
+                break;
+            }
+            if c == '\\' && self.peek() == Some(delimiter) {
+                self.pos += 1;
+                pattern.push(delimiter);
+            } else {
+                pattern.push(c);
+            }
+        }
+        pattern
+    }
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut cursor = Cursor::new(input.trim_start());
+        cursor.skip_spaces();
+        let range = cursor.range()?;
+        cursor.skip_spaces();
+
+        let start = cursor.pos;
+        if cursor.peek().map(|c| c.is_ascii_alphabetic()).unwrap_or(false) {
+            while cursor.peek().map(|c| c.is_ascii_alphanumeric() || c == '_').unwrap_or(false) {
+                cursor.pos += 1;
+            }
+        }
+        let name: String = cursor.chars[start..cursor.pos].iter().collect();
+
+        if matches!(name.as_str(), "g" | "global" | "v" | "vglobal") {
+            let bang = cursor.eat('!');
+            let invert = name.starts_with('v') || bang;
+            let delimiter = cursor
+                .peek()
+                .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '"')
+                .ok_or_else(|| invalid(format!("Missing pattern delimiter: {}", input)))?;
+            cursor.pos += 1;
+            let pattern = cursor.pattern(delimiter);
+            let command = cursor.rest().trim().to_string();
+            return Ok(Self { range, name, args: String::new(), global: Some(Global { pattern, invert, command }) });
+        }
+
+        let args = cursor.rest().trim().to_string();
+        if name.is_empty() && !args.is_empty() {
+            return Err(invalid(format!("Not a command: {}", args)));
+        }
+        Ok(Self { range, name, args, global: None })
+    }
+}
+
+impl AppState {
+    /// Runs an ex command line. A range or `:g` makes the command act on
+    /// those tasks of the displayed list instead of the selection.
+    pub fn execute_command(&mut self, cmd: &str) -> Result<()> {
+        let result = ExCommand::parse(cmd).and_then(|ex| self.run_ex(&ex));
+        self.command_targets = None;
+        result
+    }
+
+    fn run_ex(&mut self, ex: &ExCommand) -> Result<()> {
+        let lines = match ex.range {
+            Some(range) => Some(self.resolve_range(range)?),
+            None => None,
+        };
+
+        if let Some(global) = &ex.global {
+            return self.run_global(global, lines);
+        }
+
+        if let Some(lines) = lines {
+            if ex.name.is_empty() {
+                // `:5` jumps to the fifth task
+                if let Some(&last) = lines.last() {
+                    self.selected_index = last;
+                }
+                return Ok(());
+            }
+            if !RANGE_COMMANDS.contains(&ex.name.as_str()) {
+                return Err(invalid(format!("No range allowed: {}", ex.name)));
+            }
+            self.command_targets = Some(lines.iter().map(|&i| self.tasks[i].id).collect());
+        }
+
+        self.run_named(&ex.name, &ex.args, ex.range.is_none())
+    }
+
+    /// Runs command `name`. Task edits are remembered for `.` when
+    /// `repeatable`, i.e. when they act on the selection.
+    fn run_named(&mut self, name: &str, args: &str, repeatable: bool) -> Result<()> {
+        if name == "archive" && !args.is_empty() {
+            return self.archive_command(args);
+        }
+        if self.run_task_command(name, args)? {
+            if name != "export" && name != "mark" && repeatable {
+                let line = format!("{} {}", name, args);
+                self.record_change(Change::Command(line.trim().to_string()));
+            }
+            return Ok(());
+        }
+
+        match name {
+            "" => {}
+            "q" | "quit" | "wq" | "x" => self.running = false,
+            "w" | "write" => {
+                // Already persisted on every change for now, but could be batched later
+                self.info(format!("{} tasks saved", self.storage.get_tasks(None)?.len()));
+            }
+            "sort" => {
+                self.sort_by = match args {
+                    "priority" => SortBy::Priority,
+                    "created" => SortBy::CreatedAt,
+                    "position" | "" => SortBy::Position,
+                    _ => return Err(invalid(format!("Invalid sort: {}", args))),
+                };
+                self.reload_tasks()?;
+            }
+            "stats" => self.open_stats()?,
+            "noh" | "nohlsearch" => self.clear_search_highlight(),
+            "lua" => {
+                let result = self.lua_config.run_code(args);
+                self.reload_config();
+                self.refresh_sidebar()?;
+                result?;
+            }
+            "filter" => {
+                self.filter_string = (!args.is_empty()).then(|| args.to_string());
+                self.reload_tasks()?;
+            }
+            "view" => self.view = (!args.is_empty()).then(|| args.to_string()),
+            "find" => {
+                let source = PickerSource::from_name(args).ok_or_else(|| invalid(format!("Nothing to find: {}", args)))?;
+                self.open_picker(source)?;
+            }
+            "messages" | "mes" => self.show_messages(args),
+            "start" => self.start_timer()?,
+            "stop" => self.stop_timer()?,
+            "log" => self.log_time(args)?,
+            "entries" | "time" => self.open_time_entries()?,
+            "retime" => self.retime_entry(args)?,
+            "report" => self.open_report(args)?,
+            "pomodoro" | "pomo" => self.pomodoro_command(args)?,
+            "info" => self.open_detail()?,
+            "gc" => self.run_gc()?,
+            "archived" => self.open_archive()?,
+            "trash" => self.trash_command(args)?,
+            "import" if !args.is_empty() => self.import_file(args)?,
+            _ if self.config.commands.contains_key(name) => {
+                self.lua_config.run_command(name, args)?;
+                self.reload_config();
+                self.reload_tasks()?;
+            }
+            _ => return Err(invalid(format!("Not a command: {}", name))),
+        }
+        Ok(())
+    }
+
+    /// The commands that edit the target tasks. Returns false for anything
+    /// else.
+    fn run_task_command(&mut self, name: &str, args: &str) -> Result<bool> {
+        match name {
+            "done" | "doing" | "todo" => self.execute_bulk_command(&format!("status {}", name)),
+            "archive" => self.execute_bulk_command("status archived"),
+            "d" | "delete" => {
+                self.delete_selected_task()?;
+                Ok(true)
+            }
+            "mark" => {
+                let ids = self.selected_ids();
+                self.marked_tasks.extend(ids);
+                self.end_visual();
+                Ok(true)
+            }
+            "set" | "modify" | "mod" => {
+                self.modify_selection(args)?;
+                Ok(true)
+            }
+            _ => self.execute_bulk_command(format!("{} {}", name, args).trim()),
+        }
+    }
+
+    /// `:g/pattern/ command` runs the command once over every task of the
+    /// range (all tasks by default) matching the pattern; `:v` and `:g!` over
+    /// those that do not match. Without a command the tasks are marked.
+    fn run_global(&mut self, global: &Global, lines: Option<Vec<usize>>) -> Result<()> {
+        let pattern = match (global.pattern.as_str(), &self.search) {
+            ("", Some(search)) => search.pattern.clone(),
+            (pattern, _) => pattern.to_string(),
+        };
+        let search = Search::new(&pattern, false).ok_or_else(|| invalid("No previous pattern"))?;
+        let lines = lines.unwrap_or_else(|| (0..self.tasks.len()).collect());
+        let ids: Vec<Uuid> = lines
+            .into_iter()
+            .map(|i| &self.tasks[i])
+            .filter(|t| search.matches(t) != global.invert)
+            .map(|t| t.id)
+            .collect();
+        if ids.is_empty() {
+            return Err(invalid(format!("Pattern not found: {}", pattern)));
+        }
+
+        let command = if global.command.is_empty() { "mark" } else { global.command.as_str() };
+        let sub = ExCommand::parse(command)?;
+        if sub.range.is_some() || sub.global.is_some() {
+            return Err(invalid(format!("Cannot nest a range or :g in :g: {}", command)));
+        }
+        self.command_targets = Some(ids);
+        self.run_named(&sub.name, &sub.args, false)
+    }
+
+    /// The displayed task indexes covered by `range`. A backwards range is
+    /// swapped, like Vim.
+    pub fn resolve_range(&self, range: Range) -> Result<Vec<usize>> {
+        let (first, last) = match range {
+            Range::All => return Ok((0..self.tasks.len()).collect()),
+            Range::Lines(a, b) => (self.resolve_address(a)?, self.resolve_address(b)?),
+        };
+        Ok((first.min(last)..=first.max(last)).collect())
+    }
+
+    fn resolve_address(&self, address: Address) -> Result<usize> {
+        let base = match address.base {
+            AddressBase::Current => self.selected_index as i64,
+            AddressBase::Last => self.tasks.len() as i64 - 1,
+            AddressBase::Line(n) => n.max(1) as i64 - 1,
+            AddressBase::VisualStart | AddressBase::VisualEnd => {
+                let (start, end) = self.last_visual.ok_or_else(|| invalid("Mark not set: '<"))?;
+                let id = if address.base == AddressBase::VisualStart { start } else { end };
+                self.tasks
+                    .iter()
+                    .position(|t| t.id == id)
+                    .ok_or_else(|| invalid("Mark not set: the task is not displayed"))? as i64
+            }
+        };
+        let line = base + address.offset;
+        if line < 0 || line >= self.tasks.len() as i64 {
+            return Err(invalid("Invalid range"));
+        }
+        Ok(line as usize)
+    }
+}
@@ -0,0 +1,72 @@
+/// Scores `candidate` against a fuzzy `pattern`: every pattern character
+/// must appear in order. Returns the score (higher is better) and the char
+/// indices of the matched characters, or `None` when it does not match.
+///
+/// Matching is smart-case. Consecutive matches and matches at the start of
+/// a word score higher, gaps and long candidates score lower.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let case_sensitive = pattern.iter().any(|c| c.is_uppercase());
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let chars: Vec<char> = candidate.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    let pattern: Vec<char> = pattern.into_iter().map(fold).collect();
+
+    // Forward pass finds where the first complete match ends...
+    let mut p = 0;
+    let mut end = None;
+    for (i, &c) in folded.iter().enumerate() {
+        if c == pattern[p] {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // ...then a backward pass from there finds the tightest window
+    let mut start = end;
+    let mut p = pattern.len();
+    for i in (0..=end).rev() {
+        if folded[i] == pattern[p - 1] {
+            p -= 1;
+            if p == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut p = 0;
+    for (i, &c) in folded.iter().enumerate().take(end + 1).skip(start) {
+        if p < pattern.len() && c == pattern[p] {
+            positions.push(i);
+            p += 1;
+        }
+    }
+
+    let mut score: i64 = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        score += 16;
+        if n > 0 && positions[n - 1] + 1 == i {
+            score += 8;
+        }
+        let word_start = i == 0
+            || !chars[i - 1].is_alphanumeric()
+            || (chars[i - 1].is_lowercase() && chars[i].is_uppercase());
+        if word_start {
+            score += 10;
+        }
+    }
+    let gaps = (end - start + 1 - positions.len()) as i64;
+    score -= gaps;
+    score -= start as i64 / 4;
+    score -= chars.len() as i64 / 8;
+    Some((score, positions))
+}
@@ -0,0 +1,403 @@
+use crate::core::actions::Action;
+use crate::core::keymap::keys_to_notation;
+use crate::core::repeat::Change;
+use crate::core::sidebar::Focus;
+use crate::core::state::{AppState, InsertAction, Mode};
+use crate::error::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Nested `@x` replays stop at this depth, so a macro calling itself ends.
+pub const MAX_MACRO_DEPTH: usize = 100;
+
+impl AppState {
+    /// Runs one key through the modal input pipeline. Keys typed by the user
+    /// and keys replayed from a macro both come through here, so a macro
+    /// behaves exactly like typing it.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        if let (Some(reg), 0) = (self.macro_recording, self.macro_depth) {
+            self.macros.entry(reg).or_default().push(key);
+        }
+        if self.macro_depth == 0 {
+            self.message = None;
+        }
+
+        // `"x` picks the register for the next yank, delete or paste
+        if self.pending_register {
+            self.pending_register = false;
+            if let KeyCode::Char(c) = key.code {
+                self.select_register(c)?;
+            }
+            return Ok(());
+        }
+
+        let operator_mode = matches!(self.mode, Mode::Normal | Mode::Visual | Mode::VisualLine);
+        if operator_mode {
+            if key.code == KeyCode::Char('"') {
+                self.pending_register = true;
+                return Ok(());
+            }
+            if self.handle_pending_key(key)? {
+                return Ok(());
+            }
+            if let KeyCode::Char(c @ '0'..='9') = key.code {
+                if key.modifiers.is_empty() && (c != '0' || self.pending_count.is_some()) {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    self.pending_count = Some(self.pending_count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    return Ok(());
+                }
+            }
+        }
+
+        let sidebar_action = match (self.mode, self.focus) {
+            (Mode::Normal, Focus::Sidebar) => self.config.keymap.get_sidebar_action(key),
+            _ => None,
+        };
+        if let Some(action) = sidebar_action.or_else(|| self.config.keymap.get_action(self.mode, key)) {
+            let count = self.pending_count.take();
+            return self.handle_counted_action(action, count);
+        }
+
+        match self.mode {
+            Mode::Normal | Mode::Visual | Mode::VisualLine => self.handle_prefix_key(key),
+            Mode::Insert => self.handle_insert_key(key),
+            Mode::Search => self.handle_search_key(key),
+            Mode::Filter => self.handle_filter_key(key),
+            Mode::Picker => self.handle_picker_key(key),
+            Mode::Command => self.handle_command_key(key),
+            Mode::CommandWindow => self.handle_command_window_key(key),
+            Mode::Messages => self.handle_messages_key(key),
+            Mode::TimeEntries => self.handle_time_entries_key(key),
+            Mode::Report => self.handle_report_key(key),
+            Mode::Detail => self.handle_detail_key(key),
+            Mode::Archive => self.handle_archive_key(key),
+            Mode::Trash => self.handle_trash_key(key),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs `action`, `count` times for actions that take a count. `5G`
+    /// jumps to the fifth task.
+    pub fn handle_counted_action(&mut self, action: Action, count: Option<usize>) -> Result<()> {
+        match (action, count) {
+            (Action::PlayMacro, _) => {
+                // The count applies to the replay, once the register is known
+                self.pending_count = count;
+                self.pending_at = true;
+            }
+            (Action::RepeatChange, count) => self.repeat_change(count)?,
+            (Action::MoveToBottom, Some(n)) => {
+                self.selected_index = n.saturating_sub(1).min(self.tasks.len().saturating_sub(1));
+            }
+            (Action::Delete, Some(n)) => {
+                self.delete_counted(n)?;
+                self.record_change(Change::Delete);
+                self.set_change_count(n);
+            }
+            (action, Some(n)) if action.takes_count() => {
+                for _ in 0..n {
+                    self.handle_action(action)?;
+                }
+                if action.is_change() {
+                    self.set_change_count(n);
+                }
+            }
+            (action, _) => self.handle_action(action)?,
+        }
+        Ok(())
+    }
+
+    /// Completes a multi-key sequence started by the previous key (`gg`, `g/`,
+    /// `za`, `yy`, `>>`, `qa`, `q:`, `@a`, `Ctrl-w h`). Returns false when no
+    /// sequence was pending.
+    fn handle_pending_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let c = match key.code {
+            KeyCode::Char(c) => Some(c),
+            _ => None,
+        };
+
+        if self.pending_q {
+            self.pending_q = false;
+            match c {
+                Some(':') if self.mode == Mode::Normal => self.open_command_window(),
+                Some(c) if c.is_ascii_alphanumeric() => self.start_recording(c),
+                _ => {}
+            }
+        } else if self.pending_at {
+            self.pending_at = false;
+            let count = self.pending_count.take().unwrap_or(1);
+            if let Some(c) = c {
+                self.play_macro(c, count)?;
+            }
+        } else if self.pending_ctrl_w {
+            self.pending_ctrl_w = false;
+            match key.code {
+                KeyCode::Char('h') | KeyCode::Left => self.handle_action(Action::FocusSidebar)?,
+                KeyCode::Char('l') | KeyCode::Right => self.handle_action(Action::FocusTasks)?,
+                _ => {}
+            }
+        } else if self.pending_g {
+            self.pending_g = false;
+            match c {
+                Some('g') => self.move_to_top(),
+                Some('/') if self.mode == Mode::Normal => self.handle_action(Action::EnterFilterSearch)?,
+                Some('t') if self.mode == Mode::Normal => self.handle_action(Action::NextProject)?,
+                Some('T') if self.mode == Mode::Normal => self.handle_action(Action::PrevProject)?,
+                _ => {}
+            }
+        } else if self.pending_z {
+            self.pending_z = false;
+            if c == Some('a') {
+                self.handle_action(Action::ToggleCollapse)?;
+            }
+        } else if self.pending_y {
+            self.pending_y = false;
+            if c == Some('y') {
+                self.handle_action(Action::Yank)?;
+            }
+        } else if let Some(first) = self.pending_indent.take() {
+            if c == Some(first) {
+                let action = if first == '>' { Action::Indent } else { Action::Outdent };
+                self.handle_action(action)?;
+            }
+        } else {
+            return Ok(false);
+        }
+        self.pending_count = None;
+        Ok(true)
+    }
+
+    /// Keys that start a multi-key sequence in Normal and Visual mode.
+    fn handle_prefix_key(&mut self, key: KeyEvent) -> Result<()> {
+        let normal = self.mode == Mode::Normal;
+        match key.code {
+            KeyCode::Char('g') => self.pending_g = true,
+            KeyCode::Char('w') if normal && key.modifiers.contains(KeyModifiers::CONTROL) => self.pending_ctrl_w = true,
+            KeyCode::Char('z') if normal => self.pending_z = true,
+            KeyCode::Char('y') if normal => self.pending_y = true,
+            KeyCode::Char(c @ ('>' | '<')) if normal => self.pending_indent = Some(c),
+            _ => self.pending_count = None,
+        }
+        Ok(())
+    }
+
+    fn handle_insert_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.editing_task_id = None;
+            }
+            KeyCode::Enter if !self.command_buffer.is_empty() => {
+                let title = self.command_buffer.clone();
+                match self.insert_action {
+                    InsertAction::AddEnd => self.add_task(title.clone())?,
+                    InsertAction::AddBelow => self.add_task_below(title.clone())?,
+                    InsertAction::AddAbove => self.add_task_above(title.clone())?,
+                    InsertAction::Edit => self.commit_edit()?,
+                }
+                let change = match self.insert_action {
+                    InsertAction::Edit => Change::EditTitle(title),
+                    action => Change::Insert(action, title),
+                };
+                self.record_change(change);
+                self.command_buffer.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Char(c) => self.command_buffer.push(c),
+            KeyCode::Backspace => {
+                self.command_buffer.pop();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => self.cancel_search(),
+            KeyCode::Enter => self.confirm_search(),
+            KeyCode::Char(c) => {
+                self.command_buffer.push(c);
+                self.update_incremental_search();
+            }
+            KeyCode::Backspace => {
+                if self.command_buffer.pop().is_none() {
+                    self.cancel_search();
+                } else {
+                    self.update_incremental_search();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.command_buffer.clear();
+                self.update_filter_search()?;
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Enter => self.mode = Mode::Normal,
+            KeyCode::Char(c) => {
+                self.command_buffer.push(c);
+                self.update_filter_search()?;
+            }
+            KeyCode::Backspace => {
+                self.command_buffer.pop();
+                self.update_filter_search()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let Some(picker) = self.picker.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.picker = None;
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Enter => self.accept_picker()?,
+            KeyCode::Down | KeyCode::Tab => picker.move_selection(1),
+            KeyCode::Up | KeyCode::BackTab => picker.move_selection(-1),
+            KeyCode::Char('n' | 'j') if ctrl => picker.move_selection(1),
+            KeyCode::Char('p' | 'k') if ctrl => picker.move_selection(-1),
+            KeyCode::Char('u') if ctrl => {
+                picker.query.clear();
+                picker.refresh();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                picker.query.push(c);
+                picker.refresh();
+            }
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.refresh();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `:messages`: scrolls the message history until it is closed.
+    fn handle_messages_key(&mut self, key: KeyEvent) -> Result<()> {
+        let last = self.messages.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.messages_scroll = (self.messages_scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.messages_scroll = self.messages_scroll.saturating_sub(1),
+            KeyCode::Char('G') => self.messages_scroll = last,
+            KeyCode::Char('g') => self.messages_scroll = 0,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.mode = Mode::Normal,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_command_key(&mut self, key: KeyEvent) -> Result<()> {
+        if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.completion = None;
+        }
+        if !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            self.history_index = None;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = self.command_line_return_mode();
+                self.command_targets = None;
+            }
+            KeyCode::Char('c') if ctrl => {
+                self.mode = self.command_line_return_mode();
+                self.command_targets = None;
+            }
+            // Backspace on an empty line leaves the command line, like Vim
+            KeyCode::Backspace if self.command_buffer.is_empty() => {
+                self.mode = self.command_line_return_mode();
+                self.command_targets = None;
+            }
+            KeyCode::Enter => {
+                let cmd = std::mem::take(&mut self.command_buffer);
+                self.command_cursor = 0;
+                self.run_command_line(&cmd)?;
+            }
+            KeyCode::Tab => self.complete_command_line(true)?,
+            KeyCode::BackTab => self.complete_command_line(false)?,
+            KeyCode::Up => self.recall_command_history(true),
+            KeyCode::Down => self.recall_command_history(false),
+            KeyCode::Char('p') if ctrl => self.recall_command_history(true),
+            KeyCode::Char('n') if ctrl => self.recall_command_history(false),
+            KeyCode::Char('f') if ctrl => {
+                self.command_buffer.clear();
+                self.open_command_window();
+            }
+            _ => {
+                self.edit_command_line(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// `q`: stops the running recording, or waits for the register to record
+    /// into.
+    pub fn toggle_recording(&mut self) -> Result<()> {
+        let Some(reg) = self.macro_recording.take() else {
+            self.pending_q = true;
+            return Ok(());
+        };
+        // The key that stopped the recording is not part of the macro
+        let keys = self.macros.entry(reg).or_default();
+        if self.macro_depth == 0 {
+            keys.pop();
+        }
+        let notation = keys_to_notation(keys);
+        self.storage.save_register(reg, "macro", &notation)
+    }
+
+    /// Starts recording into `reg`; uppercase names append to the lowercase
+    /// macro, like Vim.
+    pub fn start_recording(&mut self, reg: char) {
+        let lower = reg.to_ascii_lowercase();
+        if !reg.is_ascii_uppercase() {
+            self.macros.insert(lower, Vec::new());
+        }
+        self.macro_recording = Some(lower);
+    }
+
+    /// `@x` replays macro `x` `count` times; `@@` repeats the last one played.
+    pub fn play_macro(&mut self, reg: char, count: usize) -> Result<()> {
+        let reg = match reg {
+            '@' => match self.last_macro {
+                Some(last) => last,
+                None => return Ok(()),
+            },
+            _ => reg.to_ascii_lowercase(),
+        };
+        let Some(keys) = self.macros.get(&reg).cloned() else {
+            return Ok(());
+        };
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            self.warn(format!("Macro @{} stopped after {} nested replays", reg, MAX_MACRO_DEPTH));
+            return Ok(());
+        }
+        self.last_macro = Some(reg);
+
+        self.macro_depth += 1;
+        let mut result = Ok(());
+        'replay: for _ in 0..count {
+            for key in &keys {
+                result = self.handle_key(*key);
+                if result.is_err() || !self.running {
+                    break 'replay;
+                }
+            }
+        }
+        self.macro_depth -= 1;
+        result
+    }
+}
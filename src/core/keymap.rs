@@ -11,9 +11,14 @@ pub struct KeyCombination {
 
 impl KeyCombination {
     pub fn from_event(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        // Terminals report `J` as Shift+J; the character already carries the case
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
         Self {
             code: event.code,
-            modifiers: event.modifiers,
+            modifiers,
         }
     }
 
@@ -27,20 +32,15 @@ impl KeyCombination {
 
         let parts: Vec<&str> = s.split('-').collect();
         let mut modifiers = KeyModifiers::empty();
-        let code_str;
-
-        if parts.len() > 1 {
-            for i in 0..parts.len() - 1 {
-                match parts[i].to_lowercase().as_str() {
-                    "ctrl" | "c" => modifiers.insert(KeyModifiers::CONTROL),
-                    "alt" | "a" => modifiers.insert(KeyModifiers::ALT),
-                    "shift" | "s" => modifiers.insert(KeyModifiers::SHIFT),
-                    _ => {}
-                }
+        let (code_str, modifier_parts) = parts.split_last()?;
+
+        for part in modifier_parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "c" => modifiers.insert(KeyModifiers::CONTROL),
+                "alt" | "a" => modifiers.insert(KeyModifiers::ALT),
+                "shift" | "s" => modifiers.insert(KeyModifiers::SHIFT),
+                _ => {}
             }
-            code_str = parts[parts.len() - 1];
-        } else {
-            code_str = parts[0];
         }
 
         let code = match code_str.to_lowercase().as_str() {
@@ -53,7 +53,8 @@ impl KeyCombination {
             "left" => KeyCode::Left,
             "right" => KeyCode::Right,
             "space" => KeyCode::Char(' '),
-            s if s.len() == 1 => KeyCode::Char(s.chars().next().unwrap()),
+            // Keep the case of single characters: `J` and `j` are different keys
+            _ if code_str.chars().count() == 1 => KeyCode::Char(code_str.chars().next().unwrap()),
             _ => return None,
         };
 
@@ -61,9 +62,104 @@ impl KeyCombination {
     }
 }
 
+/// Writes keys in Vim notation (`dd`, `<CR>`, `<C-r>`, `<lt>`), used to
+/// store macros.
+pub fn keys_to_notation(keys: &[KeyEvent]) -> String {
+    let mut out = String::new();
+    for key in keys {
+        let name = match key.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "CR".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Up => "Up".to_string(),
+            KeyCode::Down => "Down".to_string(),
+            KeyCode::Left => "Left".to_string(),
+            KeyCode::Right => "Right".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::BackTab => "S-Tab".to_string(),
+            _ => continue,
+        };
+        let mut prefix = String::new();
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("M-");
+        }
+        if prefix.is_empty() && name.chars().count() == 1 {
+            out.push_str(&name);
+        } else {
+            out.push_str(&format!("<{}{}>", prefix, name));
+        }
+    }
+    out
+}
+
+/// The inverse of `keys_to_notation`. A `<` that does not start a known key
+/// name is taken literally.
+pub fn parse_key_notation(s: &str) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(key) = rest[1..].split_once('>').and_then(|(name, _)| parse_key_name(name)) {
+                rest = &rest[rest.find('>').unwrap() + 1..];
+                keys.push(key);
+                continue;
+            }
+        }
+        keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        rest = &rest[c.len_utf8()..];
+    }
+    keys
+}
+
+fn parse_key_name(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    loop {
+        if let Some(rest) = name.strip_prefix("C-") {
+            modifiers.insert(KeyModifiers::CONTROL);
+            name = rest;
+        } else if let Some(rest) = name.strip_prefix("M-") {
+            modifiers.insert(KeyModifiers::ALT);
+            name = rest;
+        } else {
+            break;
+        }
+    }
+    let code = match name {
+        "lt" => KeyCode::Char('<'),
+        "Space" => KeyCode::Char(' '),
+        "CR" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "BS" => KeyCode::Backspace,
+        "Tab" => KeyCode::Tab,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Del" => KeyCode::Delete,
+        "S-Tab" => KeyCode::BackTab,
+        _ if name.chars().count() == 1 && !modifiers.is_empty() => KeyCode::Char(name.chars().next()?),
+        _ => return None,
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Keymap {
     pub mappings: HashMap<Mode, HashMap<KeyCombination, Action>>,
+    /// Normal mode mappings that take precedence while the sidebar has focus.
+    pub sidebar: HashMap<KeyCombination, Action>,
 }
 
 impl Keymap {
@@ -81,6 +177,8 @@ impl Keymap {
         let defaults = [
             ("j", MoveDown),
             ("k", MoveUp),
+            ("J", MoveTaskDown),
+            ("K", MoveTaskUp),
             ("G", MoveToBottom),
             ("ctrl-d", PageDown),
             ("ctrl-u", PageUp),
@@ -89,6 +187,9 @@ impl Keymap {
             ("O", EnterInsertAbove),
             ("d", Delete),
             ("v", EnterVisual),
+            ("V", EnterVisualLine),
+            ("m", ToggleMark),
+            ("esc", Cancel),
             ("r", EnterInsert),
             (":", EnterCommand),
             ("enter", CycleStatus),
@@ -97,14 +198,24 @@ impl Keymap {
             ("u", Undo),
             ("ctrl-r", Redo),
             ("p", Paste),
-            ("y", Yank), // For single y if needed, but yy handled in tui
+            ("P", PasteAbove),
             ("/", EnterSearch),
-            ("q", Quit),
+            ("?", EnterSearchBackward),
+            ("n", SearchNext),
+            ("N", SearchPrev),
+            ("*", SearchProject),
+            ("#", SearchProjectBackward),
+            ("ctrl-p", FindTasks),
+            ("ctrl-k", CommandPalette),
+            ("q", RecordMacro),
+            ("@", PlayMacro),
+            (".", RepeatChange),
+            ("tab", ShowDetail),
         ];
 
         for (key, action) in defaults {
             let combo = KeyCombination::from_str(key)
-                .expect(&format!("Failed to parse default keybinding: {}", key));
+                .unwrap_or_else(|| panic!("Failed to parse default keybinding: {}", key));
             normal.insert(combo, action);
         }
 
@@ -112,13 +223,31 @@ impl Keymap {
         let visual_defaults = [
             ("j", MoveDown),
             ("k", MoveUp),
+            ("J", MoveTaskDown),
+            ("K", MoveTaskUp),
+            ("G", MoveToBottom),
+            ("v", EnterVisual),
+            ("V", EnterVisualLine),
             ("d", Delete),
+            ("y", Yank),
+            ("m", ToggleMark),
+            ("enter", CycleStatus),
+            ("+", IncreasePriority),
+            ("-", DecreasePriority),
+            ("s", PromptStatus),
+            ("p", PromptProject),
+            ("t", PromptTags),
+            ("D", PromptDue),
+            (":", EnterCommand),
+            (".", RepeatChange),
+            ("n", SearchNext),
+            ("N", SearchPrev),
             ("esc", Cancel),
         ];
 
         for (key, action) in visual_defaults {
             let combo = KeyCombination::from_str(key)
-                .expect(&format!("Failed to parse visual keybinding: {}", key));
+                .unwrap_or_else(|| panic!("Failed to parse visual keybinding: {}", key));
             visual.insert(combo, action);
         }
 
@@ -130,13 +259,22 @@ impl Keymap {
 
         for (key, action) in stats_defaults {
             let combo = KeyCombination::from_str(key)
-                .expect(&format!("Failed to parse stats keybinding: {}", key));
+                .unwrap_or_else(|| panic!("Failed to parse stats keybinding: {}", key));
             stats.insert(combo, action);
         }
+
+        let combo = KeyCombination::from_str("enter").expect("Failed to parse sidebar keybinding");
+        self.sidebar.insert(combo, SidebarApply);
     }
 
     pub fn get_action(&self, mode: Mode, event: KeyEvent) -> Option<Action> {
         let combo = KeyCombination::from_event(event);
+        // Visual-Line shares the Visual mappings
+        let mode = if mode == Mode::VisualLine { Mode::Visual } else { mode };
         self.mappings.get(&mode)?.get(&combo).copied()
     }
+
+    pub fn get_sidebar_action(&self, event: KeyEvent) -> Option<Action> {
+        self.sidebar.get(&KeyCombination::from_event(event)).copied()
+    }
 }
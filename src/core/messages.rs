@@ -0,0 +1,89 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::Task;
+use crate::error::TaskVimError;
+use chrono::{DateTime, Local};
+
+/// How many messages `:messages` keeps.
+pub const MESSAGE_HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl MessageLevel {
+    /// The highlight group the message is drawn with.
+    pub fn highlight_group(&self) -> &'static str {
+        match self {
+            MessageLevel::Info => "InfoMsg",
+            MessageLevel::Warn => "WarningMsg",
+            MessageLevel::Error => "ErrorMsg",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub level: MessageLevel,
+    pub text: String,
+    pub time: DateTime<Local>,
+}
+
+impl AppState {
+    /// Shows `text` in the status bar until the next key and adds it to the
+    /// `:messages` history.
+    pub fn message(&mut self, level: MessageLevel, text: impl Into<String>) {
+        let message = Message { level, text: text.into(), time: Local::now() };
+        self.messages.push(message.clone());
+        let excess = self.messages.len().saturating_sub(MESSAGE_HISTORY_LIMIT);
+        self.messages.drain(..excess);
+        self.message = Some(message);
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.message(MessageLevel::Info, text);
+    }
+
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.message(MessageLevel::Warn, text);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.message(MessageLevel::Error, text);
+    }
+
+    /// Reports an error from a key or command. The state stays usable: the
+    /// change that failed is abandoned and pending input is reset.
+    pub fn report_error(&mut self, err: &TaskVimError) {
+        let text = match err {
+            TaskVimError::Validation(msg) => msg.clone(),
+            err => err.to_string(),
+        };
+        self.error(text);
+        self.pending_count = None;
+        if self.mode == Mode::Command {
+            self.mode = self.command_line_return_mode();
+        }
+    }
+
+    /// Runs the Lua hook `name`, reporting a failing hook instead of
+    /// failing the change that triggered it.
+    pub fn run_hook(&mut self, name: &str, task: Option<&Task>) {
+        if let Err(err) = self.lua_config.trigger_hook(name, task) {
+            self.error(format!("{} hook failed: {}", name, err));
+        }
+    }
+
+    /// `:messages` opens the message history; `:messages clear` empties it.
+    pub fn show_messages(&mut self, arg: &str) {
+        if arg == "clear" {
+            self.messages.clear();
+            self.message = None;
+            return;
+        }
+        self.messages_scroll = self.messages.len().saturating_sub(1);
+        self.mode = Mode::Messages;
+    }
+}
@@ -1,7 +1,31 @@
 pub mod state;
 pub mod actions;
 pub mod keymap;
+pub mod sidebar;
+pub mod tree;
+pub mod position;
+pub mod registers;
+pub mod clipboard;
+pub mod bulk;
+pub mod input;
+pub mod repeat;
+pub mod search;
+pub mod commands;
+pub mod fuzzy;
+pub mod picker;
+pub mod cmdline;
+pub mod messages;
+pub mod ex;
+pub mod timer;
+pub mod pomodoro;
+pub mod stats;
+pub mod detail;
+pub mod archive;
+pub mod trash;
+pub mod transfer;
+pub mod sync;
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 pub use state::{AppState, Mode};
@@ -0,0 +1,227 @@
+use crate::core::commands::EX_COMMANDS;
+use crate::core::fuzzy::fuzzy_match;
+use crate::core::state::{AppState, Mode};
+use crate::error::Result;
+use uuid::Uuid;
+
+/// What a picker lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerSource {
+    Tasks,
+    Projects,
+    Tags,
+    Views,
+    /// Ex commands, Lua commands and views: the command palette.
+    Commands,
+}
+
+impl PickerSource {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "" | "tasks" => Some(PickerSource::Tasks),
+            "projects" => Some(PickerSource::Projects),
+            "tags" => Some(PickerSource::Tags),
+            "views" => Some(PickerSource::Views),
+            "commands" => Some(PickerSource::Commands),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            PickerSource::Tasks => "Tasks",
+            PickerSource::Projects => "Projects",
+            PickerSource::Tags => "Tags",
+            PickerSource::Views => "Views",
+            PickerSource::Commands => "Commands",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerItem {
+    Task(Uuid),
+    Project(String),
+    Tag(String),
+    View(String),
+    /// An ex command line; a trailing space means it still needs arguments.
+    Command(String),
+    LuaCommand(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PickerEntry {
+    pub label: String,
+    /// Shown dimmed after the label.
+    pub detail: String,
+    pub item: PickerItem,
+}
+
+/// A ranked match: entry index, score and the matched char positions in
+/// the label.
+#[derive(Debug, Clone)]
+pub struct PickerMatch {
+    pub index: usize,
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Picker {
+    pub source: PickerSource,
+    pub query: String,
+    pub entries: Vec<PickerEntry>,
+    pub matches: Vec<PickerMatch>,
+    pub selected: usize,
+}
+
+impl Picker {
+    pub fn new(source: PickerSource, entries: Vec<PickerEntry>) -> Self {
+        let mut picker = Self {
+            source,
+            query: String::new(),
+            entries,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        picker.refresh();
+        picker
+    }
+
+    /// Re-ranks the entries against the query, best first. Ties keep the
+    /// original order.
+    pub fn refresh(&mut self) {
+        self.matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                fuzzy_match(&self.query, &entry.label).map(|(score, positions)| PickerMatch { index, score, positions })
+            })
+            .collect();
+        self.matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+        self.selected = 0;
+    }
+
+    pub fn selected_entry(&self) -> Option<&PickerEntry> {
+        self.matches.get(self.selected).map(|m| &self.entries[m.index])
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let len = self.matches.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+}
+
+impl AppState {
+    /// Opens a fuzzy picker over `source`.
+    pub fn open_picker(&mut self, source: PickerSource) -> Result<()> {
+        let entries = self.picker_entries(source)?;
+        self.picker = Some(Picker::new(source, entries));
+        self.mode = Mode::Picker;
+        Ok(())
+    }
+
+    fn picker_entries(&self, source: PickerSource) -> Result<Vec<PickerEntry>> {
+        let entry = |label: String, detail: String, item| PickerEntry { label, detail, item };
+        let mut entries = Vec::new();
+        match source {
+            PickerSource::Tasks => {
+                // Every task, including filtered or folded ones
+                let (tasks, _) = crate::core::tree::build_tree(self.storage.get_tasks(None)?, &Default::default());
+                for task in tasks {
+                    let mut detail = task.project.clone().unwrap_or_default();
+                    for tag in &task.tags {
+                        detail.push_str(&format!(" +{}", tag));
+                    }
+                    entries.push(entry(task.title.clone(), detail.trim().to_string(), PickerItem::Task(task.id)));
+                }
+            }
+            PickerSource::Projects => {
+                for (project, count) in self.storage.project_counts(self.config.show_archived)? {
+                    entries.push(entry(project.clone(), format!("{} tasks", count), PickerItem::Project(project)));
+                }
+            }
+            PickerSource::Tags => {
+                for (tag, count) in self.storage.tag_counts(self.config.show_archived)? {
+                    entries.push(entry(tag.clone(), format!("{} tasks", count), PickerItem::Tag(tag)));
+                }
+            }
+            PickerSource::Views | PickerSource::Commands => {
+                let mut views: Vec<&String> = self.config.view_columns.keys().collect();
+                views.sort();
+                for view in views {
+                    entries.push(entry(view.clone(), "view".to_string(), PickerItem::View(view.clone())));
+                }
+                if source == PickerSource::Commands {
+                    for (cmd, desc) in EX_COMMANDS {
+                        entries.push(entry(cmd.trim_end().to_string(), desc.to_string(), PickerItem::Command(cmd.to_string())));
+                    }
+                    for (name, desc) in &self.config.commands {
+                        entries.push(entry(name.clone(), desc.clone(), PickerItem::LuaCommand(name.clone())));
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// `Enter` in the picker: jumps to the task, applies the project or tag
+    /// filter, switches view, or runs the command.
+    pub fn accept_picker(&mut self) -> Result<()> {
+        let Some(item) = self.picker.take().and_then(|p| p.selected_entry().map(|e| e.item.clone())) else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        self.mode = Mode::Normal;
+        match item {
+            PickerItem::Task(id) => self.reveal_task(id)?,
+            PickerItem::Project(project) => {
+                self.filter_string = Some(format!("project={}", project));
+                self.reload_tasks()?;
+                self.selected_index = 0;
+            }
+            PickerItem::Tag(tag) => {
+                self.filter_string = Some(format!("tag={}", tag));
+                self.reload_tasks()?;
+                self.selected_index = 0;
+            }
+            PickerItem::View(view) => self.execute_command(&format!("view {}", view))?,
+            PickerItem::Command(cmd) if cmd.ends_with(' ') => self.set_command_line(&cmd),
+            PickerItem::Command(cmd) | PickerItem::LuaCommand(cmd) => self.execute_command(&cmd)?,
+        }
+        Ok(())
+    }
+
+    /// Selects task `id`, clearing whatever hides it: filters, a collapsed
+    /// project or folded parents.
+    pub fn reveal_task(&mut self, id: Uuid) -> Result<()> {
+        let Some(task) = self.storage.get_task(id)? else {
+            return Ok(());
+        };
+        if !self.tasks.iter().any(|t| t.id == id) {
+            if let Some(project) = &task.project {
+                self.collapsed_projects.remove(project);
+            }
+            let mut parent = task.parent_id;
+            while let Some(p) = parent {
+                self.folded_tasks.remove(&p);
+                parent = self.storage.get_task(p)?.and_then(|t| t.parent_id);
+                if parent == Some(id) {
+                    break;
+                }
+            }
+            self.reload_tasks()?;
+        }
+        if !self.tasks.iter().any(|t| t.id == id) {
+            self.filter_string = None;
+            self.filter_query = None;
+            self.reload_tasks()?;
+        }
+        self.select_task(id);
+        Ok(())
+    }
+}
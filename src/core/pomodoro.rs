@@ -0,0 +1,173 @@
+use crate::core::state::AppState;
+use crate::domain::TaskStatus;
+use crate::error::{Result, TaskVimError};
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "work",
+            PomodoroPhase::ShortBreak => "break",
+            PomodoroPhase::LongBreak => "long break",
+        }
+    }
+}
+
+/// A running Pomodoro on one task: alternating work periods and breaks
+/// until it is stopped or the task leaves Doing.
+#[derive(Debug, Clone)]
+pub struct Pomodoro {
+    pub task_id: Uuid,
+    pub title: String,
+    pub phase: PomodoroPhase,
+    pub ends_at: DateTime<Utc>,
+    /// Work periods finished since this Pomodoro started.
+    pub completed: u32,
+}
+
+impl Pomodoro {
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.ends_at - now).max(Duration::zero())
+    }
+}
+
+/// `mm:ss` for the status bar countdown.
+pub fn format_countdown(duration: Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+impl AppState {
+    /// `:pomodoro`: starts a work period on the selected task, moving it to
+    /// Doing and starting its timer. Done and Archived tasks are refused.
+    pub fn start_pomodoro(&mut self) -> Result<()> {
+        let id = self.selected_ids().first().copied();
+        let task = match id {
+            Some(id) => self.storage.get_task(id)?,
+            None => None,
+        }
+        .ok_or_else(|| TaskVimError::Validation("No task selected".to_string()))?;
+        if matches!(task.status, TaskStatus::Done | TaskStatus::Archived) {
+            return Err(TaskVimError::Validation(format!("Cannot start a pomodoro on a {} task", task.status)));
+        }
+        let (id, title) = (task.id, task.title);
+        self.start_timer()?;
+        let minutes = self.config.pomodoro.work;
+        self.pomodoro = Some(Pomodoro {
+            task_id: id,
+            title,
+            phase: PomodoroPhase::Work,
+            ends_at: Utc::now() + Duration::minutes(minutes as i64),
+            completed: 0,
+        });
+        self.info(format!("Pomodoro started: {}m of work", minutes));
+        Ok(())
+    }
+
+    /// `:pomodoro stop`
+    pub fn stop_pomodoro(&mut self) {
+        match self.pomodoro.take() {
+            Some(pomodoro) => self.info(format!("Pomodoro stopped after {} completed", pomodoro.completed)),
+            None => self.warn("No pomodoro running"),
+        }
+    }
+
+    /// `:pomodoro skip`: ends the current work period or break early. A
+    /// skipped work period is not counted.
+    pub fn skip_pomodoro_phase(&mut self) -> Result<()> {
+        let Some(pomodoro) = self.pomodoro.as_mut() else {
+            self.warn("No pomodoro running");
+            return Ok(());
+        };
+        let now = Utc::now();
+        let next = match pomodoro.phase {
+            PomodoroPhase::Work => PomodoroPhase::ShortBreak,
+            _ => PomodoroPhase::Work,
+        };
+        self.begin_pomodoro_phase(next, now);
+        Ok(())
+    }
+
+    fn begin_pomodoro_phase(&mut self, phase: PomodoroPhase, now: DateTime<Utc>) {
+        let settings = &self.config.pomodoro;
+        let minutes = match phase {
+            PomodoroPhase::Work => settings.work,
+            PomodoroPhase::ShortBreak => settings.short_break,
+            PomodoroPhase::LongBreak => settings.long_break,
+        };
+        if let Some(pomodoro) = self.pomodoro.as_mut() {
+            pomodoro.phase = phase;
+            pomodoro.ends_at = now + Duration::minutes(minutes as i64);
+        }
+    }
+
+    /// Called by the event loop. When the current period is over, counts a
+    /// finished work period, rings the bell, runs `on_pomodoro_end` and
+    /// moves on to the next period.
+    pub fn tick_pomodoro(&mut self, now: DateTime<Utc>) -> Result<()> {
+        let Some(pomodoro) = self.pomodoro.clone() else {
+            return Ok(());
+        };
+        if now < pomodoro.ends_at {
+            return Ok(());
+        }
+        self.bell = true;
+        if pomodoro.phase != PomodoroPhase::Work {
+            self.begin_pomodoro_phase(PomodoroPhase::Work, now);
+            self.info(format!("Break over, back to {}", pomodoro.title));
+            return Ok(());
+        }
+
+        self.storage.add_pomodoro(pomodoro.task_id, now)?;
+        self.refresh_pomodoro_counts()?;
+        let completed = pomodoro.completed + 1;
+        let every = self.config.pomodoro.long_break_every.max(1);
+        let phase = if completed % every == 0 { PomodoroPhase::LongBreak } else { PomodoroPhase::ShortBreak };
+        if let Some(running) = self.pomodoro.as_mut() {
+            running.completed = completed;
+        }
+        self.begin_pomodoro_phase(phase, now);
+        let task = self.storage.get_task(pomodoro.task_id)?;
+        self.run_hook("on_pomodoro_end", task.as_ref());
+        let total = self.pomodoro_counts.get(&pomodoro.task_id).copied().unwrap_or(0);
+        self.info(format!("Pomodoro {} on {} done, time for a {}", total, pomodoro.title, phase.label()));
+        Ok(())
+    }
+
+    pub fn refresh_pomodoro_counts(&mut self) -> Result<()> {
+        self.pomodoro_counts = self.storage.pomodoro_counts()?;
+        Ok(())
+    }
+
+    /// The Pomodoro belongs to a Doing task, so it ends when the task moves
+    /// on.
+    pub fn end_pomodoro_on_status_changes(&mut self, changes: &[(Uuid, TaskStatus, TaskStatus)]) {
+        let Some(task_id) = self.pomodoro.as_ref().map(|p| p.task_id) else {
+            return;
+        };
+        if changes.iter().any(|&(id, _, after)| id == task_id && after != TaskStatus::Doing) {
+            self.stop_pomodoro();
+        }
+    }
+
+    /// `:pomodoro [start|stop|skip]`
+    pub fn pomodoro_command(&mut self, args: &str) -> Result<()> {
+        match args {
+            "" | "start" => self.start_pomodoro(),
+            "stop" => {
+                self.stop_pomodoro();
+                Ok(())
+            }
+            "skip" => self.skip_pomodoro_phase(),
+            _ => Err(TaskVimError::Validation(format!("Invalid pomodoro argument: {} (start, stop, skip)", args))),
+        }
+    }
+}
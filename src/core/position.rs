@@ -0,0 +1,129 @@
+use crate::core::state::AppState;
+use crate::domain::Task;
+use crate::error::Result;
+use uuid::Uuid;
+
+/// Spacing between sibling positions. New tasks take the midpoint of their
+/// neighbours, so reordering usually rewrites a single row; siblings are
+/// only respaced once a gap is used up.
+pub const POSITION_GAP: i32 = 1024;
+
+impl AppState {
+    /// The tasks sharing `parent`, in position order, across the whole database.
+    pub fn siblings(&self, parent: Option<Uuid>) -> Result<Vec<Task>> {
+        let mut siblings: Vec<Task> = self
+            .storage
+            .get_tasks(None)?
+            .into_iter()
+            .filter(|t| t.parent_id == parent)
+            .collect();
+        siblings.sort_by_key(|t| t.position);
+        Ok(siblings)
+    }
+
+    /// Spreads `parent`'s children `POSITION_GAP` apart, keeping their order.
+    /// Callers are expected to run inside a history group.
+    fn respace_siblings(&mut self, parent: Option<Uuid>) -> Result<()> {
+        for (i, mut task) in self.siblings(parent)?.into_iter().enumerate() {
+            let position = (i as i32 + 1) * POSITION_GAP;
+            if task.position != position {
+                self.storage.push_history(&task)?;
+                task.position = position;
+                self.storage.save_task(&task)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `count` increasing positions directly after sibling `anchor`
+    /// (or before the first child of `parent` when `anchor` is `None`).
+    pub fn slots_after(&mut self, parent: Option<Uuid>, anchor: Option<Uuid>, count: usize) -> Result<Vec<i32>> {
+        let count_i = count as i32;
+        for attempt in 0..2 {
+            let siblings = self.siblings(parent)?;
+            let idx = anchor.and_then(|a| siblings.iter().position(|t| t.id == a));
+            let (lo, hi) = match idx {
+                Some(i) => (Some(siblings[i].position), siblings.get(i + 1).map(|t| t.position)),
+                None if anchor.is_some() => (siblings.last().map(|t| t.position), None),
+                None => (None, siblings.first().map(|t| t.position)),
+            };
+
+            let slots = match (lo, hi) {
+                (None, None) => (1..=count_i).map(|i| i * POSITION_GAP).collect(),
+                (Some(lo), None) => (1..=count_i).map(|i| lo + i * POSITION_GAP).collect(),
+                (None, Some(hi)) => (0..count_i).map(|i| hi - (count_i - i) * POSITION_GAP).collect(),
+                (Some(lo), Some(hi)) if hi - lo > count_i => {
+                    let step = (hi - lo) / (count_i + 1);
+                    (1..=count_i).map(|i| lo + i * step).collect()
+                }
+                _ if attempt == 0 => {
+                    self.respace_siblings(parent)?;
+                    continue;
+                }
+                _ => break,
+            };
+            return Ok(slots);
+        }
+        Err(crate::error::TaskVimError::Internal("no free position after respacing".into()))
+    }
+
+    /// `J`/`K`: moves the targeted tasks one step down (`down`) or up among
+    /// their siblings. Subtasks move along with their parent; a selected
+    /// block keeps its shape and stops at the edge of its level.
+    pub fn move_selected(&mut self, down: bool) -> Result<()> {
+        let ids = self.selected_ids();
+        let Some(&cursor) = ids.first() else {
+            return Ok(());
+        };
+        let cursor = self.tasks.get(self.selected_index).map(|t| t.id).unwrap_or(cursor);
+        let mut parents: Vec<Option<Uuid>> = Vec::new();
+        for task in self.tasks.iter().filter(|t| ids.contains(&t.id)) {
+            if !parents.contains(&task.parent_id) {
+                parents.push(task.parent_id);
+            }
+        }
+
+        self.with_history_group(|state| {
+            for &parent in &parents {
+                let mut siblings = state.siblings(parent)?;
+                if siblings.windows(2).any(|w| w[0].position == w[1].position) {
+                    state.respace_siblings(parent)?;
+                    siblings = state.siblings(parent)?;
+                }
+                let slots: Vec<i32> = siblings.iter().map(|t| t.position).collect();
+
+                // Bubble every selected task past its unselected neighbour,
+                // walking against the direction of travel.
+                let selected = |t: &Task| ids.contains(&t.id);
+                let n = siblings.len();
+                for step in 0..n.saturating_sub(1) {
+                    let i = if down { n - 2 - step } else { step + 1 };
+                    let j = if down { i + 1 } else { i - 1 };
+                    if selected(&siblings[i]) && !selected(&siblings[j]) {
+                        siblings.swap(i, j);
+                    }
+                }
+
+                for (task, slot) in siblings.iter_mut().zip(slots) {
+                    if task.position != slot {
+                        state.storage.push_history(task)?;
+                        task.position = slot;
+                        state.storage.save_task(task)?;
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+        let before = self.selected_index;
+        self.reload_tasks()?;
+        self.select_task(cursor);
+        // Keep a Visual selection on the moved block so it can be moved again
+        if let Some(anchor) = self.selection_anchor.as_mut() {
+            *anchor = (*anchor + self.selected_index).saturating_sub(before).min(self.tasks.len().saturating_sub(1));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::core::keymap::parse_key_notation;
+use crate::core::position::POSITION_GAP;
+use crate::core::state::AppState;
+use crate::domain::Task;
+use crate::error::{Result, TaskVimError};
+use uuid::Uuid;
+
+pub const UNNAMED: char = '"';
+pub const CLIPBOARD: char = '+';
+
+pub fn is_valid_register(reg: char) -> bool {
+    reg == UNNAMED || reg == CLIPBOARD || reg.is_ascii_alphanumeric()
+}
+
+/// Renders tasks as an indented outline, one title per line, for the
+/// system clipboard.
+pub fn tasks_to_text(tasks: &[Task]) -> String {
+    let depth_of = |task: &Task| {
+        let mut depth = 0;
+        let mut parent = task.parent_id;
+        while let Some(p) = parent {
+            match tasks.iter().find(|t| t.id == p) {
+                Some(t) if depth < tasks.len() => {
+                    depth += 1;
+                    parent = t.parent_id;
+                }
+                _ => break,
+            }
+        }
+        depth
+    };
+    tasks
+        .iter()
+        .map(|t| format!("{}{}", "  ".repeat(depth_of(t)), t.title))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Turns clipboard text back into tasks. Indentation (two spaces or a tab
+/// per level) becomes subtask nesting; blank lines are skipped.
+pub fn text_to_tasks(text: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut stack: Vec<(usize, Uuid)> = Vec::new();
+    for line in text.lines() {
+        let title = line.trim();
+        if title.is_empty() {
+            continue;
+        }
+        let indent: usize = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 2 } else { 1 })
+            .sum();
+        while stack.last().map(|(i, _)| *i >= indent).unwrap_or(false) {
+            stack.pop();
+        }
+        let mut task = Task::new(title.to_string());
+        task.parent_id = stack.last().map(|(_, id)| *id);
+        task.position = (tasks.len() as i32 + 1) * POSITION_GAP;
+        stack.push((indent, task.id));
+        tasks.push(task);
+    }
+    tasks
+}
+
+impl AppState {
+    /// The register chosen with a `"x` prefix, or the unnamed one. The
+    /// prefix only applies to the next yank, delete or paste.
+    pub fn take_register(&mut self) -> char {
+        self.active_register.take().unwrap_or(UNNAMED)
+    }
+
+    /// Stores `tasks` in `reg`. Like Vim, uppercase names append to the
+    /// lowercase register and every write also updates the unnamed register.
+    pub fn store_register(&mut self, reg: char, tasks: Vec<Task>) -> Result<()> {
+        let mut written = vec![UNNAMED];
+        if reg == CLIPBOARD {
+            self.clipboard.set_text(&tasks_to_text(&tasks))?;
+            self.registers.insert(UNNAMED, tasks);
+        } else if reg.is_ascii_uppercase() {
+            let lower = reg.to_ascii_lowercase();
+            self.registers.entry(lower).or_default().extend(tasks);
+            let combined = self.registers[&lower].clone();
+            self.registers.insert(UNNAMED, combined);
+            written.push(lower);
+        } else {
+            if reg != UNNAMED {
+                self.registers.insert(reg, tasks.clone());
+                written.push(reg);
+            }
+            self.registers.insert(UNNAMED, tasks);
+        }
+
+        for name in written {
+            let content = serde_json::to_string(&self.registers[&name])?;
+            self.storage.save_register(name, "tasks", &content)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the registers and macros saved by earlier sessions.
+    pub fn load_registers(&mut self) -> Result<()> {
+        for (name, kind, content) in self.storage.load_registers()? {
+            match kind.as_str() {
+                "tasks" => {
+                    if let Ok(tasks) = serde_json::from_str(&content) {
+                        self.registers.insert(name, tasks);
+                    }
+                }
+                "macro" => {
+                    self.macros.insert(name, parse_key_notation(&content));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_register(&mut self, reg: char) -> Result<Vec<Task>> {
+        if reg == CLIPBOARD {
+            let text = self.clipboard.get_text()?.unwrap_or_default();
+            let mut tasks = text_to_tasks(&text);
+            for task in tasks.iter_mut() {
+                task.priority = self.config.default_priority;
+            }
+            return Ok(tasks);
+        }
+        Ok(self.registers.get(&reg.to_ascii_lowercase()).cloned().unwrap_or_default())
+    }
+
+    /// `yy` in Normal mode, `y` in Visual mode: copies the selected tasks and
+    /// their subtasks into the active register.
+    pub fn yank_selected(&mut self) -> Result<()> {
+        let ids = self.selected_ids();
+        let reg = self.take_register();
+        if !ids.is_empty() {
+            let tasks = self.collect_subtrees(&ids)?;
+            self.store_register(reg, tasks)?;
+        }
+        self.end_visual();
+        Ok(())
+    }
+
+    /// `p` / `P`: pastes the active register below or above the selected
+    /// task, at its level, keeping order and nesting.
+    pub fn paste(&mut self, above: bool) -> Result<()> {
+        let reg = self.take_register();
+        let tasks = self.read_register(reg)?;
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        let current = self.tasks.get(self.selected_index).cloned();
+        let parent = current.as_ref().and_then(|t| t.parent_id);
+        let anchor = match (&current, above) {
+            (Some(_), true) => self.sibling_before(current.as_ref())?,
+            (Some(t), false) => Some(t.id),
+            (None, _) => self.siblings(None)?.last().map(|t| t.id),
+        };
+        self.paste_tasks(tasks, parent, anchor)
+    }
+
+    pub fn select_register(&mut self, reg: char) -> Result<()> {
+        if !is_valid_register(reg) {
+            return Err(TaskVimError::Validation(format!("Invalid register: {}", reg)));
+        }
+        self.active_register = Some(reg);
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use crate::core::registers::UNNAMED;
+use crate::core::state::{AppState, InsertAction};
+use crate::error::Result;
+
+/// A modifying action remembered for `.`. Changes are replayed against
+/// whatever is selected when `.` is pressed, not the tasks they first touched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    CycleStatus,
+    ShiftPriority(i32),
+    Delete,
+    Paste { above: bool, register: char },
+    /// Retitling the selected task.
+    EditTitle(String),
+    /// Adding a task with `a`, `o` or `O`.
+    Insert(InsertAction, String),
+    /// A task-editing ex command such as `tag +work` or `status done`.
+    Command(String),
+}
+
+impl AppState {
+    /// Remembers `change` for `.`, done once.
+    pub fn record_change(&mut self, change: Change) {
+        self.last_change = Some((change, 1));
+    }
+
+    /// Sets how many times the last change ran, after a counted action
+    /// such as `3+`, so `.` repeats it that often too.
+    pub fn set_change_count(&mut self, count: usize) {
+        if let Some((_, n)) = self.last_change.as_mut() {
+            *n = count;
+        }
+    }
+
+    /// `.`: re-applies the last change to the current selection, `count`
+    /// times (or as often as the original ran). The repeat is a single undo
+    /// step.
+    pub fn repeat_change(&mut self, count: Option<usize>) -> Result<()> {
+        let Some((change, original)) = self.last_change.clone() else {
+            return Ok(());
+        };
+        let times = count.unwrap_or(original);
+        self.with_history_group(|state| {
+            for _ in 0..times {
+                state.apply_change(&change)?;
+            }
+            Ok(())
+        })?;
+        self.last_change = Some((change, times));
+        Ok(())
+    }
+
+    fn apply_change(&mut self, change: &Change) -> Result<()> {
+        match change {
+            Change::CycleStatus => self.cycle_status(),
+            Change::ShiftPriority(delta) => self.shift_priority(*delta),
+            Change::Delete => self.delete_selected_task(),
+            Change::Paste { above, register } => {
+                if *register != UNNAMED {
+                    self.select_register(*register)?;
+                }
+                self.paste(*above)
+            }
+            Change::EditTitle(title) => {
+                let ids = self.selected_ids();
+                self.update_tasks(&ids, "on_task_update", |task| {
+                    let changed = task.title != *title;
+                    task.title = title.clone();
+                    changed
+                })?;
+                Ok(())
+            }
+            Change::Insert(action, title) => match action {
+                InsertAction::AddBelow => self.add_task_below(title.clone()),
+                InsertAction::AddAbove => self.add_task_above(title.clone()),
+                _ => self.add_task(title.clone()),
+            },
+            Change::Command(cmd) => self.execute_command(cmd),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::Task;
+use crate::error::Result;
+use regex::{Regex, RegexBuilder};
+
+/// What a search pattern is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    /// Title and description, from `/` and `?`.
+    Text,
+    /// The project name, from `*` and `#`.
+    Project,
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub pattern: String,
+    pub regex: Regex,
+    pub kind: SearchKind,
+    /// Set by `?` and `#`; `n` then moves up the list.
+    pub backward: bool,
+}
+
+/// Compiles a search pattern. Patterns are regular expressions; one that
+/// does not parse (e.g. `C++`) is matched literally. Smart-case: the match
+/// ignores case unless the pattern contains an uppercase letter.
+pub fn compile_pattern(pattern: &str) -> Option<Regex> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let ignore_case = !pattern.chars().any(|c| c.is_uppercase());
+    let build = |p: &str| RegexBuilder::new(p).case_insensitive(ignore_case).build();
+    build(pattern).or_else(|_| build(&regex::escape(pattern))).ok()
+}
+
+impl Search {
+    pub fn new(pattern: &str, backward: bool) -> Option<Self> {
+        Some(Self {
+            pattern: pattern.to_string(),
+            regex: compile_pattern(pattern)?,
+            kind: SearchKind::Text,
+            backward,
+        })
+    }
+
+    pub fn project(project: &str, backward: bool) -> Option<Self> {
+        let regex = Regex::new(&format!("^{}$", regex::escape(project))).ok()?;
+        Some(Self {
+            pattern: project.to_string(),
+            regex,
+            kind: SearchKind::Project,
+            backward,
+        })
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self.kind {
+            SearchKind::Text => {
+                self.regex.is_match(&task.title)
+                    || task.description.as_deref().map(|d| self.regex.is_match(d)).unwrap_or(false)
+            }
+            SearchKind::Project => task.project.as_deref().map(|p| self.regex.is_match(p)).unwrap_or(false),
+        }
+    }
+
+    /// The regex to highlight in titles, for text searches.
+    pub fn title_highlight(&self) -> Option<&Regex> {
+        (self.kind == SearchKind::Text).then_some(&self.regex)
+    }
+}
+
+impl AppState {
+    /// `/` and `?`: starts an incremental search from the selected task.
+    pub fn start_search(&mut self, backward: bool) {
+        self.mode = Mode::Search;
+        self.command_buffer.clear();
+        self.search_origin = Some(self.selected_index);
+        self.search_backward = backward;
+    }
+
+    /// Called as the pattern is typed: jumps to the first match from where
+    /// the search started, or back there when nothing matches.
+    pub fn update_incremental_search(&mut self) {
+        let origin = self.search_origin.unwrap_or(self.selected_index);
+        self.selected_index = origin;
+        if let Some(search) = Search::new(&self.command_buffer, self.search_backward) {
+            if let Some(idx) = self.find_match(&search, origin, !search.backward) {
+                self.selected_index = idx;
+            }
+        }
+    }
+
+    /// `Enter`: keeps the pattern for `n`/`N` and highlighting.
+    pub fn confirm_search(&mut self) {
+        if self.command_buffer.is_empty() {
+            // An empty pattern repeats the last search, as in Vim
+            if let Some(search) = self.search.as_mut() {
+                search.backward = self.search_backward;
+            }
+            self.selected_index = self.search_origin.unwrap_or(self.selected_index);
+            self.search_next(false);
+        } else {
+            self.search = Search::new(&self.command_buffer, self.search_backward);
+            if let Some(search) = &self.search {
+                if !self.tasks.iter().any(|t| search.matches(t)) {
+                    self.error(format!("Pattern not found: {}", search.pattern));
+                }
+            }
+        }
+        self.search_origin = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// `Esc`: abandons the search and returns to where it started.
+    pub fn cancel_search(&mut self) {
+        if let Some(origin) = self.search_origin.take() {
+            self.selected_index = origin;
+        }
+        self.mode = Mode::Normal;
+    }
+
+    /// `n` (and `N` with `reverse`): moves to the next match in the search's
+    /// direction, wrapping around the list.
+    pub fn search_next(&mut self, reverse: bool) {
+        let Some(search) = self.search.clone() else {
+            return;
+        };
+        match self.find_match(&search, self.selected_index, search.backward == reverse) {
+            Some(idx) => self.selected_index = idx,
+            None => self.error(format!("Pattern not found: {}", search.pattern)),
+        }
+    }
+
+    /// `*` and `#`: searches forward or backward for the selected task's
+    /// project.
+    pub fn search_project(&mut self, backward: bool) {
+        let Some(project) = self.tasks.get(self.selected_index).and_then(|t| t.project.clone()) else {
+            return;
+        };
+        self.search = Search::project(&project, backward);
+        self.search_next(false);
+    }
+
+    /// `:noh`: clears the highlighting until the next search.
+    pub fn clear_search_highlight(&mut self) {
+        self.search = None;
+    }
+
+    /// The index of the first task after (or before) `from` matching
+    /// `search`, wrapping around.
+    fn find_match(&self, search: &Search, from: usize, forward: bool) -> Option<usize> {
+        let n = self.tasks.len();
+        (1..=n)
+            .map(|step| if forward { (from + step) % n } else { (from + n * 2 - step) % n })
+            .find(|&idx| search.matches(&self.tasks[idx]))
+    }
+
+    /// `g/`: narrows the list to the tasks matching what is typed.
+    pub fn start_filter_search(&mut self) {
+        self.mode = Mode::Filter;
+        self.command_buffer = self.filter_query.clone().unwrap_or_default();
+    }
+
+    pub fn update_filter_search(&mut self) -> Result<()> {
+        self.filter_query = (!self.command_buffer.is_empty()).then(|| self.command_buffer.clone());
+        self.reload_tasks()
+    }
+}
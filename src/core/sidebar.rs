@@ -0,0 +1,103 @@
+use crate::core::actions::Action;
+use crate::core::state::AppState;
+use crate::domain::TaskStatus;
+use crate::error::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Tasks,
+    Sidebar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SidebarItem {
+    All,
+    Project(String),
+    Tag(String),
+}
+
+impl SidebarItem {
+    /// The filter expression this entry applies when selected.
+    pub fn filter(&self) -> Option<String> {
+        match self {
+            SidebarItem::All => None,
+            SidebarItem::Project(p) => Some(format!("project={}", p)),
+            SidebarItem::Tag(t) => Some(format!("tag={}", t)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SidebarEntry {
+    pub item: SidebarItem,
+    pub count: usize,
+}
+
+impl AppState {
+    pub fn refresh_sidebar(&mut self) -> Result<()> {
+        if !self.config.show_sidebar {
+            self.sidebar.clear();
+            return Ok(());
+        }
+
+        // Archived tasks only count when the task list shows them
+        let archived = self.config.show_archived;
+        let total = self.storage.get_tasks(None)?.iter().filter(|t| archived || t.status != TaskStatus::Archived).count();
+        let mut entries = vec![SidebarEntry { item: SidebarItem::All, count: total }];
+        for (project, count) in self.storage.project_counts(archived)? {
+            entries.push(SidebarEntry { item: SidebarItem::Project(project), count });
+        }
+        for (tag, count) in self.storage.tag_counts(archived)? {
+            entries.push(SidebarEntry { item: SidebarItem::Tag(tag), count });
+        }
+        self.sidebar = entries;
+
+        if self.sidebar_index >= self.sidebar.len() {
+            self.sidebar_index = self.sidebar.len().saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Index of the entry whose filter is currently applied, if any.
+    pub fn active_sidebar_index(&self) -> Option<usize> {
+        self.sidebar.iter().position(|e| e.item.filter() == self.filter_string)
+    }
+
+    pub fn focus_sidebar(&mut self) {
+        if self.config.show_sidebar && !self.sidebar.is_empty() {
+            self.focus = Focus::Sidebar;
+            if let Some(idx) = self.active_sidebar_index() {
+                self.sidebar_index = idx;
+            }
+        }
+    }
+
+    pub fn focus_tasks(&mut self) {
+        self.focus = Focus::Tasks;
+    }
+
+    pub fn apply_sidebar_selection(&mut self) -> Result<()> {
+        if let Some(entry) = self.sidebar.get(self.sidebar_index) {
+            self.filter_string = entry.item.filter();
+            self.reload_tasks()?;
+            self.selected_index = 0;
+        }
+        Ok(())
+    }
+
+    /// Handles `action` while the sidebar has focus. Returns false when the
+    /// action does not apply to the sidebar and should reach the task list.
+    pub fn handle_sidebar_action(&mut self, action: Action) -> Result<bool> {
+        let last = self.sidebar.len().saturating_sub(1);
+        match action {
+            Action::MoveDown => self.sidebar_index = (self.sidebar_index + 1).min(last),
+            Action::MoveUp => self.sidebar_index = self.sidebar_index.saturating_sub(1),
+            Action::MoveToTop => self.sidebar_index = 0,
+            Action::MoveToBottom => self.sidebar_index = last,
+            Action::SidebarApply => self.apply_sidebar_selection()?,
+            Action::Cancel => self.focus_tasks(),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
@@ -3,20 +3,48 @@ use crate::storage::SqliteStorage;
 use crate::error::Result;
 use crate::config::lua::{Config, LuaConfig};
 use crate::core::actions::Action;
+use crate::core::clipboard::{Clipboard, MemoryClipboard, Osc52Clipboard};
+use crate::core::registers::UNNAMED;
+use crate::core::repeat::Change;
+use crate::core::cmdline::Completion;
+use crate::core::messages::Message;
+use crate::core::picker::{Picker, PickerSource};
+use crate::core::search::Search;
+use crate::core::sidebar::{Focus, SidebarEntry};
+use crate::core::tree::{build_tree, TreeNode};
+use crate::storage::sqlite::HistoryStack;
+use crate::core::timer::{TimeEntriesView, TimeReport};
+use crate::core::pomodoro::Pomodoro;
+use crate::core::stats::Stats;
+use crate::core::archive::ArchiveView;
+use crate::core::trash::TrashView;
+use crate::core::sync::TodoTxtSync;
+use crate::domain::time::TimeEntry;
+use crate::ui::theme::Theme;
 use chrono::Utc;
 use uuid::Uuid;
 use std::collections::{HashSet, HashMap};
-use std::sync::Arc;
+use std::rc::Rc;
+use std::io::IsTerminal;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Mode {
     Normal,
     Insert,
     Visual,
+    VisualLine,
     Command,
     Filter,
     Stats,
     Search,
+    Picker,
+    CommandWindow,
+    Messages,
+    TimeEntries,
+    Report,
+    Detail,
+    Archive,
+    Trash,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,19 +80,80 @@ pub struct AppState {
     pub selection_anchor: Option<usize>,
     pub editing_task_id: Option<Uuid>,
     pub config: Config,
-    pub lua_config: Arc<LuaConfig>,
+    /// Built from `config` whenever it is loaded, not on every frame.
+    pub theme: Theme,
+    pub lua_config: Rc<LuaConfig>,
     pub collapsed_projects: HashSet<String>,
-    pub yanked_task: Option<Task>,
+    pub registers: HashMap<char, Vec<Task>>,
+    pub active_register: Option<char>,
+    pub pending_register: bool,
+    pub clipboard: Box<dyn Clipboard>,
     pub macro_recording: Option<char>,
     pub macros: HashMap<char, Vec<crossterm::event::KeyEvent>>,
-    pub search_query: Option<String>,
+    /// The live filter typed in `g/` mode.
+    pub filter_query: Option<String>,
+    /// The last confirmed search, used by `n`/`N` and for highlighting.
+    pub search: Option<Search>,
+    /// Where an incremental search started, so `Esc` can return there.
+    pub search_origin: Option<usize>,
+    pub search_backward: bool,
+    pub focus: Focus,
+    pub sidebar: Vec<SidebarEntry>,
+    pub sidebar_index: usize,
+    pub pending_ctrl_w: bool,
+    pub view: Option<String>,
+    pub tree: HashMap<Uuid, TreeNode>,
+    pub folded_tasks: HashSet<Uuid>,
+    pub pending_indent: Option<char>,
+    pub marked_tasks: HashSet<Uuid>,
+    /// Tasks targeted by the command being typed, captured when the command
+    /// line is opened from Visual mode or with marks set.
+    pub command_targets: Option<Vec<Uuid>>,
+    pub pending_count: Option<usize>,
+    pub last_macro: Option<char>,
+    /// How many macro replays are running; keys are only recorded at depth 0.
+    pub macro_depth: usize,
+    /// The change `.` repeats and how many times it ran.
+    pub last_change: Option<(Change, usize)>,
+    pub picker: Option<Picker>,
+    /// Char index of the cursor in `command_buffer` in Command mode.
+    pub command_cursor: usize,
+    pub command_history: Vec<String>,
+    /// The history entry on the command line while recalling with Up/Down.
+    pub history_index: Option<usize>,
+    pub history_prefix: String,
+    pub completion: Option<Completion>,
+    pub command_window_index: usize,
+    /// The message in the status bar, cleared by the next key.
+    pub message: Option<Message>,
+    pub messages: Vec<Message>,
+    pub messages_scroll: usize,
+    /// The first and last task of the last Visual selection, for `'<,'>`.
+    pub last_visual: Option<(Uuid, Uuid)>,
+    /// The running time entry and its task's title, for the status bar.
+    pub running_timer: Option<(TimeEntry, String)>,
+    pub time_entries_view: Option<TimeEntriesView>,
+    pub report: Option<TimeReport>,
+    pub pomodoro: Option<Pomodoro>,
+    /// Finished pomodoros per task, for the stats view.
+    pub pomodoro_counts: HashMap<Uuid, usize>,
+    /// Set when the terminal bell should ring; the event loop rings it.
+    pub bell: bool,
+    pub stats: Option<Stats>,
+    /// The task shown in the detail view.
+    pub detail_task: Option<Uuid>,
+    pub detail_scroll: usize,
+    pub archive_view: Option<ArchiveView>,
+    pub trash_view: Option<TrashView>,
+    pub todotxt_sync: Option<TodoTxtSync>,
 }
 
 impl AppState {
-    pub fn new(storage: SqliteStorage, lua_config: Arc<LuaConfig>) -> Result<Self> {
+    pub fn new(storage: SqliteStorage, lua_config: Rc<LuaConfig>) -> Result<Self> {
         let tasks = storage.get_tasks(None)?;
         let config = lua_config.get_config();
-        Ok(Self {
+        let theme = Theme::from_config(&config);
+        let mut state = Self {
             tasks,
             selected_index: 0,
             mode: Mode::Normal,
@@ -82,13 +171,74 @@ impl AppState {
             selection_anchor: None,
             editing_task_id: None,
             config,
+            theme,
             lua_config,
             collapsed_projects: HashSet::new(),
-            yanked_task: None,
+            registers: HashMap::new(),
+            active_register: None,
+            pending_register: false,
+            clipboard: if std::io::stdout().is_terminal() {
+                Box::new(Osc52Clipboard::default())
+            } else {
+                Box::new(MemoryClipboard::default())
+            },
             macro_recording: None,
             macros: HashMap::new(),
-            search_query: None,
-        })
+            filter_query: None,
+            search: None,
+            search_origin: None,
+            search_backward: false,
+            focus: Focus::Tasks,
+            sidebar: Vec::new(),
+            sidebar_index: 0,
+            pending_ctrl_w: false,
+            view: None,
+            tree: HashMap::new(),
+            folded_tasks: HashSet::new(),
+            pending_indent: None,
+            marked_tasks: HashSet::new(),
+            command_targets: None,
+            pending_count: None,
+            last_macro: None,
+            macro_depth: 0,
+            last_change: None,
+            picker: None,
+            command_cursor: 0,
+            command_history: Vec::new(),
+            history_index: None,
+            history_prefix: String::new(),
+            completion: None,
+            command_window_index: 0,
+            message: None,
+            messages: Vec::new(),
+            messages_scroll: 0,
+            last_visual: None,
+            running_timer: None,
+            time_entries_view: None,
+            report: None,
+            pomodoro: None,
+            pomodoro_counts: HashMap::new(),
+            bell: false,
+            stats: None,
+            detail_task: None,
+            detail_scroll: 0,
+            archive_view: None,
+            trash_view: None,
+            todotxt_sync: None,
+        };
+        state.load_registers()?;
+        state.load_command_history()?;
+        state.refresh_timer()?;
+        state.refresh_pomodoro_counts()?;
+        state.reload_tasks()?;
+        Ok(state)
+    }
+
+    /// Picks up `set.*`, `map()` and `highlight()` calls made since the
+    /// config was last read.
+    pub fn reload_config(&mut self) {
+        self.config = self.lua_config.get_config();
+        self.theme = Theme::from_config(&self.config);
     }
 
     pub fn reload_tasks(&mut self) -> Result<()> {
@@ -101,93 +251,161 @@ impl AppState {
             SortBy::CreatedAt => all_tasks.sort_by_key(|t| t.created_at),
         }
 
-        // Filter out tasks in collapsed projects and apply search
-        self.tasks = all_tasks.into_iter().filter(|t| {
+        // Filter out archived tasks and tasks in collapsed projects and apply
+        // the `g/` filter
+        let filter = self.filter_query.as_deref().and_then(|q| Search::new(q, false));
+        let show_archived = self.shows_archived();
+        let visible: Vec<Task> = all_tasks.into_iter().filter(|t| {
+            if t.status == TaskStatus::Archived && !show_archived {
+                return false;
+            }
             let project_visible = if let Some(p) = &t.project {
                 !self.collapsed_projects.contains(p)
             } else {
                 true
             };
 
-            let search_match = if let Some(q) = &self.search_query {
-                t.title.to_lowercase().contains(&q.to_lowercase()) || 
-                t.description.as_ref().map(|d| d.to_lowercase().contains(&q.to_lowercase())).unwrap_or(false)
-            } else {
-                true
-            };
+            let search_match = filter.as_ref().map(|f| f.matches(t)).unwrap_or(true);
 
             project_visible && search_match
         }).collect();
 
+        let (tasks, tree) = build_tree(visible, &self.folded_tasks);
+        self.tasks = tasks;
+        self.tree = tree;
+
         if self.selected_index >= self.tasks.len() && !self.tasks.is_empty() {
             self.selected_index = self.tasks.len() - 1;
         }
-        Ok(())
+        self.refresh_sidebar()
     }
 
     pub fn add_task(&mut self, title: String) -> Result<()> {
         let mut task = Task::new(title);
         task.priority = self.config.default_priority;
-        task.position = self.tasks.iter().map(|t| t.position).max().unwrap_or(0) + 1;
-        self.storage.save_task(&task)?;
-        let _ = self.lua_config.trigger_hook("on_task_create", Some(&task));
-        self.reload_tasks()?;
+        let last_root = self.siblings(None)?.last().map(|t| t.id);
+        self.insert_new_task(task, None, last_root)?;
         Ok(())
     }
 
     pub fn add_task_below(&mut self, title: String) -> Result<()> {
-        let current_pos = self.tasks.get(self.selected_index).map(|t| t.position).unwrap_or(0);
-        
-        // Shift all tasks after current_pos
-        for task in self.tasks.iter_mut() {
-            if task.position > current_pos {
-                task.position += 1;
-                self.storage.save_task(task)?;
-            }
-        }
+        let mut new_task = Task::new(title);
+        new_task.priority = self.config.default_priority;
+        let current = self.tasks.get(self.selected_index).cloned();
+        let parent = current.as_ref().and_then(|t| t.parent_id);
+        let anchor = match &current {
+            Some(t) => Some(t.id),
+            None => self.siblings(None)?.last().map(|t| t.id),
+        };
+        self.insert_new_task(new_task, parent, anchor)
+    }
 
+    pub fn add_task_above(&mut self, title: String) -> Result<()> {
         let mut new_task = Task::new(title);
         new_task.priority = self.config.default_priority;
-        new_task.position = current_pos + 1;
-        self.storage.save_task(&new_task)?;
-        let _ = self.lua_config.trigger_hook("on_task_create", Some(&new_task));
+        let current = self.tasks.get(self.selected_index).cloned();
+        let parent = current.as_ref().and_then(|t| t.parent_id);
+        let anchor = self.sibling_before(current.as_ref())?;
+        self.insert_new_task(new_task, parent, anchor)
+    }
+
+    /// The sibling preceding `task`, used as the insertion anchor for "above".
+    pub fn sibling_before(&self, task: Option<&Task>) -> Result<Option<Uuid>> {
+        let Some(task) = task else {
+            return Ok(None);
+        };
+        let siblings = self.siblings(task.parent_id)?;
+        let idx = siblings.iter().position(|t| t.id == task.id).unwrap_or(0);
+        Ok(idx.checked_sub(1).map(|i| siblings[i].id))
+    }
+
+    /// Saves a freshly created task right after sibling `anchor` of `parent`.
+    fn insert_new_task(&mut self, mut task: Task, parent: Option<Uuid>, anchor: Option<Uuid>) -> Result<()> {
+        task.parent_id = parent;
+        self.with_history_group(|state| {
+            task.position = state.slots_after(parent, anchor, 1)?[0];
+            state.storage.push_snapshot(HistoryStack::Undo, task.id, None)?;
+            state.storage.save_task(&task)
+        })?;
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+        self.run_hook("on_task_create", Some(&task));
         self.reload_tasks()?;
-        self.selected_index += 1;
+        self.select_task(task.id);
         Ok(())
     }
 
-    pub fn add_task_above(&mut self, title: String) -> Result<()> {
-        let current_pos = self.tasks.get(self.selected_index).map(|t| t.position).unwrap_or(0);
-        
-        // Shift all tasks starting from current_pos
-        for task in self.tasks.iter_mut() {
-            if task.position >= current_pos {
-                task.position += 1;
-                self.storage.save_task(task)?;
+    /// `ids` followed by all their descendants, each task once.
+    pub fn collect_subtrees(&self, ids: &[Uuid]) -> Result<Vec<Task>> {
+        let mut collected: Vec<Uuid> = Vec::new();
+        for &id in ids {
+            for id in std::iter::once(id).chain(self.descendant_ids(id)?) {
+                if !collected.contains(&id) {
+                    collected.push(id);
+                }
+            }
+        }
+        let mut tasks = Vec::new();
+        for id in collected {
+            if let Some(task) = self.storage.get_task(id)? {
+                tasks.push(task);
             }
         }
+        Ok(tasks)
+    }
 
-        let mut new_task = Task::new(title);
-        new_task.priority = self.config.default_priority;
-        new_task.position = current_pos;
-        self.storage.save_task(&new_task)?;
-        let _ = self.lua_config.trigger_hook("on_task_create", Some(&new_task));
-        self.reload_tasks()?;
-        Ok(())
+    /// Runs `f` with every history snapshot it pushes grouped into a single
+    /// undo step.
+    pub fn with_history_group<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.storage.begin_history_group()?;
+        let result = f(self);
+        self.storage.end_history_group();
+        result
     }
 
-    pub fn delete_selected_task(&mut self) -> Result<()> {
-        if self.mode == Mode::Visual {
-            self.delete_visual_selection()?;
-            self.mode = Mode::Normal;
-            self.selection_anchor = None;
-        } else {
-            if let Some(task) = self.tasks.get(self.selected_index) {
-                self.storage.delete_task(task.id)?;
-                self.reload_tasks()?;
+    /// Deletes the given tasks together with all their subtasks as one
+    /// undoable step. The removed tasks are kept for pasting, like Vim's `d`.
+    pub fn delete_tasks(&mut self, ids: &[Uuid]) -> Result<()> {
+        let doomed = self.collect_subtrees(ids)?;
+
+        let batch = self.storage.next_trash_batch()?;
+        let now = Utc::now();
+        self.with_history_group(|state| {
+            for task in &doomed {
+                state.storage.push_history(task)?;
+                state.storage.trash_task(task, batch, now)?;
+                state.folded_tasks.remove(&task.id);
             }
+            Ok(())
+        })?;
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+        let reg = self.take_register();
+        if !doomed.is_empty() {
+            self.store_register(reg, doomed)?;
         }
-        Ok(())
+        self.reload_tasks()
+    }
+
+    /// `d`: cuts the targeted tasks (see `selected_ids`) with their subtasks.
+    pub fn delete_selected_task(&mut self) -> Result<()> {
+        let ids = self.selected_ids();
+        self.end_visual();
+        for id in &ids {
+            self.marked_tasks.remove(id);
+        }
+        self.delete_tasks(&ids)
+    }
+
+    /// `3d`: cuts `count` tasks from the cursor down, with their subtasks, as
+    /// one step. A Visual range or marks take precedence over the count.
+    pub fn delete_counted(&mut self, count: usize) -> Result<()> {
+        if self.selection_anchor.is_some() || !self.marked_tasks.is_empty() || self.tasks.is_empty() {
+            return self.delete_selected_task();
+        }
+        let end = (self.selected_index + count).min(self.tasks.len());
+        let ids: Vec<Uuid> = self.tasks[self.selected_index..end].iter().map(|t| t.id).collect();
+        self.delete_tasks(&ids)
     }
 
     pub fn start_editing(&mut self) {
@@ -207,7 +425,8 @@ impl AppState {
                 task.title = self.command_buffer.clone();
                 task.updated_at = Utc::now();
                 self.storage.save_task(&task)?;
-                let _ = self.lua_config.trigger_hook("on_task_update", Some(&task));
+                self.write_todotxt()?;
+                self.run_hook("on_task_update", Some(&task));
                 self.reload_tasks()?;
             }
             self.editing_task_id = None;
@@ -215,144 +434,108 @@ impl AppState {
         Ok(())
     }
 
-    pub fn delete_visual_selection(&mut self) -> Result<()> {
-        if let Some(anchor) = self.selection_anchor {
-            let start = anchor.min(self.selected_index);
-            let end = anchor.max(self.selected_index);
-            
-            // Collect IDs first to avoid index shifting issues during deletion if we were modifying in-place
-            // But we are deleting from DB, so it's fine.
-            let ids_to_delete: Vec<Uuid> = self.tasks[start..=end].iter().map(|t| t.id).collect();
-            
-            for id in ids_to_delete {
-                self.storage.delete_task(id)?;
-            }
-            self.reload_tasks()?;
-            
-            // Adjust selection
-            if self.selected_index >= self.tasks.len() && !self.tasks.is_empty() {
-                self.selected_index = self.tasks.len() - 1;
-            } else if self.tasks.is_empty() {
-                self.selected_index = 0;
+    /// Reverts the latest group on `from`, recording the state it replaces
+    /// on the opposite stack so the step can be re-applied.
+    fn revert_history_group(&mut self, from: HistoryStack) -> Result<()> {
+        let entries = self.storage.take_latest_group(from)?;
+        if entries.is_empty() {
+            match from {
+                HistoryStack::Undo => self.warn("Already at oldest change"),
+                HistoryStack::Redo => self.warn("Already at newest change"),
             }
+            return Ok(());
         }
-        Ok(())
-    }
+        let to = match from {
+            HistoryStack::Undo => HistoryStack::Redo,
+            HistoryStack::Redo => HistoryStack::Undo,
+        };
 
-    pub fn undo(&mut self) -> Result<()> {
-        if let Some((hist_id, task)) = self.storage.get_latest_history()? {
-            // Push current state to redo before reverting
-            if let Some(current) = self.tasks.iter().find(|t| t.id == task.id) {
-                self.storage.push_redo(current)?;
+        // Tasks the step removes go to the trash rather than away for good
+        let batch = self.storage.next_trash_batch()?;
+        let now = Utc::now();
+        self.with_history_group(|state| {
+            // Newest first, so a task touched twice ends at its oldest snapshot
+            for (id, snapshot) in entries.iter().rev() {
+                let current = state.storage.get_task(*id)?;
+                state.storage.push_snapshot(to, *id, current.as_ref())?;
+                match (snapshot, &current) {
+                    (Some(task), _) => state.storage.save_task(task)?,
+                    (None, Some(task)) => state.storage.trash_task(task, batch, now)?,
+                    (None, None) => {}
+                }
             }
-            self.storage.save_task(&task)?;
-            self.storage.delete_history_entry(hist_id)?;
-            self.reload_tasks()?;
+            Ok(())
+        })?;
+        self.write_todotxt()?;
+        self.reload_tasks()?;
+        if let Some((id, Some(_))) = entries.first() {
+            self.select_task(*id);
         }
         Ok(())
     }
 
-    pub fn play_macro(&mut self, reg: char) -> Result<()> {
-        if let Some(events) = self.macros.get(&reg).cloned() {
-            for event in events {
-                if let Some(action) = self.config.keymap.get_action(self.mode, event) {
-                    self.handle_action(action)?;
-                }
-            }
-        }
-        Ok(())
+    pub fn undo(&mut self) -> Result<()> {
+        self.revert_history_group(HistoryStack::Undo)
     }
 
     pub fn redo(&mut self) -> Result<()> {
-        if let Some((redo_id, task)) = self.storage.get_latest_redo()? {
-            // Push current state to undo before applying redo
-            if let Some(current) = self.tasks.iter().find(|t| t.id == task.id) {
-                self.storage.push_history(current)?;
-            }
-            self.storage.save_task(&task)?;
-            self.storage.delete_redo_entry(redo_id)?;
-            self.reload_tasks()?;
-        }
-        Ok(())
+        self.revert_history_group(HistoryStack::Redo)
     }
 
-    pub fn yank_selected(&mut self) {
-        if let Some(task) = self.tasks.get(self.selected_index) {
-            self.yanked_task = Some(task.clone());
+    pub fn paste_tasks(&mut self, tasks: Vec<Task>, parent: Option<Uuid>, anchor: Option<Uuid>) -> Result<()> {
+        let now = Utc::now();
+        let mut id_map: HashMap<Uuid, Uuid> = HashMap::new();
+        for task in &tasks {
+            let restore = self.storage.get_task(task.id)?.is_none();
+            id_map.insert(task.id, if restore { task.id } else { Uuid::new_v4() });
         }
-    }
 
-    pub fn paste_below(&mut self) -> Result<()> {
-        if let Some(task) = self.yanked_task.clone() {
-            let mut new_task = task;
-            new_task.id = Uuid::new_v4();
-            new_task.created_at = Utc::now();
-            new_task.updated_at = Utc::now();
-            
-            let current_pos = self.tasks.get(self.selected_index).map(|t| t.position).unwrap_or(0);
-            
-            // Shift
-            for t in self.tasks.iter_mut() {
-                if t.position > current_pos {
-                    t.position += 1;
-                    self.storage.save_task(t)?;
+        let roots: Vec<Uuid> = tasks
+            .iter()
+            .filter(|t| t.parent_id.map(|p| !id_map.contains_key(&p)).unwrap_or(true))
+            .map(|t| t.id)
+            .collect();
+
+        let mut pasted = Vec::new();
+        self.with_history_group(|state| {
+            let slots = state.slots_after(parent, anchor, roots.len())?;
+            for mut task in tasks {
+                let new_id = id_map[&task.id];
+                if new_id != task.id {
+                    task.created_at = now;
                 }
+                task.updated_at = now;
+                if let Some(slot) = roots.iter().position(|r| *r == task.id) {
+                    task.parent_id = parent;
+                    task.position = slots[slot];
+                } else {
+                    task.parent_id = task.parent_id.map(|p| id_map[&p]);
+                }
+                task.id = new_id;
+                state.storage.push_snapshot(HistoryStack::Undo, task.id, None)?;
+                state.storage.save_task(&task)?;
+                pasted.push(task);
             }
-
-            new_task.position = current_pos + 1;
-            self.storage.save_task(&new_task)?;
-            self.storage.clear_redo()?;
-            self.reload_tasks()?;
-            self.selected_index += 1;
-        }
-        Ok(())
-    }
-
-    pub fn increase_priority(&mut self) -> Result<()> {
-        if let Some(mut task) = self.tasks.get(self.selected_index).cloned() {
-            if task.priority < 5 {
-                self.storage.push_history(&task)?;
-                self.storage.clear_redo()?;
-                task.priority += 1;
-                self.storage.save_task(&task)?;
-                self.reload_tasks()?;
-            }
-        }
-        Ok(())
-    }
-
-    pub fn decrease_priority(&mut self) -> Result<()> {
-        if let Some(mut task) = self.tasks.get(self.selected_index).cloned() {
-            if task.priority > 1 {
-                self.storage.push_history(&task)?;
-                self.storage.clear_redo()?;
-                task.priority -= 1;
-                self.storage.save_task(&task)?;
-                self.reload_tasks()?;
-            }
+            Ok(())
+        })?;
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+        for task in &pasted {
+            self.run_hook("on_task_create", Some(task));
         }
-        Ok(())
-    }
-
-    pub fn cycle_status(&mut self) -> Result<()> {
-        if let Some(mut task) = self.tasks.get(self.selected_index).cloned() {
-            self.storage.push_history(&task)?;
-            self.storage.clear_redo()?;
-            task.status = match task.status {
-                TaskStatus::Todo => TaskStatus::Doing,
-                TaskStatus::Doing => TaskStatus::Done,
-                TaskStatus::Done => TaskStatus::Archived,
-                TaskStatus::Archived => TaskStatus::Todo,
-            };
-            task.updated_at = Utc::now();
-            self.storage.save_task(&task)?;
-            let _ = self.lua_config.trigger_hook("on_status_change", Some(&task));
-            self.reload_tasks()?;
+        self.reload_tasks()?;
+        if let Some(first) = pasted.first() {
+            self.select_task(first.id);
         }
         Ok(())
     }
 
+    /// `za`: folds the selected task's subtasks, or collapses its project
+    /// when it has none.
     pub fn toggle_collapse(&mut self) -> Result<()> {
+        if self.toggle_fold()? {
+            return Ok(());
+        }
         if let Some(task) = self.tasks.get(self.selected_index) {
             if let Some(project) = &task.project {
                 if self.collapsed_projects.contains(project) {
@@ -399,6 +582,9 @@ impl AppState {
         self.filter_string = Some(format!("project={}", projects[next_idx]));
         self.reload_tasks()?;
         self.selected_index = 0;
+        if let Some(idx) = self.active_sidebar_index() {
+            self.sidebar_index = idx;
+        }
         Ok(())
     }
 
@@ -420,6 +606,9 @@ impl AppState {
         self.filter_string = Some(format!("project={}", projects[prev_idx]));
         self.reload_tasks()?;
         self.selected_index = 0;
+        if let Some(idx) = self.active_sidebar_index() {
+            self.sidebar_index = idx;
+        }
         Ok(())
     }
 
@@ -454,6 +643,10 @@ impl AppState {
     }
 
     pub fn handle_action(&mut self, action: Action) -> Result<()> {
+        if self.focus == Focus::Sidebar && self.handle_sidebar_action(action)? {
+            return Ok(());
+        }
+
         match action {
             Action::Quit => self.running = false,
             Action::MoveDown => self.move_selection_down(),
@@ -462,10 +655,22 @@ impl AppState {
             Action::MoveToBottom => self.move_to_bottom(),
             Action::PageDown => self.page_down(),
             Action::PageUp => self.page_up(),
-            Action::Delete => self.delete_selected_task()?,
-            Action::CycleStatus => self.cycle_status()?,
-            Action::IncreasePriority => self.increase_priority()?,
-            Action::DecreasePriority => self.decrease_priority()?,
+            Action::Delete => {
+                self.delete_selected_task()?;
+                self.record_change(Change::Delete);
+            }
+            Action::CycleStatus => {
+                self.cycle_status()?;
+                self.record_change(Change::CycleStatus);
+            }
+            Action::IncreasePriority => {
+                self.increase_priority()?;
+                self.record_change(Change::ShiftPriority(1));
+            }
+            Action::DecreasePriority => {
+                self.decrease_priority()?;
+                self.record_change(Change::ShiftPriority(-1));
+            }
             Action::EnterInsert => {
                 if self.tasks.is_empty() {
                     self.mode = Mode::Insert;
@@ -482,15 +687,22 @@ impl AppState {
                 self.mode = Mode::Insert;
                 self.insert_action = InsertAction::AddAbove;
             }
-            Action::EnterVisual => {
-                self.mode = Mode::Visual;
-                self.selection_anchor = Some(self.selected_index);
-            }
-            Action::EnterCommand => {
-                self.mode = Mode::Command;
-                self.command_buffer.clear();
-            }
+            Action::EnterVisual => self.toggle_visual(Mode::Visual),
+            Action::EnterVisualLine => self.toggle_visual(Mode::VisualLine),
+            Action::ToggleMark => self.toggle_mark(),
+            Action::EnterCommand => self.prompt_command(""),
+            Action::PromptStatus => self.prompt_command("status "),
+            Action::PromptProject => self.prompt_command("project "),
+            Action::PromptTags => self.prompt_command("tag "),
+            Action::PromptDue => self.prompt_command("due "),
+            Action::RecordMacro => self.toggle_recording()?,
+            Action::PlayMacro => self.pending_at = true,
             Action::Cancel => {
+                // Esc in Normal mode drops the marks
+                if self.mode == Mode::Normal {
+                    self.marked_tasks.clear();
+                }
+                self.remember_visual();
                 self.mode = Mode::Normal;
                 self.selection_anchor = None;
                 self.editing_task_id = None;
@@ -500,57 +712,37 @@ impl AppState {
             Action::ToggleCollapse => self.toggle_collapse()?,
             Action::NextProject => self.next_project()?,
             Action::PrevProject => self.prev_project()?,
-            Action::Yank => self.yank_selected(),
-            Action::Paste => self.paste_below()?,
-            Action::EnterSearch => {
-                self.mode = Mode::Search;
-                self.command_buffer.clear();
-            }
-        }
-        Ok(())
-    }
-
-    pub fn execute_command(&mut self, cmd: &str) -> Result<()> {
-        match cmd {
-            "q" => self.running = false,
-            "wq" => {
-                self.running = false;
-            }
-            "w" => {
-                // Already persisted on every change for now, but could be batched later
-            }
-            "sort priority" => {
-                self.sort_by = SortBy::Priority;
-                self.reload_tasks()?;
-            }
-            "sort created" => {
-                self.sort_by = SortBy::CreatedAt;
-                self.reload_tasks()?;
-            }
-            "sort position" => {
-                self.sort_by = SortBy::Position;
-                self.reload_tasks()?;
-            }
-            "stats" => {
-                self.mode = Mode::Stats;
-            }
-            _ => {
-                if cmd.starts_with("lua ") {
-                    let code = &cmd[4..];
-                    let _ = self.lua_config.run_code(code);
-                } else if cmd.starts_with("filter ") {
-                    let filter_part = &cmd[7..];
-                    if filter_part.trim().is_empty() {
-                         self.filter_string = None;
-                    } else {
-                         self.filter_string = Some(filter_part.to_string());
-                    }
-                    self.reload_tasks()?;
-                } else if cmd == "filter" {
-                     self.filter_string = None;
-                     self.reload_tasks()?;
-                }
+            Action::Yank => self.yank_selected()?,
+            Action::Paste | Action::PasteAbove => {
+                let above = action == Action::PasteAbove;
+                let register = self.active_register.unwrap_or(UNNAMED);
+                self.paste(above)?;
+                self.record_change(Change::Paste { above, register });
             }
+            Action::RepeatChange => self.repeat_change(None)?,
+            Action::EnterSearch => self.start_search(false),
+            Action::EnterSearchBackward => self.start_search(true),
+            Action::SearchNext => self.search_next(false),
+            Action::SearchPrev => self.search_next(true),
+            Action::SearchProject => self.search_project(false),
+            Action::SearchProjectBackward => self.search_project(true),
+            Action::EnterFilterSearch => self.start_filter_search(),
+            Action::FindTasks => self.open_picker(PickerSource::Tasks)?,
+            Action::CommandPalette => self.open_picker(PickerSource::Commands)?,
+            Action::StartTimer => self.start_timer()?,
+            Action::StopTimer => self.stop_timer()?,
+            Action::ShowDetail => self.open_detail()?,
+            Action::TogglePomodoro => match self.pomodoro {
+                Some(_) => self.stop_pomodoro(),
+                None => self.start_pomodoro()?,
+            },
+            Action::MoveTaskDown => self.move_selected(true)?,
+            Action::MoveTaskUp => self.move_selected(false)?,
+            Action::Indent => self.indent_selected()?,
+            Action::Outdent => self.outdent_selected()?,
+            Action::FocusSidebar => self.focus_sidebar(),
+            Action::FocusTasks => self.focus_tasks(),
+            Action::SidebarApply => self.apply_sidebar_selection()?,
         }
         Ok(())
     }
@@ -0,0 +1,194 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::timeline::{self, StatusEvent};
+use crate::domain::{Task, TaskStatus};
+use crate::error::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Days covered by the daily completion sparkline.
+pub const STATS_DAYS: usize = 30;
+/// Weeks covered by the weekly completion bar chart.
+pub const STATS_WEEKS: usize = 8;
+/// Days covered by each project's burndown.
+pub const BURNDOWN_DAYS: usize = 14;
+
+/// Open and done counts for one tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagStats {
+    pub tag: String,
+    pub open: usize,
+    pub done: usize,
+}
+
+/// Everything the stats view shows, computed from every task in the
+/// database and its status events, never from the filtered task list.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub total: usize,
+    pub todo: usize,
+    pub doing: usize,
+    pub done: usize,
+    pub archived: usize,
+    /// Open tasks whose due date has passed.
+    pub overdue: usize,
+    pub due_today: usize,
+    /// Completions per local day, oldest first, ending today.
+    pub daily: Vec<u64>,
+    /// Completions per week (starting Monday) labelled by its start date,
+    /// oldest first, ending with the current week.
+    pub weekly: Vec<(String, u64)>,
+    /// Mean time from creation to the last completion of each done task.
+    pub lead_time: Option<Duration>,
+    /// Mean time from first starting to the last completion of each done
+    /// task that was ever in Doing.
+    pub cycle_time: Option<Duration>,
+    /// Open tasks per project at the end of each of the last days, largest
+    /// projects first.
+    pub burndown: Vec<(String, Vec<u64>)>,
+    pub tags: Vec<TagStats>,
+    /// Finished pomodoros per task title, most first.
+    pub pomodoros: Vec<(String, usize)>,
+}
+
+impl Stats {
+    pub fn compute(tasks: &[Task], events: &[StatusEvent], now: DateTime<Utc>) -> Self {
+        let completions = timeline::completions(events);
+        let mut stats = Stats {
+            total: tasks.len(),
+            ..Default::default()
+        };
+        let today = now.with_timezone(&Local).date_naive();
+        for task in tasks {
+            match task.status {
+                TaskStatus::Todo => stats.todo += 1,
+                TaskStatus::Doing => stats.doing += 1,
+                TaskStatus::Done => stats.done += 1,
+                TaskStatus::Archived => stats.archived += 1,
+            }
+            if let (true, Some(due)) = (is_open(task), task.due_date) {
+                if due < now {
+                    stats.overdue += 1;
+                }
+                if due.with_timezone(&Local).date_naive() == today {
+                    stats.due_today += 1;
+                }
+            }
+        }
+
+        let day_of = |at: &DateTime<Utc>| at.with_timezone(&Local).date_naive();
+        stats.daily = (0..STATS_DAYS)
+            .rev()
+            .map(|back| {
+                let day = today - Duration::days(back as i64);
+                completions.iter().filter(|(_, at)| day_of(at) == day).count() as u64
+            })
+            .collect();
+        let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        stats.weekly = (0..STATS_WEEKS)
+            .rev()
+            .map(|back| {
+                let start = this_week - Duration::weeks(back as i64);
+                let end = start + Duration::weeks(1);
+                let count = completions.iter().filter(|(_, at)| (start..end).contains(&day_of(at))).count();
+                (start.format("%m-%d").to_string(), count as u64)
+            })
+            .collect();
+
+        // The last completion of each task that is still done
+        let mut completed: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+        for (id, at) in &completions {
+            completed.insert(*id, *at);
+        }
+        let closed_at = |task: &Task| if is_open(task) { None } else { completed.get(&task.id).copied() };
+        let lead_times: Vec<Duration> = tasks
+            .iter()
+            .filter_map(|t| closed_at(t).map(|at| at - t.created_at))
+            .collect();
+        stats.lead_time = mean(&lead_times);
+        let cycle_times: Vec<Duration> = tasks
+            .iter()
+            .filter_map(|t| Some(closed_at(t)? - timeline::started_at(events, t.id)?))
+            .collect();
+        stats.cycle_time = mean(&cycle_times);
+
+        let mut projects: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+        for task in tasks {
+            if let Some(project) = task.project.as_deref() {
+                projects.entry(project).or_default().push(task);
+            }
+        }
+        stats.burndown = projects
+            .into_iter()
+            .map(|(project, tasks)| {
+                let remaining = (0..BURNDOWN_DAYS)
+                    .rev()
+                    .map(|back| {
+                        let day = today - Duration::days(back as i64);
+                        tasks.iter().filter(|t| open_on(t, closed_at(t), day)).count() as u64
+                    })
+                    .collect();
+                (project.to_string(), remaining)
+            })
+            .collect();
+        stats.burndown.sort_by(|a, b| b.1.iter().max().cmp(&a.1.iter().max()).then(a.0.cmp(&b.0)));
+
+        let mut tags: BTreeMap<&str, TagStats> = BTreeMap::new();
+        for task in tasks {
+            for tag in &task.tags {
+                let entry = tags.entry(tag).or_insert_with(|| TagStats { tag: tag.clone(), open: 0, done: 0 });
+                if is_open(task) {
+                    entry.open += 1;
+                } else {
+                    entry.done += 1;
+                }
+            }
+        }
+        stats.tags = tags.into_values().collect();
+        stats.tags.sort_by(|a, b| (b.open + b.done).cmp(&(a.open + a.done)).then(a.tag.cmp(&b.tag)));
+        stats
+    }
+
+    pub fn completion_rate(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.done + self.archived) as f64 / self.total as f64 * 100.0
+    }
+}
+
+fn mean(durations: &[Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    let sum = durations.iter().fold(Duration::zero(), |sum, d| sum + (*d).max(Duration::zero()));
+    Some(sum / durations.len() as i32)
+}
+
+fn is_open(task: &Task) -> bool {
+    matches!(task.status, TaskStatus::Todo | TaskStatus::Doing)
+}
+
+/// Whether `task` existed and was not yet closed at the end of `day`.
+fn open_on(task: &Task, closed_at: Option<DateTime<Utc>>, day: NaiveDate) -> bool {
+    let local = |at: DateTime<Utc>| at.with_timezone(&Local).date_naive();
+    local(task.created_at) <= day && closed_at.map(|at| local(at) > day).unwrap_or(is_open(task))
+}
+
+impl AppState {
+    /// `:stats`: computes the statistics of the whole database.
+    pub fn open_stats(&mut self) -> Result<()> {
+        let tasks = self.storage.get_tasks(None)?;
+        let events = self.storage.status_events(None)?;
+        let mut stats = Stats::compute(&tasks, &events, Utc::now());
+        self.refresh_pomodoro_counts()?;
+        stats.pomodoros = tasks
+            .iter()
+            .filter_map(|t| self.pomodoro_counts.get(&t.id).map(|n| (t.title.clone(), *n)))
+            .collect();
+        stats.pomodoros.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        self.stats = Some(stats);
+        self.mode = Mode::Stats;
+        Ok(())
+    }
+}
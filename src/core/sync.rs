@@ -0,0 +1,101 @@
+use crate::core::state::AppState;
+use crate::error::Result;
+use crate::storage::todotxt;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// The todo.txt file kept in sync with the database (`set.todotxt`) and
+/// what was last written to it.
+#[derive(Debug, Clone)]
+pub struct TodoTxtSync {
+    pub path: String,
+    pub written: String,
+    pub modified: Option<SystemTime>,
+    /// The tasks in the file as last written or read.
+    pub ids: HashSet<Uuid>,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl AppState {
+    /// Called by the event loop. Starts syncing when `set.todotxt` names a
+    /// file (reading it first if it exists) and reads it again when another
+    /// program changed it.
+    pub fn sync_todotxt(&mut self) -> Result<()> {
+        let Some(path) = self.config.todotxt.clone() else {
+            self.todotxt_sync = None;
+            return Ok(());
+        };
+        match &self.todotxt_sync {
+            Some(sync) if sync.path == path => {
+                if modified(&path) != sync.modified && Path::new(&path).exists() {
+                    self.read_todotxt(true)?;
+                }
+            }
+            _ => {
+                self.todotxt_sync = Some(TodoTxtSync { path: path.clone(), written: String::new(), modified: None, ids: HashSet::new() });
+                if Path::new(&path).exists() {
+                    self.read_todotxt(false)?;
+                }
+                self.write_todotxt()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the synced file into the database: changed lines update their
+    /// task and new lines add tasks. With `deletions`, tasks whose line was
+    /// removed since the file was last written go to the trash.
+    fn read_todotxt(&mut self, deletions: bool) -> Result<()> {
+        let Some(path) = self.todotxt_sync.as_ref().map(|s| s.path.clone()) else {
+            return Ok(());
+        };
+        let text = std::fs::read_to_string(&path)?;
+        let mut export = self.todotxt_document(&text)?;
+        let ids: HashSet<Uuid> = export.tasks.iter().map(|t| t.id).collect();
+        let removed: Vec<Uuid> = match &self.todotxt_sync {
+            Some(sync) if deletions => sync.ids.difference(&ids).copied().collect(),
+            _ => Vec::new(),
+        };
+        if let Some(sync) = self.todotxt_sync.as_mut() {
+            sync.modified = modified(&path);
+            sync.written = text;
+            sync.ids = ids;
+        }
+        self.place_imported(&mut export.tasks)?;
+        let summary = self.import_document(export)?;
+        let removed: Vec<Uuid> = removed.into_iter().filter(|id| self.storage.get_task(*id).ok().flatten().is_some()).collect();
+        if !removed.is_empty() {
+            self.delete_tasks(&removed)?;
+        }
+        if summary.added + summary.updated + removed.len() > 0 {
+            self.info(format!("{}: {} new, {} updated, {} deleted", path, summary.added, summary.updated, removed.len()));
+        }
+        Ok(())
+    }
+
+    /// Writes every task to the synced file when it changed. Called after
+    /// each change to the tasks.
+    pub fn write_todotxt(&mut self) -> Result<()> {
+        let Some(sync) = &self.todotxt_sync else {
+            return Ok(());
+        };
+        let export = self.export_document(None)?;
+        let text = todotxt::export(&export.tasks, &export.status_events);
+        if text == sync.written {
+            return Ok(());
+        }
+        std::fs::write(&sync.path, &text)?;
+        let path = sync.path.clone();
+        if let Some(sync) = self.todotxt_sync.as_mut() {
+            sync.modified = modified(&path);
+            sync.ids = export.tasks.iter().map(|t| t.id).collect();
+            sync.written = text;
+        }
+        Ok(())
+    }
+}
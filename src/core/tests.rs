@@ -9,7 +9,7 @@ mod tests {
         let tmp_file = NamedTempFile::new().unwrap();
         let path = tmp_file.path().to_str().unwrap();
         let storage = SqliteStorage::new(path).unwrap();
-        let lua_config = std::sync::Arc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
         let mut state = AppState::new(storage, lua_config).unwrap();
 
         state.add_task("Task 1".to_string()).unwrap();
@@ -18,7 +18,7 @@ mod tests {
         assert_eq!(state.tasks.len(), 2);
         assert_eq!(state.tasks[0].title, "Task 1");
         assert_eq!(state.tasks[1].title, "Task 2");
-        assert_eq!(state.tasks[1].position, state.tasks[0].position + 1);
+        assert_eq!(state.tasks[1].position, state.tasks[0].position + crate::core::position::POSITION_GAP);
 
         state.selected_index = 0;
         state.add_task_below("Task 1.5".to_string()).unwrap();
@@ -33,7 +33,7 @@ mod tests {
         let tmp_file = NamedTempFile::new().unwrap();
         let path = tmp_file.path().to_str().unwrap();
         let storage = SqliteStorage::new(path).unwrap();
-        let lua_config = std::sync::Arc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
         let mut state = AppState::new(storage, lua_config).unwrap();
 
         state.add_task("Original Title".to_string()).unwrap();
@@ -58,7 +58,7 @@ mod tests {
         let tmp_file = NamedTempFile::new().unwrap();
         let path = tmp_file.path().to_str().unwrap();
         let storage = SqliteStorage::new(path).unwrap();
-        let lua_config = std::sync::Arc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
         let mut state = AppState::new(storage, lua_config).unwrap();
 
         state.add_task("Initial Task".to_string()).unwrap();
@@ -81,4 +81,1521 @@ mod tests {
         state.undo().unwrap();
         assert_eq!(state.tasks[0].priority, 3);
     }
+
+    #[test]
+    fn test_sidebar_counts_and_filtering() {
+        use crate::core::actions::Action;
+        use crate::core::sidebar::{Focus, SidebarItem};
+        use crossterm::event::{KeyCode, KeyEvent};
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+
+        state.add_task("Write report".to_string()).unwrap();
+        state.add_task("Fix bike".to_string()).unwrap();
+        state.add_task("Call bank".to_string()).unwrap();
+        for (i, (project, tag)) in [("work", "urgent"), ("home", "urgent"), ("work", "phone")].iter().enumerate() {
+            let mut task = state.tasks[i].clone();
+            task.project = Some(project.to_string());
+            task.tags = vec![tag.to_string()];
+            state.storage.save_task(&task).unwrap();
+        }
+        state.reload_tasks().unwrap();
+
+        let items: Vec<(SidebarItem, usize)> = state.sidebar.iter().map(|e| (e.item.clone(), e.count)).collect();
+        assert_eq!(items, vec![
+            (SidebarItem::All, 3),
+            (SidebarItem::Project("home".into()), 1),
+            (SidebarItem::Project("work".into()), 2),
+            (SidebarItem::Tag("phone".into()), 1),
+            (SidebarItem::Tag("urgent".into()), 2),
+        ]);
+
+        state.handle_action(Action::FocusSidebar).unwrap();
+        assert_eq!(state.focus, Focus::Sidebar);
+        state.handle_action(Action::MoveToBottom).unwrap();
+        state.handle_key(KeyEvent::from(KeyCode::Enter)).unwrap();
+        assert_eq!(state.filter_string.as_deref(), Some("tag=urgent"));
+        assert_eq!(state.tasks.len(), 2);
+        assert_eq!(state.active_sidebar_index(), Some(4));
+
+        // Applying an entry has its own action, so remapping it leaves
+        // status cycling alone
+        state.lua_config.run_code(r#"map("sidebar", "l", "sidebar_apply")"#).unwrap();
+        state.reload_config();
+        state.handle_action(Action::MoveToTop).unwrap();
+        state.handle_key(KeyEvent::from(KeyCode::Char('l'))).unwrap();
+        assert_eq!(state.filter_string, None);
+
+        // Archived tasks only count when they are shown
+        let report = state.tasks.iter().find(|t| t.title == "Write report").unwrap().id;
+        state.update_tasks(&[report], "on_status_change", |task| {
+            task.status = crate::domain::TaskStatus::Archived;
+            true
+        }).unwrap();
+        let counts = |state: &AppState| state.sidebar.iter().map(|e| e.count).collect::<Vec<_>>();
+        assert_eq!(counts(&state), vec![2, 1, 1, 1, 1]);
+        state.config.show_archived = true;
+        state.refresh_sidebar().unwrap();
+        assert_eq!(counts(&state), vec![3, 1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn test_subtask_tree_fold_and_subtree_undo() {
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+
+        for title in ["Release", "Write notes", "Tag build", "Publish", "Unrelated"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        let release = state.tasks[0].id;
+
+        // Indent "Write notes" and "Tag build" under "Release", then nest "Publish" under "Tag build"
+        for i in [1, 2, 3, 3] {
+            state.selected_index = i;
+            state.indent_selected().unwrap();
+        }
+        let titles: Vec<&str> = state.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Release", "Write notes", "Tag build", "Publish", "Unrelated"]);
+        let depths: Vec<usize> = state.tasks.iter().map(|t| state.tree[&t.id].depth).collect();
+        assert_eq!(depths, vec![0, 1, 1, 2, 0]);
+
+        // Progress rolls up through every level
+        let mut publish = state.tasks[3].clone();
+        publish.status = TaskStatus::Done;
+        state.storage.save_task(&publish).unwrap();
+        state.reload_tasks().unwrap();
+        assert_eq!((state.tree[&release].done, state.tree[&release].total), (1, 3));
+
+        // Folding hides the whole subtree
+        state.selected_index = 0;
+        state.toggle_collapse().unwrap();
+        assert_eq!(state.tasks.len(), 2);
+        state.toggle_collapse().unwrap();
+        assert_eq!(state.tasks.len(), 5);
+
+        // Deleting a parent removes its subtree; a single undo brings it all back
+        state.selected_index = 0;
+        state.delete_selected_task().unwrap();
+        assert_eq!(state.tasks.len(), 1);
+        state.undo().unwrap();
+        assert_eq!(state.tasks.len(), 5);
+        assert_eq!(state.tree[&release].total, 3);
+        state.redo().unwrap();
+        assert_eq!(state.tasks.len(), 1);
+
+        // Outdenting moves a subtask right below its former parent
+        state.undo().unwrap();
+        state.selected_index = 3;
+        state.outdent_selected().unwrap();
+        assert_eq!(state.tasks[3].title, "Publish");
+        assert_eq!(state.tree[&state.tasks[3].id].depth, 1);
+    }
+
+    #[test]
+    fn test_reordering_uses_sparse_positions() {
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+
+        for title in ["A", "B", "C", "D"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        let titles = |state: &AppState| state.tasks.iter().map(|t| t.title.clone()).collect::<Vec<_>>().join("");
+
+        // Inserting between two tasks only writes the new row
+        let before: Vec<i32> = state.tasks.iter().map(|t| t.position).collect();
+        state.selected_index = 0;
+        state.add_task_below("A2".to_string()).unwrap();
+        assert_eq!(titles(&state), "AA2BCD");
+        let after: Vec<i32> = state.tasks.iter().filter(|t| t.title != "A2").map(|t| t.position).collect();
+        assert_eq!(before, after);
+
+        // J/K swap with the neighbouring sibling and undo as one step
+        state.selected_index = 0;
+        state.move_selected(true).unwrap();
+        assert_eq!(titles(&state), "A2ABCD");
+        assert_eq!(state.tasks[1].title, "A");
+        assert_eq!(state.selected_index, 1);
+        state.undo().unwrap();
+        assert_eq!(titles(&state), "AA2BCD");
+
+        // >> and << only write the moved task
+        let others = |state: &AppState| {
+            state.storage.get_tasks(None).unwrap().into_iter().filter(|t| t.title != "B").map(|t| (t.id, t.position)).collect::<Vec<_>>()
+        };
+        let before = others(&state);
+        state.selected_index = 2;
+        state.indent_selected().unwrap();
+        assert_eq!(state.tasks[2].parent_id, Some(state.tasks[1].id));
+        state.outdent_selected().unwrap();
+        assert_eq!(titles(&state), "AA2BCD");
+        assert!(state.tasks[2].parent_id.is_none());
+        assert_eq!(others(&state), before);
+
+        // ddp: cutting a task and pasting it below the next one swaps them,
+        // keeping the original id
+        state.selected_index = 2;
+        let b_id = state.tasks[2].id;
+        state.delete_selected_task().unwrap();
+        assert_eq!(titles(&state), "AA2CD");
+        state.selected_index = 2;
+        state.paste(false).unwrap();
+        assert_eq!(titles(&state), "AA2CBD");
+        assert_eq!(state.tasks[3].id, b_id);
+
+        // A second paste is a copy with a fresh id
+        state.paste(false).unwrap();
+        assert_eq!(titles(&state), "AA2CBBD");
+        assert_ne!(state.tasks[4].id, b_id);
+
+        // Exhausted gaps trigger a one-off respacing of the siblings
+        for _ in 0..12 {
+            state.selected_index = 0;
+            state.add_task_below("x".to_string()).unwrap();
+        }
+        assert_eq!(state.tasks[0].title, "A");
+        assert_eq!(state.tasks.last().unwrap().title, "D");
+        let positions: Vec<i32> = state.tasks.iter().map(|t| t.position).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_named_registers_and_clipboard() {
+        use crate::core::clipboard::MemoryClipboard;
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        state.clipboard = Box::new(MemoryClipboard::default());
+
+        for title in ["A", "B", "C"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        let titles = |state: &AppState| state.tasks.iter().map(|t| t.title.clone()).collect::<Vec<_>>().join("");
+
+        // "ayy then "Ayy appends to register a
+        state.selected_index = 0;
+        state.select_register('a').unwrap();
+        state.yank_selected().unwrap();
+        state.selected_index = 1;
+        state.select_register('A').unwrap();
+        state.yank_selected().unwrap();
+        assert_eq!(state.registers[&'a'].len(), 2);
+
+        // A plain delete goes to the unnamed register and leaves "a alone
+        state.selected_index = 2;
+        state.delete_selected_task().unwrap();
+        assert_eq!(state.registers[&'"'][0].title, "C");
+
+        // "aP pastes both tasks above the selection, in order
+        state.selected_index = 0;
+        state.select_register('a').unwrap();
+        state.paste(true).unwrap();
+        assert_eq!(titles(&state), "ABAB");
+
+        // Visual y yanks a range into the clipboard register as an outline
+        state.selected_index = 2;
+        state.mode = Mode::Visual;
+        state.selection_anchor = Some(3);
+        state.select_register('+').unwrap();
+        state.yank_selected().unwrap();
+        assert_eq!(state.mode, Mode::Normal);
+        assert_eq!(state.clipboard.get_text().unwrap().as_deref(), Some("A\nB"));
+
+        // Pasting from + parses indented lines into subtasks
+        state.clipboard.set_text("Trip\n  Book hotel\n  Pack").unwrap();
+        state.selected_index = 3;
+        state.select_register('+').unwrap();
+        state.paste(false).unwrap();
+        assert_eq!(titles(&state), "ABABTripBook hotelPack");
+        assert_eq!(state.tree[&state.tasks[5].id].depth, 1);
+    }
+
+    #[test]
+    fn test_osc52_round_trip() {
+        use crate::core::clipboard::{osc52_copy_sequence, parse_osc52_reply};
+
+        let seq = osc52_copy_sequence("Buy milk\n  2% ✓");
+        assert!(seq.starts_with("\x1b]52;c;") && seq.ends_with('\x07'));
+        assert_eq!(parse_osc52_reply(&seq).as_deref(), Some("Buy milk\n  2% ✓"));
+        assert_eq!(parse_osc52_reply("\x1b]52;c;QQ==").as_deref(), Some("A"));
+        assert_eq!(parse_osc52_reply("garbage"), None);
+    }
+
+    #[test]
+    fn test_visual_bulk_operations_and_marks() {
+        use crate::core::actions::Action;
+        use crate::core::Mode;
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+
+        for title in ["A", "B", "C", "D"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        let titles = |state: &AppState| state.tasks.iter().map(|t| t.title.clone()).collect::<Vec<_>>().join("");
+
+        // `vj` then Enter moves both tasks on to Doing as one undo step
+        state.selected_index = 0;
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::MoveDown).unwrap();
+        state.handle_action(Action::CycleStatus).unwrap();
+        assert_eq!(state.mode, Mode::Normal);
+        assert!(state.tasks[..2].iter().all(|t| t.status == TaskStatus::Doing));
+        assert_eq!(state.tasks[2].status, TaskStatus::Todo);
+        state.undo().unwrap();
+        assert!(state.tasks.iter().all(|t| t.status == TaskStatus::Todo));
+
+        // Commands opened from Visual mode apply to the range
+        state.selected_index = 1;
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::MoveDown).unwrap();
+        state.handle_action(Action::PromptProject).unwrap();
+        assert_eq!(state.mode, Mode::Command);
+        state.execute_command("project home").unwrap();
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::MoveUp).unwrap();
+        state.handle_action(Action::EnterCommand).unwrap();
+        state.execute_command("tag +errand -nothing").unwrap();
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::EnterCommand).unwrap();
+        state.execute_command("due 2030-01-02").unwrap();
+        let project: Vec<_> = state.tasks.iter().map(|t| t.project.as_deref()).collect();
+        assert_eq!(project, [None, Some("home"), Some("home"), None]);
+        assert!(state.tasks[0].tags.is_empty());
+        assert_eq!(state.tasks[1].tags, ["errand"]);
+        assert_eq!(state.tasks[2].tags, ["errand"]);
+        assert!(state.tasks[0].due_date.is_none());
+        assert_eq!(state.tasks[1].due_date.unwrap().format("%Y-%m-%d").to_string(), "2030-01-02");
+        assert!(state.command_targets.is_none());
+
+        // Invalid values are rejected without touching anything
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::EnterCommand).unwrap();
+        assert!(state.execute_command("status someday").is_err());
+        assert!(state.command_targets.is_none());
+        state.mode = Mode::Normal;
+
+        // Marks pick non-contiguous tasks; `J` moves them as a block
+        state.selected_index = 0;
+        state.handle_action(Action::ToggleMark).unwrap();
+        state.selected_index = 2;
+        state.handle_action(Action::ToggleMark).unwrap();
+        state.handle_action(Action::IncreasePriority).unwrap();
+        assert_eq!(state.tasks[0].priority, 4);
+        assert_eq!(state.tasks[1].priority, 3);
+        assert_eq!(state.tasks[2].priority, 4);
+        state.handle_action(Action::MoveTaskDown).unwrap();
+        assert_eq!(titles(&state), "BADC");
+        state.undo().unwrap();
+        assert_eq!(titles(&state), "ABCD");
+        state.handle_action(Action::Cancel).unwrap();
+        assert!(state.marked_tasks.is_empty());
+
+        // A Visual block moves together and stays selected
+        state.selected_index = 1;
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::MoveDown).unwrap();
+        state.handle_action(Action::MoveTaskUp).unwrap();
+        assert_eq!(titles(&state), "BCAD");
+        assert_eq!(state.selected_ids().len(), 2);
+        state.handle_action(Action::MoveTaskUp).unwrap();
+        assert_eq!(titles(&state), "BCAD");
+
+        // Visual-Line includes subtasks; `y` yanks the range
+        state.handle_action(Action::Cancel).unwrap();
+        state.selected_index = 3;
+        state.indent_selected().unwrap();
+        state.folded_tasks.insert(state.tasks[2].id);
+        state.reload_tasks().unwrap();
+        state.selected_index = 2;
+        state.handle_action(Action::EnterVisualLine).unwrap();
+        state.set_status(TaskStatus::Done).unwrap();
+        assert!(state.storage.get_tasks(None).unwrap().iter().filter(|t| t.title == "A" || t.title == "D").all(|t| t.status == TaskStatus::Done));
+
+        let export = NamedTempFile::new().unwrap();
+        state.selected_index = 0;
+        state.handle_action(Action::EnterVisual).unwrap();
+        state.handle_action(Action::MoveDown).unwrap();
+        state.handle_action(Action::EnterCommand).unwrap();
+        state.execute_command(&format!("export {}", export.path().display())).unwrap();
+        let exported = crate::storage::json::Export::from_json(&std::fs::read_to_string(export.path()).unwrap()).unwrap().tasks;
+        assert_eq!(exported.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), ["B", "C"]);
+    }
+
+    #[test]
+    fn test_keymap_keeps_key_case() {
+        use crate::core::actions::Action;
+        use crate::core::keymap::Keymap;
+        use crate::core::Mode;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let keymap = Keymap::new();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(keymap.get_action(Mode::Normal, key('j')), Some(Action::MoveDown));
+        assert_eq!(keymap.get_action(Mode::Normal, key('J')), Some(Action::MoveTaskDown));
+        assert_eq!(keymap.get_action(Mode::Normal, key('V')), Some(Action::EnterVisualLine));
+        assert_eq!(keymap.get_action(Mode::VisualLine, key('d')), Some(Action::Delete));
+    }
+
+    #[test]
+    fn test_macros_replay_through_input_pipeline() {
+        use crate::core::keymap::{keys_to_notation, parse_key_notation};
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let new_state = || {
+            let storage = SqliteStorage::new(path).unwrap();
+            let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+            AppState::new(storage, lua_config).unwrap()
+        };
+        let mut state = new_state();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+
+        // Record: add a task below, type its title, jump back to the top
+        feed(&mut state, "ixx<CR>");
+        feed(&mut state, "qaoNew<CR>ggq");
+        assert_eq!(state.tasks.len(), 2);
+        assert_eq!(state.macro_recording, None);
+        assert_eq!(keys_to_notation(&state.macros[&'a']), "oNew<CR>gg");
+
+        feed(&mut state, "3@a");
+        assert_eq!(state.tasks.len(), 5);
+        assert_eq!(state.selected_index, 0);
+        feed(&mut state, "@@");
+        assert_eq!(state.tasks.len(), 6);
+        assert_eq!(state.mode, Mode::Normal);
+
+        // Macros and named registers survive a restart
+        feed(&mut state, "\"byy");
+        drop(state);
+        let mut state = new_state();
+        assert_eq!(keys_to_notation(&state.macros[&'a']), "oNew<CR>gg");
+        assert_eq!(state.registers[&'b'][0].title, "xx");
+        feed(&mut state, "@a");
+        assert_eq!(state.tasks.len(), 7);
+
+        // A macro that replays itself stops instead of recursing forever
+        feed(&mut state, "qcj@cq");
+        feed(&mut state, "gg@c");
+        assert_eq!(state.selected_index, state.tasks.len() - 1);
+
+        // Counts repeat motions and `5G` jumps to a line
+        feed(&mut state, "gg2j");
+        assert_eq!(state.selected_index, 2);
+        feed(&mut state, "5G");
+        assert_eq!(state.selected_index, 4);
+
+        assert_eq!(keys_to_notation(&parse_key_notation("a<lt>b<C-r><Esc><Space>")), "a<lt>b<C-r><Esc><Space>");
+        assert_eq!(keys_to_notation(&parse_key_notation("<nope>")), "<lt>nope>");
+    }
+
+    #[test]
+    fn test_dot_repeats_last_change() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        for title in ["A", "B", "C", "D"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+
+        // A counted change repeats with its count, or with a new one
+        feed(&mut state, "gg2-j.");
+        assert_eq!(state.tasks[0].priority, 1);
+        assert_eq!(state.tasks[1].priority, 1);
+        feed(&mut state, "j1.");
+        assert_eq!(state.tasks[2].priority, 2);
+
+        // `.` after a Visual change applies to the new selection as one undo step
+        feed(&mut state, "gg<CR>jvj.");
+        assert_eq!(state.tasks[0].status, TaskStatus::Doing);
+        assert_eq!(state.tasks[1].status, TaskStatus::Doing);
+        assert_eq!(state.tasks[2].status, TaskStatus::Doing);
+        state.undo().unwrap();
+        assert_eq!(state.tasks[1].status, TaskStatus::Todo);
+        assert_eq!(state.tasks[2].status, TaskStatus::Todo);
+
+        // Ex commands and edits are repeated with their text
+        feed(&mut state, "gg:tag +home<CR>j.");
+        assert_eq!(state.tasks[1].tags, ["home"]);
+        feed(&mut state, "iRenamed<CR>j.");
+        assert_eq!(state.tasks[1].title, "BRenamed");
+        assert_eq!(state.tasks[2].title, "BRenamed");
+        feed(&mut state, "GoNew<CR>2.");
+        assert_eq!(state.tasks.iter().filter(|t| t.title == "New").count(), 3);
+
+        // Delete and paste
+        let before = state.tasks.len();
+        feed(&mut state, "gg d.");
+        assert_eq!(state.tasks.len(), before - 2);
+        feed(&mut state, "p.");
+        assert_eq!(state.tasks.len(), before);
+
+        // A count deletes that many tasks from the cursor as one undo step
+        feed(&mut state, "gg3d");
+        assert_eq!(state.tasks.len(), before - 3);
+        assert_eq!(state.read_register('"').unwrap().len(), 3);
+        feed(&mut state, "u");
+        assert_eq!(state.tasks.len(), before);
+        feed(&mut state, "gg2d.");
+        assert_eq!(state.tasks.len(), before - 4);
+    }
+
+    #[test]
+    fn test_incremental_search_and_filter() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::search::compile_pattern;
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        for title in ["Buy milk", "Call Bob", "Fix bug 12", "Buy bread", "Read C++ book"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 0;
+        state.set_project(Some("home".to_string())).unwrap();
+        state.selected_index = 3;
+        state.set_project(Some("home".to_string())).unwrap();
+
+        // Smart-case, regex, and literal fallback for invalid patterns
+        assert!(compile_pattern("buy").unwrap().is_match("BUY"));
+        assert!(!compile_pattern("Buy").unwrap().is_match("buy"));
+        assert!(compile_pattern(r"bug \d+").unwrap().is_match("Fix bug 12"));
+        assert!(compile_pattern("c++").unwrap().is_match("Read C++ book"));
+
+        // Typing jumps to the first match without filtering; n/N cycle with wrap
+        feed(&mut state, "gg/bu");
+        assert_eq!(state.mode, Mode::Search);
+        assert_eq!(state.selected_index, 2);
+        assert_eq!(state.tasks.len(), 5);
+        feed(&mut state, "y<CR>");
+        assert_eq!(state.selected_index, 3);
+        feed(&mut state, "n");
+        assert_eq!(state.selected_index, 0);
+        feed(&mut state, "N");
+        assert_eq!(state.selected_index, 3);
+        feed(&mut state, "gg2n");
+        assert_eq!(state.selected_index, 0);
+
+        // Esc returns to where the search started; `?` searches upwards
+        feed(&mut state, "G/call<Esc>");
+        assert_eq!(state.selected_index, 4);
+        feed(&mut state, "?b<CR>");
+        assert_eq!(state.selected_index, 3);
+        feed(&mut state, "n");
+        assert_eq!(state.selected_index, 2);
+
+        // `*` jumps between tasks of the selected task's project
+        feed(&mut state, "gg*");
+        assert_eq!(state.selected_index, 3);
+        feed(&mut state, "n");
+        assert_eq!(state.selected_index, 0);
+        feed(&mut state, ":noh<CR>");
+        assert!(state.search.is_none());
+
+        // `g/` filters the list instead
+        feed(&mut state, "g/buy");
+        assert_eq!(state.mode, Mode::Filter);
+        assert_eq!(state.tasks.len(), 2);
+        feed(&mut state, "<CR>");
+        assert_eq!(state.tasks.len(), 2);
+        feed(&mut state, "g/<Esc>");
+        assert_eq!(state.tasks.len(), 5);
+    }
+
+    #[test]
+    fn test_fuzzy_finder_and_command_palette() {
+        use crate::core::fuzzy::fuzzy_match;
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::picker::{PickerItem, PickerSource};
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+
+        // Word starts and consecutive runs beat scattered matches
+        let (tight, positions) = fuzzy_match("wr", "Write report").unwrap();
+        assert_eq!(positions, vec![0, 1]);
+        let (loose, _) = fuzzy_match("wr", "Water the flowers").unwrap();
+        assert!(tight > loose);
+        assert!(fuzzy_match("rw", "Write").is_none());
+        assert!(fuzzy_match("W", "water").is_none());
+
+        for title in ["Water plants", "Write report", "Proofread report", "Pay rent"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 2;
+        feed(&mut state, ">>");
+        assert_eq!(state.tasks[2].parent_id, Some(state.tasks[1].id));
+        state.folded_tasks.insert(state.tasks[1].id);
+        state.execute_command("filter status=done").unwrap();
+        assert!(state.tasks.is_empty());
+
+        // Ctrl-p finds tasks hidden by filters and folds and jumps to them
+        feed(&mut state, "<C-p>proof");
+        assert_eq!(state.mode, Mode::Picker);
+        let picker = state.picker.as_ref().unwrap();
+        assert_eq!(picker.matches.len(), 1);
+        assert_eq!(picker.selected_entry().unwrap().label, "Proofread report");
+        feed(&mut state, "<CR>");
+        assert_eq!(state.mode, Mode::Normal);
+        assert!(state.picker.is_none());
+        assert_eq!(state.tasks[state.selected_index].title, "Proofread report");
+
+        // Esc closes without doing anything
+        feed(&mut state, "<C-p>pay<Esc>");
+        assert_eq!(state.mode, Mode::Normal);
+        assert_eq!(state.tasks[state.selected_index].title, "Proofread report");
+
+        // Lua commands show up in the palette and run as ex commands
+        state.execute_command("lua command('dark', function(args) set.theme(args) end, 'Switch theme')").unwrap();
+        feed(&mut state, "<C-k>dark");
+        let entry = state.picker.as_ref().unwrap().selected_entry().unwrap().clone();
+        assert_eq!(entry.item, PickerItem::LuaCommand("dark".to_string()));
+        assert_eq!(entry.detail, "Switch theme");
+        feed(&mut state, "<Esc>:dark gruvbox<CR>");
+        assert_eq!(state.config.theme, "gruvbox");
+
+        // Commands that take arguments open the command line
+        state.open_picker(PickerSource::Commands).unwrap();
+        feed(&mut state, "priority");
+        feed(&mut state, "<CR>");
+        assert_eq!(state.mode, Mode::Command);
+        assert_eq!(state.command_buffer, "priority ");
+    }
+
+    #[test]
+    fn test_command_line_editing_history_and_completion() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let new_state = || {
+            let storage = SqliteStorage::new(path).unwrap();
+            let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+            AppState::new(storage, lua_config).unwrap()
+        };
+        let mut state = new_state();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        state.add_task("Draft".to_string()).unwrap();
+        state.selected_index = 0;
+        state.set_project(Some("work".to_string())).unwrap();
+        state.edit_tags("+urgent").unwrap();
+
+        // Cursor movement, insertion in the middle and word deletion
+        feed(&mut state, ":sort priorty<Left><Left>i");
+        assert_eq!(state.command_buffer, "sort priority");
+        assert_eq!(state.command_cursor, 11);
+        feed(&mut state, "<End><C-w>");
+        assert_eq!(state.command_buffer, "sort ");
+        feed(&mut state, "<C-w><C-u>");
+        assert_eq!(state.command_buffer, "");
+        feed(&mut state, "<BS>");
+        assert_eq!(state.mode, Mode::Normal);
+
+        // Tab completes command names, then arguments, cycling on repeats
+        feed(&mut state, ":so<Tab>");
+        assert_eq!(state.command_buffer, "sort");
+        feed(&mut state, " p<Tab>");
+        assert_eq!(state.command_buffer, "sort position");
+        feed(&mut state, "<Tab>");
+        assert_eq!(state.command_buffer, "sort priority");
+        feed(&mut state, "<Tab>");
+        assert_eq!(state.command_buffer, "sort p");
+        feed(&mut state, "<S-Tab>");
+        assert_eq!(state.command_buffer, "sort priority");
+        feed(&mut state, "<CR>");
+
+        // Filter fields, operators and values; tags keep their sign
+        feed(&mut state, ":filter pri<Tab>");
+        assert_eq!(state.command_buffer, "filter priority");
+        feed(&mut state, "<Tab>");
+        assert_eq!(state.command_buffer, "filter priority!=");
+        feed(&mut state, "<C-u>filter project=w<Tab>");
+        assert_eq!(state.command_buffer, "filter project=work");
+        feed(&mut state, "<C-u>tag -u<Tab>");
+        assert_eq!(state.command_buffer, "tag -urgent");
+        feed(&mut state, "<Esc>:noh<CR>");
+
+        // Up/Down recall history entries starting with what was typed
+        feed(&mut state, ":<Up>");
+        assert_eq!(state.command_buffer, "noh");
+        feed(&mut state, "<Up>");
+        assert_eq!(state.command_buffer, "sort priority");
+        feed(&mut state, "<Down><Down>");
+        assert_eq!(state.command_buffer, "");
+        feed(&mut state, "so<Up>");
+        assert_eq!(state.command_buffer, "sort priority");
+        feed(&mut state, "<Esc>");
+
+        // History survives a restart and `q:` lists it for re-running
+        drop(state);
+        let mut state = new_state();
+        assert_eq!(state.command_history, vec!["sort priority", "noh"]);
+        feed(&mut state, "q:");
+        assert_eq!(state.mode, Mode::CommandWindow);
+        assert_eq!(state.command_window_index, 1);
+        feed(&mut state, "k<CR>");
+        assert_eq!(state.mode, Mode::Normal);
+        assert_eq!(state.command_history, vec!["noh", "sort priority"]);
+        feed(&mut state, "q:i");
+        assert_eq!(state.mode, Mode::Command);
+        assert_eq!(state.command_buffer, "sort priority");
+    }
+
+    #[test]
+    fn test_errors_become_messages() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::messages::MessageLevel;
+        use crate::core::Mode;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        // Like the TUI loop: errors are reported, never propagated
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                if let Err(err) = state.handle_key(key) {
+                    state.report_error(&err);
+                }
+            }
+        };
+        let level = |state: &AppState| state.message.as_ref().map(|m| m.level);
+
+        feed(&mut state, ":bogus<CR>");
+        assert_eq!(state.mode, Mode::Normal);
+        assert_eq!(level(&state), Some(MessageLevel::Error));
+        assert_eq!(state.message.as_ref().unwrap().text, "Not a command: bogus");
+        // The next key clears the status bar, not the history
+        feed(&mut state, "j");
+        assert!(state.message.is_none());
+
+        feed(&mut state, "u");
+        assert_eq!(level(&state), Some(MessageLevel::Warn));
+        feed(&mut state, ":due someday<CR>");
+        assert_eq!(level(&state), Some(MessageLevel::Error));
+        feed(&mut state, ":lua error('broken')<CR>");
+        assert!(state.message.as_ref().unwrap().text.contains("broken"));
+
+        // A failing hook does not undo the change that triggered it
+        feed(&mut state, ":lua hook('on_task_create', function() error('boom') end)<CR>");
+        feed(&mut state, "iTask<CR>");
+        assert_eq!(state.tasks.len(), 1);
+        assert!(state.message.as_ref().unwrap().text.contains("boom"));
+        feed(&mut state, ":w<CR>");
+        assert_eq!(level(&state), Some(MessageLevel::Info));
+
+        feed(&mut state, ":messages<CR>");
+        assert_eq!(state.mode, Mode::Messages);
+        assert_eq!(state.messages.len(), 6);
+        assert_eq!(state.messages_scroll, 5);
+        feed(&mut state, "q");
+        assert_eq!(state.mode, Mode::Normal);
+        feed(&mut state, ":messages clear<CR>");
+        assert!(state.messages.is_empty());
+    }
+
+    #[test]
+    fn test_ex_ranges_and_global() {
+        use crate::core::ex::{Address, AddressBase, ExCommand, Range};
+        use crate::core::keymap::parse_key_notation;
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        let titles = ["Send invoice", "Call bank urgent", "Invoice follow-up", "Plan trip", "Pay rent urgent"];
+        for title in titles {
+            state.add_task(title.to_string()).unwrap();
+        }
+
+        let line = |n| Address { base: AddressBase::Line(n), offset: 0 };
+        let parsed = ExCommand::parse("1,3 set project=work").unwrap();
+        assert_eq!(parsed.range, Some(Range::Lines(line(1), line(3))));
+        assert_eq!((parsed.name.as_str(), parsed.args.as_str()), ("set", "project=work"));
+        let parsed = ExCommand::parse(".,$-1done").unwrap();
+        assert_eq!(parsed.range, Some(Range::Lines(
+            Address { base: AddressBase::Current, offset: 0 },
+            Address { base: AddressBase::Last, offset: -1 },
+        )));
+        assert_eq!(parsed.name, "done");
+        let global = ExCommand::parse(r"g!/a\/b/ tag +x").unwrap().global.unwrap();
+        assert_eq!((global.pattern.as_str(), global.invert, global.command.as_str()), ("a/b", true, "tag +x"));
+        assert!(ExCommand::parse("'x,3 done").is_err());
+
+        // Ranges act on lines of the displayed list, as one undo step
+        state.execute_command("1,3 set project=work priority=5").unwrap();
+        let projects: Vec<_> = state.tasks.iter().map(|t| t.project.as_deref()).collect();
+        assert_eq!(projects, vec![Some("work"), Some("work"), Some("work"), None, None]);
+        assert_eq!(state.tasks[2].priority, 5);
+        state.undo().unwrap();
+        assert!(state.tasks.iter().all(|t| t.project.is_none() && t.priority != 5));
+
+        // `'<,'>` is the last Visual selection, even after leaving Visual mode
+        feed(&mut state, "ggjvj<Esc>");
+        state.execute_command("'<,'>done").unwrap();
+        let statuses: Vec<_> = state.tasks.iter().map(|t| t.status).collect();
+        assert_eq!(statuses[1..3], [TaskStatus::Done, TaskStatus::Done]);
+        assert_eq!(statuses[0], TaskStatus::Todo);
+        assert!(state.execute_command("4,9 done").is_err());
+        assert!(state.execute_command("1,2 sort priority").is_err());
+
+        // `:g` and `:v` pick the matching (or other) tasks
+        state.execute_command("g/invoice/ tag +finance").unwrap();
+        let tagged: Vec<_> = state.tasks.iter().filter(|t| t.tags.contains(&"finance".to_string())).map(|t| t.title.as_str()).collect();
+        assert_eq!(tagged, vec!["Send invoice", "Invoice follow-up"]);
+        state.execute_command("v/urgent/ archive").unwrap();
+        let archived = state.storage.get_tasks(None).unwrap().iter().filter(|t| t.status == TaskStatus::Archived).count();
+        assert_eq!(archived, 3);
+        // Archived tasks leave the list
+        assert_eq!(state.tasks.len(), 2);
+        state.execute_command("g/urgent/").unwrap();
+        assert_eq!(state.marked_tasks.len(), 2);
+        assert!(state.execute_command("g/nothing/ done").is_err());
+
+        // A bare address jumps to the task
+        feed(&mut state, ":$<CR>");
+        assert_eq!(state.selected_index, 1);
+        feed(&mut state, ":1<CR>");
+        assert_eq!(state.selected_index, 0);
+    }
+
+    #[test]
+    fn test_modify_command() {
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        for title in ["One", "Two", "Three"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 0;
+        state.edit_tags("+old").unwrap();
+
+        // The selected task, then a range; each is one undo step
+        state.execute_command("modify project=Work +finance -old due:2030-01-02 priority:5 recur:weekly").unwrap();
+        let task = &state.tasks[0];
+        assert_eq!(task.project.as_deref(), Some("Work"));
+        assert_eq!(task.tags, vec!["finance"]);
+        assert_eq!(task.priority, 5);
+        assert_eq!(task.recurrence_rule.as_deref(), Some("weekly"));
+        assert_eq!(task.due_date.unwrap().format("%Y-%m-%d").to_string(), "2030-01-02");
+        state.execute_command("2,3 set status=doing description:\"Needs review\"").unwrap();
+        assert!(state.tasks[1..].iter().all(|t| t.status == TaskStatus::Doing && t.description.as_deref() == Some("Needs review")));
+        state.undo().unwrap();
+        assert!(state.tasks[1..].iter().all(|t| t.status == TaskStatus::Todo));
+
+        // An invalid word leaves every task untouched
+        assert!(state.execute_command("%modify project=Home priority:7").is_err());
+        assert!(state.tasks.iter().all(|t| t.project.as_deref() != Some("Home")));
+
+        // `.` repeats a modify on the new selection
+        state.selected_index = 2;
+        state.execute_command("modify +later").unwrap();
+        state.selected_index = 1;
+        state.repeat_change(None).unwrap();
+        assert!(state.tasks[1].tags.contains(&"later".to_string()));
+    }
+
+    #[test]
+    fn test_time_tracking() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::state::Mode;
+        use crate::core::timer::ReportGroup;
+        use chrono::Duration;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        for title in ["Write report", "Review"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 0;
+        state.execute_command("modify project=Work +writing").unwrap();
+
+        // Cycling to Doing starts the timer, moving on to Done stops it
+        state.execute_command("doing").unwrap();
+        let (entry, title) = state.running_timer.clone().unwrap();
+        assert_eq!((entry.task_id, title.as_str()), (state.tasks[0].id, "Write report"));
+        state.execute_command("done").unwrap();
+        assert!(state.running_timer.is_none());
+        assert_eq!(state.storage.time_entries(Some(state.tasks[0].id)).unwrap().len(), 1);
+
+        // Logged time shows up in the report per project, tag and task
+        state.execute_command("log 1h30m").unwrap();
+        state.selected_index = 1;
+        state.execute_command("log 45m yesterday").unwrap();
+        assert!(state.execute_command("log soon").is_err());
+        let report = state.time_report(ReportGroup::Project, None).unwrap();
+        assert_eq!(report.rows[0].0, "Work");
+        assert!(report.rows[0].1 >= Duration::minutes(90));
+        assert_eq!(report.rows[1], ("(no project)".to_string(), Duration::minutes(45)));
+        assert!(report.total >= Duration::minutes(135));
+        let report = state.time_report(ReportGroup::Tag, None).unwrap();
+        assert_eq!(report.rows.iter().map(|r| r.0.as_str()).collect::<Vec<_>>(), vec!["writing", "(no tag)"]);
+        state.execute_command("report day").unwrap();
+        assert_eq!((state.mode, state.report.as_ref().unwrap().rows.len()), (Mode::Report, 2));
+        feed(&mut state, "q");
+
+        // The entries view edits and deletes entries of the selected task
+        state.selected_index = 0;
+        state.execute_command("entries").unwrap();
+        assert_eq!(state.mode, Mode::TimeEntries);
+        assert_eq!(state.time_entries_view.as_ref().unwrap().entries.len(), 2);
+        feed(&mut state, "e");
+        assert_eq!(state.mode, Mode::Command);
+        assert!(state.command_buffer.starts_with("retime "));
+        feed(&mut state, "<Esc>");
+        assert_eq!(state.mode, Mode::TimeEntries);
+        state.execute_command("retime 2030-01-01T09:00 2030-01-01T10:15").unwrap();
+        let view = state.time_entries_view.as_ref().unwrap();
+        assert_eq!(view.entries[view.selected].duration(chrono::Utc::now()), Duration::minutes(75));
+        assert!(state.execute_command("retime 10:00 09:00").is_err());
+        feed(&mut state, "d");
+        assert_eq!(state.time_entries_view.as_ref().unwrap().entries.len(), 1);
+        feed(&mut state, "q");
+        assert_eq!(state.mode, Mode::Normal);
+
+        // With auto tracking off, only :start and :stop touch the timer
+        state.config.auto_time_tracking = false;
+        state.selected_index = 1;
+        state.execute_command("doing").unwrap();
+        assert!(state.running_timer.is_none());
+        state.execute_command("start").unwrap();
+        assert!(state.running_timer.is_some());
+        state.execute_command("stop").unwrap();
+        assert!(state.running_timer.is_none());
+    }
+
+    #[test]
+    fn test_pomodoro() {
+        use crate::core::pomodoro::PomodoroPhase;
+        use crate::domain::TaskStatus;
+        use chrono::Duration;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        lua_config.run_code("set.pomodoro{ work = 20, short_break = 3, long_break_every = 2 }").unwrap();
+        lua_config.run_code("hook('on_pomodoro_end', function() set.default_priority(5) end)").unwrap();
+        assert!(lua_config.run_code("set.pomodoro{ work = 0 }").is_err());
+        let mut state = AppState::new(storage, lua_config.clone()).unwrap();
+        state.add_task("Focus".to_string()).unwrap();
+        state.selected_index = 0;
+
+        // Starting moves the task to Doing; each finished work period is counted
+        state.execute_command("pomodoro").unwrap();
+        assert_eq!(state.tasks[0].status, TaskStatus::Doing);
+        let ends_at = state.pomodoro.as_ref().unwrap().ends_at;
+        assert!(ends_at - chrono::Utc::now() > Duration::minutes(19));
+        state.tick_pomodoro(ends_at - Duration::seconds(1)).unwrap();
+        assert_eq!(state.pomodoro.as_ref().unwrap().phase, PomodoroPhase::Work);
+        state.tick_pomodoro(ends_at).unwrap();
+        let pomodoro = state.pomodoro.clone().unwrap();
+        assert_eq!((pomodoro.phase, pomodoro.completed), (PomodoroPhase::ShortBreak, 1));
+        assert_eq!(pomodoro.ends_at, ends_at + Duration::minutes(3));
+        assert!(std::mem::take(&mut state.bell));
+        assert_eq!(lua_config.get_config().default_priority, 5);
+        assert_eq!(state.pomodoro_counts[&state.tasks[0].id], 1);
+
+        // After the break comes work, and every second period a long break
+        state.tick_pomodoro(pomodoro.ends_at).unwrap();
+        assert_eq!(state.pomodoro.as_ref().unwrap().phase, PomodoroPhase::Work);
+        let ends_at = state.pomodoro.as_ref().unwrap().ends_at;
+        state.tick_pomodoro(ends_at).unwrap();
+        assert_eq!(state.pomodoro.as_ref().unwrap().phase, PomodoroPhase::LongBreak);
+        assert_eq!(state.storage.pomodoro_counts().unwrap()[&state.tasks[0].id], 2);
+
+        // Finishing the task ends its pomodoro
+        state.execute_command("done").unwrap();
+        assert!(state.pomodoro.is_none());
+        assert!(state.execute_command("pomodoro later").is_err());
+
+        // Done and Archived tasks cannot get a pomodoro
+        assert!(state.execute_command("pomodoro").is_err());
+        state.execute_command("archive").unwrap();
+        state.execute_command("filter status=Archived").unwrap();
+        assert_eq!(state.tasks[0].status, TaskStatus::Archived);
+        assert!(state.execute_command("pomodoro").is_err());
+        assert!(state.pomodoro.is_none());
+        assert!(state.running_timer.is_none());
+        assert!(state.storage.get_tasks(None).unwrap().iter().all(|t| t.status == TaskStatus::Archived));
+    }
+
+    #[test]
+    fn test_stats_from_completion_log() {
+        use crate::core::state::Mode;
+        use crate::core::stats::Stats;
+        use crate::domain::timeline::{completions, StatusEvent};
+        use crate::domain::TaskStatus;
+        use chrono::{Duration, Utc};
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        for title in ["Ship", "Test", "Plan", "Late"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.execute_command("%modify project=Work +release").unwrap();
+        state.execute_command("4 due -2d").unwrap();
+        state.execute_command("1,2 done").unwrap();
+        state.execute_command("2 archive").unwrap();
+
+        // Stats ignore the filter and count an archived completion as done
+        state.execute_command("filter status=todo").unwrap();
+        state.execute_command("stats").unwrap();
+        assert_eq!(state.mode, Mode::Stats);
+        let stats = state.stats.clone().unwrap();
+        assert_eq!((stats.total, stats.todo, stats.done, stats.archived, stats.overdue), (4, 2, 1, 1, 1));
+        assert_eq!(*stats.daily.last().unwrap(), 2);
+        assert_eq!(stats.weekly.last().unwrap().1, 2);
+        assert!(stats.lead_time.unwrap() < Duration::minutes(1));
+        assert_eq!((stats.tags[0].open, stats.tags[0].done), (2, 2));
+        assert_eq!(stats.burndown[0].0, "Work");
+        assert_eq!(*stats.burndown[0].1.last().unwrap(), 2);
+
+        // Reopening takes the completion back; undoing the reopen logs it again
+        state.execute_command("filter").unwrap();
+        state.execute_command("1 todo").unwrap();
+        assert_eq!(completions(&state.storage.status_events(None).unwrap()).len(), 1);
+        state.undo().unwrap();
+        assert_eq!(completions(&state.storage.status_events(None).unwrap()).len(), 2);
+
+        // Completions are bucketed by day, oldest first
+        let now = Utc::now();
+        let tasks = state.storage.get_tasks(None).unwrap();
+        let done = |task_id, at| StatusEvent { id: 0, task_id, from: Some(TaskStatus::Todo), to: TaskStatus::Done, at };
+        let log = vec![done(tasks[0].id, now - Duration::days(3)), done(tasks[1].id, now - Duration::days(3)), done(tasks[2].id, now)];
+        let stats = Stats::compute(&tasks, &log, now);
+        assert_eq!(stats.daily[stats.daily.len() - 4..], [2, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_status_events() {
+        use crate::core::state::Mode;
+        use crate::domain::timeline::{completed_at, started_at};
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        for title in ["Draft", "Review", "Idea"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        let id = state.tasks[0].id;
+
+        // Every status change is recorded, edits that keep the status are not
+        state.execute_command("1 doing").unwrap();
+        state.execute_command("1 priority 5").unwrap();
+        state.execute_command("1 done").unwrap();
+        state.execute_command("2 doing").unwrap();
+        let events = state.storage.status_events(Some(id)).unwrap();
+        let changes: Vec<_> = events.iter().map(|e| (e.from, e.to)).collect();
+        assert_eq!(changes, vec![
+            (None, TaskStatus::Todo),
+            (Some(TaskStatus::Todo), TaskStatus::Doing),
+            (Some(TaskStatus::Doing), TaskStatus::Done),
+        ]);
+        assert!(started_at(&events, id).unwrap() <= completed_at(&events, id).unwrap());
+
+        // Derived fields in filters
+        state.execute_command("filter done>=-7d").unwrap();
+        assert_eq!(state.tasks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Draft"]);
+        state.execute_command("filter started>=today").unwrap();
+        assert_eq!(state.tasks.len(), 2);
+        state.execute_command("filter done<-7d").unwrap();
+        assert!(state.tasks.is_empty());
+        state.execute_command("filter").unwrap();
+
+        // The detail view shows the selected task
+        state.selected_index = 0;
+        state.execute_command("info").unwrap();
+        assert_eq!((state.mode, state.detail_task), (Mode::Detail, Some(id)));
+        state.handle_key(crossterm::event::KeyEvent::from(crossterm::event::KeyCode::Esc)).unwrap();
+        assert_eq!(state.mode, Mode::Normal);
+
+        // A database from before status events starts with its Done tasks done
+        drop(state);
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute("DELETE FROM status_events", []).unwrap();
+        conn.execute("UPDATE tasks SET status = 'Done', updated_at = '2020-01-01T00:00:00+00:00' WHERE id = ?", [id.to_string()]).unwrap();
+        drop(conn);
+        let storage = SqliteStorage::new(path).unwrap();
+        let events = storage.status_events(Some(id)).unwrap();
+        assert_eq!((events.len(), events[0].from, events[0].to), (1, None, TaskStatus::Done));
+        assert_eq!(events[0].at.format("%Y").to_string(), "2020");
+    }
+
+    #[test]
+    fn test_archive_policy_and_view() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::state::Mode;
+        use crate::domain::TaskStatus;
+        use chrono::{Duration, Utc};
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        lua_config.run_code("set.archive{ done_after = 14, purge_after = 90 }").unwrap();
+        assert!(lua_config.run_code("set.archive{ done_after = 'soon' }").is_err());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        for title in ["Old", "Recent", "Paused", "Open"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.execute_command("1,2 done").unwrap();
+        state.execute_command("3 doing").unwrap();
+        state.execute_command("3 archive").unwrap();
+
+        // Archived tasks are hidden unless asked for
+        assert_eq!(state.tasks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Old", "Recent", "Open"]);
+        state.execute_command("filter status=Archived").unwrap();
+        assert_eq!(state.tasks.len(), 1);
+        for (filter, shown) in [("status!=Done", true), ("status!=archived", false), ("project=status-page", false)] {
+            state.filter_string = Some(filter.to_string());
+            assert_eq!(state.shows_archived(), shown, "{}", filter);
+        }
+        state.execute_command("filter").unwrap();
+
+        // Nothing is old enough yet; two weeks on the Done tasks are archived
+        assert_eq!(state.collect_garbage(Utc::now()).unwrap(), (0, 0));
+        assert_eq!(state.collect_garbage(Utc::now() + Duration::days(15)).unwrap(), (2, 0));
+        assert_eq!(state.tasks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Open"]);
+
+        // The archive view restores a task to its status before archiving
+        state.execute_command("archived").unwrap();
+        assert_eq!(state.mode, Mode::Archive);
+        assert_eq!(state.archive_view.as_ref().unwrap().tasks.len(), 3);
+        let paused = state.archive_view.as_ref().unwrap().tasks.iter().position(|t| t.title == "Paused").unwrap();
+        state.archive_view.as_mut().unwrap().selected = paused;
+        feed(&mut state, "r");
+        assert_eq!(state.archive_view.as_ref().unwrap().tasks.len(), 2);
+        // and deletes to the trash
+        feed(&mut state, "D");
+        assert_eq!(state.archive_view.as_ref().unwrap().tasks.len(), 1);
+        assert_eq!(state.storage.trashed_tasks().unwrap().len(), 1);
+        feed(&mut state, "q");
+        let restored = state.tasks.iter().find(|t| t.title == "Paused").unwrap();
+        assert_eq!(restored.status, TaskStatus::Doing);
+
+        // Archived tasks past the purge age are deleted for good
+        assert_eq!(state.collect_garbage(Utc::now() + Duration::days(100)).unwrap(), (0, 1));
+        let titles: Vec<_> = state.storage.get_tasks(None).unwrap().into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, vec!["Paused", "Open"]);
+        assert!(state.execute_command("archive later").is_err());
+    }
+
+    #[test]
+    fn test_purge_keeps_live_subtasks() {
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        for title in ["Before", "Parent", "Child", "Notes", "After"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 2;
+        state.indent_selected().unwrap();
+        state.selected_index = 3;
+        state.indent_selected().unwrap();
+        let id = |state: &AppState, title: &str| state.tasks.iter().find(|t| t.title == title).unwrap().id;
+        let (parent, notes) = (id(&state, "Parent"), id(&state, "Notes"));
+        state.update_tasks(&[parent, notes], "on_status_change", |task| {
+            task.status = TaskStatus::Archived;
+            true
+        }).unwrap();
+
+        // The archived subtask goes with its parent, the Todo one takes its place
+        assert_eq!(state.purge_tasks(&[parent]).unwrap(), 2);
+        let tasks = state.storage.get_tasks(None).unwrap();
+        assert_eq!(tasks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Before", "Child", "After"]);
+        assert!(tasks.iter().all(|t| t.parent_id.is_none()));
+
+        // Undo cannot bring a purged task back or put Child under it again
+        for _ in 0..10 {
+            state.undo().unwrap();
+            assert!(state.storage.get_task(parent).unwrap().is_none());
+            assert!(state.storage.get_task(notes).unwrap().is_none());
+            assert!(state.storage.get_tasks(None).unwrap().iter().all(|t| t.parent_id != Some(parent)));
+        }
+        assert!(state.storage.trashed_tasks().unwrap().iter().all(|e| e.task.id != parent && e.task.id != notes));
+    }
+
+    #[test]
+    fn test_trash() {
+        use crate::core::keymap::parse_key_notation;
+        use crate::core::state::Mode;
+        use chrono::{Duration, Utc};
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let path = tmp_file.path().to_str().unwrap();
+        let storage = SqliteStorage::new(path).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        lua_config.run_code("set.trash_days(7)").unwrap();
+        assert!(lua_config.run_code("set.trash_days('never')").is_err());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let feed = |state: &mut AppState, keys: &str| {
+            for key in parse_key_notation(keys) {
+                state.handle_key(key).unwrap();
+            }
+        };
+        for title in ["Parent", "Child", "Blocked"] {
+            state.add_task(title.to_string()).unwrap();
+        }
+        state.selected_index = 1;
+        state.indent_selected().unwrap();
+        state.execute_command("1 tag +work").unwrap();
+        let parent = state.tasks[0].id;
+        let mut blocked = state.tasks[2].clone();
+        blocked.dependencies.push(parent);
+        state.storage.save_task(&blocked).unwrap();
+        state.reload_tasks().unwrap();
+
+        // Deleting moves the task and its subtask to the trash
+        state.execute_command("1 delete").unwrap();
+        assert_eq!(state.tasks.len(), 1);
+        assert!(state.tasks[0].dependencies.is_empty());
+        state.execute_command("trash").unwrap();
+        assert_eq!(state.mode, Mode::Trash);
+        assert_eq!(state.trash_view.as_ref().unwrap().entries.len(), 2);
+
+        // Restoring brings back the subtask, tags and dependency edges
+        feed(&mut state, "r");
+        assert!(state.trash_view.as_ref().unwrap().entries.is_empty());
+        feed(&mut state, "q");
+        let restored = state.storage.get_task(parent).unwrap().unwrap();
+        assert_eq!(restored.tags, vec!["work".to_string()]);
+        let tasks = state.storage.get_tasks(None).unwrap();
+        assert!(tasks.iter().any(|t| t.title == "Child" && t.parent_id == Some(parent)));
+        assert_eq!(tasks.iter().find(|t| t.title == "Blocked").unwrap().dependencies, vec![parent]);
+
+        // Restoring is one undoable step, and undoing it puts the tasks back
+        // in the trash
+        state.undo().unwrap();
+        assert_eq!(state.storage.get_tasks(None).unwrap().len(), 1);
+        state.execute_command("trash").unwrap();
+        assert_eq!(state.trash_view.as_ref().unwrap().entries.len(), 2);
+        feed(&mut state, "q");
+        state.redo().unwrap();
+        assert_eq!(state.storage.get_tasks(None).unwrap().len(), 3);
+        assert!(state.storage.trashed_tasks().unwrap().is_empty());
+        let tasks = state.storage.get_tasks(None).unwrap();
+        assert_eq!(tasks.iter().find(|t| t.title == "Blocked").unwrap().dependencies, vec![parent]);
+
+        // Undoing a delete takes the task out of the trash as well, and
+        // redoing it sends the task back there
+        state.execute_command("1 delete").unwrap();
+        state.undo().unwrap();
+        assert!(state.storage.trashed_tasks().unwrap().is_empty());
+        state.redo().unwrap();
+        let trashed = state.storage.trashed_tasks().unwrap();
+        assert_eq!(trashed.len(), 2);
+        assert!(trashed.iter().any(|e| e.task.id == parent));
+        state.undo().unwrap();
+        assert!(state.storage.trashed_tasks().unwrap().is_empty());
+
+        // Purging and expiry delete for good
+        state.execute_command("1 delete").unwrap();
+        state.execute_command("trash").unwrap();
+        feed(&mut state, "D");
+        feed(&mut state, "q");
+        assert!(state.storage.trashed_tasks().unwrap().is_empty());
+        state.execute_command("1 delete").unwrap();
+        assert_eq!(state.expire_trash(Utc::now()).unwrap(), 0);
+        assert_eq!(state.expire_trash(Utc::now() + Duration::days(8)).unwrap(), 1);
+        assert!(state.storage.get_tasks(None).unwrap().is_empty());
+        assert!(state.execute_command("trash later").is_err());
+    }
+
+    #[test]
+    fn test_json_export_import() {
+        use crate::domain::timeline::completed_at;
+        use crate::storage::json::{Export, EXPORT_VERSION};
+
+        let new_state = |file: &NamedTempFile| {
+            let storage = SqliteStorage::new(file.path().to_str().unwrap()).unwrap();
+            let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+            AppState::new(storage, lua_config).unwrap()
+        };
+        let (file_a, file_b, export) = (NamedTempFile::new().unwrap(), NamedTempFile::new().unwrap(), NamedTempFile::new().unwrap());
+        let export_path = export.path().display().to_string();
+        let mut a = new_state(&file_a);
+        a.add_task("Parent".to_string()).unwrap();
+        a.add_task("Child".to_string()).unwrap();
+        a.selected_index = 1;
+        a.indent_selected().unwrap();
+        a.execute_command("1 tag +home").unwrap();
+        a.execute_command("1 done").unwrap();
+        a.selected_index = 1;
+        a.execute_command("log 30m").unwrap();
+
+        // Without a selection the whole database is exported
+        a.execute_command(&format!("export {}", export_path)).unwrap();
+        let exported = Export::from_json(&std::fs::read_to_string(export.path()).unwrap()).unwrap();
+        assert_eq!(exported.version, EXPORT_VERSION);
+        assert_eq!(exported.tasks.len(), 2);
+        assert_eq!(exported.time_entries.len(), 1);
+        assert!(exported.status_events.len() >= 3);
+
+        // A fresh database gets everything, as one undo step
+        let mut b = new_state(&file_b);
+        b.execute_command(&format!("import {}", export_path)).unwrap();
+        let parent = a.tasks[0].id;
+        let imported = b.storage.get_task(parent).unwrap().unwrap();
+        assert_eq!(imported.tags, vec!["home".to_string()]);
+        assert!(b.storage.get_tasks(None).unwrap().iter().any(|t| t.title == "Child" && t.parent_id == Some(parent)));
+        assert_eq!(b.storage.time_entries(None).unwrap().len(), 1);
+        assert_eq!(
+            completed_at(&b.storage.status_events(None).unwrap(), parent),
+            completed_at(&a.storage.status_events(None).unwrap(), parent)
+        );
+        b.undo().unwrap();
+        assert!(b.storage.get_tasks(None).unwrap().is_empty());
+        b.redo().unwrap();
+
+        // Merging again changes nothing
+        let events = b.storage.status_events(None).unwrap().len();
+        let again = b.import_document(Export::from_json(&std::fs::read_to_string(export.path()).unwrap()).unwrap()).unwrap();
+        assert_eq!((again.added, again.updated, again.unchanged), (0, 0, 2));
+        assert_eq!(b.storage.status_events(None).unwrap().len(), events);
+
+        // The newer copy wins; a newer local copy is kept and reported
+        let mut child = b.storage.get_tasks(None).unwrap().into_iter().find(|t| t.title == "Child").unwrap();
+        child.title = "Child here".to_string();
+        child.updated_at = chrono::Utc::now();
+        b.storage.save_task(&child).unwrap();
+        let mut renamed = a.storage.get_task(parent).unwrap().unwrap();
+        renamed.title = "Parent there".to_string();
+        renamed.updated_at = chrono::Utc::now();
+        a.storage.save_task(&renamed).unwrap();
+        let summary = b.import_document(a.export_document(None).unwrap()).unwrap();
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.conflicts, vec!["Child here".to_string()]);
+        assert_eq!(b.storage.get_task(parent).unwrap().unwrap().title, "Parent there");
+
+        // Old bare task lists still read; newer versions are refused
+        assert_eq!(Export::from_json("[]").unwrap().version, 0);
+        assert!(Export::from_json(r#"{"version": 99, "exported_at": "2024-01-01T00:00:00Z", "tasks": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_taskwarrior_commands() {
+        use crate::domain::TaskStatus;
+
+        let new_state = |file: &NamedTempFile| {
+            let storage = SqliteStorage::new(file.path().to_str().unwrap()).unwrap();
+            let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+            AppState::new(storage, lua_config).unwrap()
+        };
+        let (file_a, file_b, export) = (NamedTempFile::new().unwrap(), NamedTempFile::new().unwrap(), NamedTempFile::new().unwrap());
+        let mut a = new_state(&file_a);
+        for title in ["Plan", "Pack", "Go"] {
+            a.add_task(title.to_string()).unwrap();
+        }
+        a.execute_command("1 modify project=Trip +home priority:5").unwrap();
+        a.execute_command("2 done").unwrap();
+        a.execute_command(&format!("export taskwarrior {}", export.path().display())).unwrap();
+
+        let mut b = new_state(&file_b);
+        b.add_task("Already here".to_string()).unwrap();
+        b.execute_command(&format!("import tw {}", export.path().display())).unwrap();
+        let titles: Vec<&str> = b.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Already here", "Plan", "Pack", "Go"]);
+        assert_eq!(b.tasks[1].project.as_deref(), Some("Trip"));
+        assert_eq!((b.tasks[1].priority, b.tasks[2].status), (5, TaskStatus::Done));
+
+        // Importing again keeps the tasks where they are
+        b.selected_index = 3;
+        b.move_selected(false).unwrap();
+        let summary = b.import_text(crate::core::transfer::Format::Taskwarrior, &std::fs::read_to_string(export.path()).unwrap()).unwrap();
+        assert_eq!(summary.unchanged, 3);
+        assert_eq!(b.tasks[2].title, "Go");
+    }
+
+    #[test]
+    fn test_todotxt_sync() {
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let storage = SqliteStorage::new(tmp_file.path().to_str().unwrap()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let todo = dir.path().join("todo.txt");
+        std::fs::write(&todo, "(A) Call the bank +Finance @phone\nWater plants\n").unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        lua_config.run_code(&format!("set.todotxt({:?})", todo.display().to_string())).unwrap();
+        let mut state = AppState::new(storage, lua_config).unwrap();
+
+        // The file is read first, then written back with an id on each line
+        state.sync_todotxt().unwrap();
+        assert_eq!(state.tasks.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), vec!["Call the bank", "Water plants"]);
+        assert_eq!((state.tasks[0].priority, state.tasks[0].project.as_deref()), (5, Some("Finance")));
+        let written = std::fs::read_to_string(&todo).unwrap();
+        assert_eq!(written.lines().filter(|l| l.contains(&format!("uuid:{}", state.tasks[0].id))).count(), 1);
+
+        // Changes here are written on every save
+        state.add_task("Buy milk".to_string()).unwrap();
+        state.execute_command("2 doing").unwrap();
+        assert!(std::fs::read_to_string(&todo).unwrap().contains("Buy milk"));
+
+        // but not when the view is only redrawn or filtered
+        let original = state.tasks[0].clone();
+        state.storage.save_task(&crate::domain::Task { title: "Renamed".to_string(), ..original.clone() }).unwrap();
+        state.reload_tasks().unwrap();
+        state.execute_command("filter priority>=3").unwrap();
+        state.execute_command("filter").unwrap();
+        assert!(!std::fs::read_to_string(&todo).unwrap().contains("Renamed"));
+        state.storage.save_task(&original).unwrap();
+
+        // Edits made elsewhere are read back: a completed line, a new line and
+        // a removed one
+        let lines: Vec<String> = std::fs::read_to_string(&todo).unwrap().lines().map(String::from).collect();
+        let edited = format!("x 2024-03-05 {}\n{}\nCall mom @phone\n", lines[0], lines[1]);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&todo, edited).unwrap();
+        state.sync_todotxt().unwrap();
+        let tasks = state.storage.get_tasks(None).unwrap();
+        let status = |title: &str| tasks.iter().find(|t| t.title == title).map(|t| t.status);
+        assert_eq!(status("Call the bank"), Some(TaskStatus::Done));
+        assert_eq!(status("Water plants"), Some(TaskStatus::Doing));
+        assert_eq!(status("Call mom"), Some(TaskStatus::Todo));
+        assert_eq!(status("Buy milk"), None);
+        assert_eq!(state.storage.trashed_tasks().unwrap().len(), 1);
+        assert!(std::fs::read_to_string(&todo).unwrap().contains("Call mom @phone uuid:"));
+
+        // A plain import matches lines without an id by title
+        let summary = state.import_text(crate::core::transfer::Format::TodoTxt, "Call mom @phone\n").unwrap();
+        assert_eq!((summary.added, summary.unchanged), (0, 1));
+    }
+
+    #[test]
+    fn test_markdown_commands() {
+        use crate::domain::TaskStatus;
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let storage = SqliteStorage::new(tmp_file.path().to_str().unwrap()).unwrap();
+        let lua_config = std::rc::Rc::new(crate::config::lua::LuaConfig::new().unwrap());
+        let mut state = AppState::new(storage, lua_config).unwrap();
+        let notes = NamedTempFile::new().unwrap();
+        std::fs::write(notes.path(), "# Launch\n\n- [ ] Write post\n  - [ ] Draft\n- [x] Pick date\n").unwrap();
+
+        state.execute_command(&format!("import md {}", notes.path().display())).unwrap();
+        let titles: Vec<&str> = state.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Write post", "Draft", "Pick date"]);
+        assert_eq!(state.tasks[1].parent_id, Some(state.tasks[0].id));
+        assert_eq!(state.tasks[2].status, TaskStatus::Done);
+
+        // Importing the notes again after ticking an item only updates it
+        std::fs::write(notes.path(), "# Launch\n\n- [x] Write post\n  - [ ] Draft\n- [x] Pick date\n- [ ] Invite press\n").unwrap();
+        let summary = state
+            .import_text(crate::core::transfer::Format::Markdown, &std::fs::read_to_string(notes.path()).unwrap())
+            .unwrap();
+        assert_eq!((summary.added, summary.updated, summary.unchanged), (1, 1, 2));
+        assert_eq!(state.tasks[0].status, TaskStatus::Done);
+
+        // `:export md` writes the current view
+        state.add_task("Other".to_string()).unwrap();
+        state.execute_command("filter project=Launch").unwrap();
+        let export = NamedTempFile::new().unwrap();
+        state.execute_command(&format!("export md {}", export.path().display())).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(export.path()).unwrap(),
+            "## Launch\n\n- [x] Write post\n  - [ ] Draft\n- [x] Pick date\n- [ ] Invite press\n"
+        );
+
+        // and `taskvim export --filter` the matching tasks, in any format
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        crate::cli::run(&args(&format!("export todotxt --filter status=Todo {}", export.path().display())), &mut state).unwrap();
+        let lines: Vec<String> = std::fs::read_to_string(export.path()).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| !l.starts_with("x ")));
+        crate::cli::run(&args(&format!("export md --filter=project=Launch {}", export.path().display())), &mut state).unwrap();
+        assert!(!std::fs::read_to_string(export.path()).unwrap().contains("Other"));
+        assert!(crate::cli::run(&args("export --filter"), &mut state).is_err());
+    }
 }
@@ -0,0 +1,311 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::date::parse_date;
+use crate::domain::time::{format_duration, parse_duration, TimeEntry};
+use crate::domain::TaskStatus;
+use crate::error::{Result, TaskVimError};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// What `:report` adds time up by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportGroup {
+    Project,
+    Tag,
+    Day,
+    Task,
+}
+
+impl ReportGroup {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "project" | "projects" => Some(ReportGroup::Project),
+            "tag" | "tags" => Some(ReportGroup::Tag),
+            "day" | "days" => Some(ReportGroup::Day),
+            "task" | "tasks" => Some(ReportGroup::Task),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReportGroup::Project => "project",
+            ReportGroup::Tag => "tag",
+            ReportGroup::Day => "day",
+            ReportGroup::Task => "task",
+        }
+    }
+}
+
+/// Logged time added up per group, for the report view.
+#[derive(Debug, Clone)]
+pub struct TimeReport {
+    pub group: ReportGroup,
+    pub since: Option<DateTime<Utc>>,
+    pub rows: Vec<(String, Duration)>,
+    pub total: Duration,
+}
+
+/// The time entries of one task, for the entries view.
+#[derive(Debug, Clone)]
+pub struct TimeEntriesView {
+    pub task_id: Uuid,
+    pub title: String,
+    pub entries: Vec<TimeEntry>,
+    pub selected: usize,
+}
+
+/// Parses `HH:MM` (on `day`) or `YYYY-MM-DDTHH:MM`, in local time.
+fn parse_local_time(input: &str, day: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let naive = match NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M") {
+        Ok(naive) => naive,
+        Err(_) => {
+            let time = NaiveTime::parse_from_str(input, "%H:%M").ok()?;
+            day.with_timezone(&Local).date_naive().and_time(time)
+        }
+    };
+    Local.from_local_datetime(&naive).single().map(|d| d.with_timezone(&Utc))
+}
+
+fn invalid(msg: String) -> TaskVimError {
+    TaskVimError::Validation(msg)
+}
+
+impl AppState {
+    /// Reloads the running timer shown in the status bar.
+    pub fn refresh_timer(&mut self) -> Result<()> {
+        self.running_timer = match self.storage.running_time_entry()? {
+            Some(entry) => {
+                let title = self.storage.get_task(entry.task_id)?.map(|t| t.title).unwrap_or_default();
+                Some((entry, title))
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn timer_target(&self) -> Result<Uuid> {
+        self.selected_ids().first().copied().ok_or_else(|| invalid("No task selected".to_string()))
+    }
+
+    /// `:start`: starts the timer on the selected task, stopping any other,
+    /// and moves a Todo task to Doing.
+    pub fn start_timer(&mut self) -> Result<()> {
+        let id = self.timer_target()?;
+        self.storage.start_time_entry(id, Utc::now())?;
+        self.refresh_timer()?;
+        self.update_tasks(&[id], "on_status_change", |task| {
+            let changed = task.status == TaskStatus::Todo;
+            if changed {
+                task.status = TaskStatus::Doing;
+            }
+            changed
+        })?;
+        Ok(())
+    }
+
+    /// `:stop`: stops the running timer.
+    pub fn stop_timer(&mut self) -> Result<()> {
+        let Some((entry, title)) = self.running_timer.clone() else {
+            self.warn("No timer running");
+            return Ok(());
+        };
+        self.storage.stop_time_entries(Utc::now())?;
+        self.refresh_timer()?;
+        self.info(format!("Stopped {} after {}", title, format_duration(entry.duration(Utc::now()))));
+        Ok(())
+    }
+
+    /// Starts the timer when a task moves to Doing and stops it when the
+    /// timed task moves on, unless `set.time_tracking(false)`.
+    pub fn track_status_changes(&mut self, changes: &[(Uuid, TaskStatus, TaskStatus)]) -> Result<()> {
+        if !self.config.auto_time_tracking {
+            return Ok(());
+        }
+        let running = self.running_timer.as_ref().map(|(entry, _)| entry.task_id);
+        let now = Utc::now();
+        for &(id, before, after) in changes {
+            if after == TaskStatus::Doing && before != TaskStatus::Doing && running != Some(id) {
+                self.storage.start_time_entry(id, now)?;
+            } else if before == TaskStatus::Doing && after != TaskStatus::Doing && running == Some(id) {
+                self.storage.stop_time_entries(now)?;
+            }
+        }
+        self.refresh_timer()
+    }
+
+    /// `:log 1h30m [date]`: records time spent on the selected task (or the
+    /// one in the entries view), ending now, or at the same time of day on
+    /// `date`.
+    pub fn log_time(&mut self, args: &str) -> Result<()> {
+        let mut words = args.split_whitespace();
+        let spec = words.next().unwrap_or("");
+        let duration = parse_duration(spec).ok_or_else(|| invalid(format!("Invalid duration: {} (e.g. 1h30m, 45m, 1:30)", spec)))?;
+        let now = Utc::now();
+        let end = match words.next() {
+            Some(date) => {
+                let day = parse_date(date, now).ok_or_else(|| invalid(format!("Invalid date: {}", date)))?;
+                let local_now = now.with_timezone(&Local);
+                let naive = day.with_timezone(&Local).date_naive().and_time(local_now.time());
+                Local.from_local_datetime(&naive).single().map(|d| d.with_timezone(&Utc)).unwrap_or(day)
+            }
+            None => now,
+        };
+        let id = match &self.time_entries_view {
+            Some(view) => view.task_id,
+            None => self.timer_target()?,
+        };
+        self.storage.save_time_entry(&TimeEntry { id: 0, task_id: id, start: end - duration, end: Some(end) })?;
+        self.info(format!("Logged {}", format_duration(duration)));
+        self.reload_time_entries()
+    }
+
+    /// `:entries`: lists the time entries of the selected task.
+    pub fn open_time_entries(&mut self) -> Result<()> {
+        let id = self.timer_target()?;
+        let title = self.storage.get_task(id)?.map(|t| t.title).unwrap_or_default();
+        let entries = self.storage.time_entries(Some(id))?;
+        let selected = entries.len().saturating_sub(1);
+        self.time_entries_view = Some(TimeEntriesView { task_id: id, title, entries, selected });
+        self.mode = Mode::TimeEntries;
+        Ok(())
+    }
+
+    fn reload_time_entries(&mut self) -> Result<()> {
+        if let Some(view) = self.time_entries_view.as_mut() {
+            view.entries = self.storage.time_entries(Some(view.task_id))?;
+            view.selected = view.selected.min(view.entries.len().saturating_sub(1));
+        }
+        self.refresh_timer()
+    }
+
+    fn selected_time_entry(&self) -> Option<TimeEntry> {
+        let view = self.time_entries_view.as_ref()?;
+        view.entries.get(view.selected).cloned()
+    }
+
+    /// `:retime <start> <end>`: changes the times of the entry selected in
+    /// the entries view. Times are `HH:MM` on the entry's day or
+    /// `YYYY-MM-DDTHH:MM`, local time.
+    pub fn retime_entry(&mut self, args: &str) -> Result<()> {
+        let mut entry = self.selected_time_entry().ok_or_else(|| invalid("No time entry selected (see :entries)".to_string()))?;
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let [start, end] = words[..] else {
+            return Err(invalid("Usage: retime <start> <end>".to_string()));
+        };
+        let start = parse_local_time(start, entry.start).ok_or_else(|| invalid(format!("Invalid time: {}", start)))?;
+        let end = parse_local_time(end, entry.start).ok_or_else(|| invalid(format!("Invalid time: {}", end)))?;
+        if end <= start {
+            return Err(invalid("The entry must end after it starts".to_string()));
+        }
+        entry.start = start;
+        entry.end = Some(end);
+        self.storage.save_time_entry(&entry)?;
+        self.reload_time_entries()
+    }
+
+    pub fn handle_time_entries_key(&mut self, key: crossterm::event::KeyEvent) -> Result<()> {
+        use crossterm::event::KeyCode;
+        let Some(view) = self.time_entries_view.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        let last = view.entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => view.selected = (view.selected + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('d') | KeyCode::Char('x') => {
+                if let Some(entry) = self.selected_time_entry() {
+                    self.storage.delete_time_entry(entry.id)?;
+                    self.reload_time_entries()?;
+                }
+            }
+            KeyCode::Char('e') => match self.selected_time_entry() {
+                Some(entry) if entry.end.is_some() => {
+                    let format = |d: DateTime<Utc>| d.with_timezone(&Local).format("%Y-%m-%dT%H:%M").to_string();
+                    let line = format!("retime {} {}", format(entry.start), format(entry.end.unwrap_or(entry.start)));
+                    self.set_command_line(&line);
+                }
+                Some(_) => self.warn("Stop the timer before editing its entry"),
+                None => {}
+            },
+            KeyCode::Char('a') => self.set_command_line("log "),
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.time_entries_view = None;
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds up the time logged since `since` per project, tag, day or task.
+    /// A task with several tags counts towards each of them.
+    pub fn time_report(&self, group: ReportGroup, since: Option<DateTime<Utc>>) -> Result<TimeReport> {
+        let now = Utc::now();
+        let tasks: HashMap<Uuid, _> = self.storage.get_tasks(None)?.into_iter().map(|t| (t.id, t)).collect();
+        let mut totals: HashMap<String, Duration> = HashMap::new();
+        let mut total = Duration::zero();
+        for entry in self.storage.time_entries(None)? {
+            if since.map(|s| entry.start < s).unwrap_or(false) {
+                continue;
+            }
+            let duration = entry.duration(now);
+            total += duration;
+            let task = tasks.get(&entry.task_id);
+            let keys = match group {
+                ReportGroup::Project => vec![task.and_then(|t| t.project.clone()).unwrap_or_else(|| "(no project)".to_string())],
+                ReportGroup::Tag => match task.map(|t| t.tags.clone()).unwrap_or_default() {
+                    tags if tags.is_empty() => vec!["(no tag)".to_string()],
+                    tags => tags,
+                },
+                ReportGroup::Day => vec![entry.start.with_timezone(&Local).format("%Y-%m-%d").to_string()],
+                ReportGroup::Task => vec![task.map(|t| t.title.clone()).unwrap_or_else(|| "(deleted)".to_string())],
+            };
+            for key in keys {
+                let sum = totals.entry(key).or_insert_with(Duration::zero);
+                *sum += duration;
+            }
+        }
+        let mut rows: Vec<(String, Duration)> = totals.into_iter().collect();
+        match group {
+            ReportGroup::Day => rows.sort_by(|a, b| a.0.cmp(&b.0)),
+            _ => rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0))),
+        }
+        Ok(TimeReport { group, since, rows, total })
+    }
+
+    /// `:report [project|tag|day|task] [since]`, e.g. `:report day -7d`.
+    pub fn open_report(&mut self, args: &str) -> Result<()> {
+        let mut group = ReportGroup::Project;
+        let mut since = None;
+        for word in args.split_whitespace() {
+            match ReportGroup::from_name(word) {
+                Some(g) => group = g,
+                None => since = Some(parse_date(word, Utc::now()).ok_or_else(|| invalid(format!("Invalid report argument: {}", word)))?),
+            }
+        }
+        self.report = Some(self.time_report(group, since)?);
+        self.mode = Mode::Report;
+        Ok(())
+    }
+
+    pub fn handle_report_key(&mut self, key: crossterm::event::KeyEvent) -> Result<()> {
+        use crossterm::event::KeyCode;
+        let group = match key.code {
+            KeyCode::Char('p') => ReportGroup::Project,
+            KeyCode::Char('t') => ReportGroup::Tag,
+            KeyCode::Char('d') => ReportGroup::Day,
+            KeyCode::Char('T') => ReportGroup::Task,
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.report = None;
+                self.mode = Mode::Normal;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let since = self.report.as_ref().and_then(|r| r.since);
+        self.report = Some(self.time_report(group, since)?);
+        Ok(())
+    }
+}
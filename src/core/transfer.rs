@@ -0,0 +1,335 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::time::TimeEntry;
+use crate::domain::timeline::StatusEvent;
+use crate::domain::{Task, TaskStatus};
+use crate::error::Result;
+use crate::storage::json::Export;
+use crate::storage::sqlite::HistoryStack;
+use crate::storage::{markdown, taskwarrior, todotxt};
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// The file formats of `:export` and `:import`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Taskwarrior,
+    TodoTxt,
+    Markdown,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Format::Json),
+            "taskwarrior" | "tw" => Some(Format::Taskwarrior),
+            "todotxt" | "todo.txt" => Some(Format::TodoTxt),
+            "md" | "markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Splits `[format] <path>`; without a format name the file is JSON.
+    pub fn split_args(args: &str) -> (Self, &str) {
+        let args = args.trim();
+        let (name, rest) = args.split_once(' ').unwrap_or((args, ""));
+        match Format::from_name(name) {
+            Some(format) => (format, rest.trim()),
+            None => (Format::Json, args),
+        }
+    }
+
+    /// Writes an export in this format.
+    pub fn render(&self, export: &Export) -> Result<String> {
+        match self {
+            Format::Json => export.to_json(),
+            Format::Taskwarrior => taskwarrior::export(&export.tasks, &export.status_events),
+            Format::TodoTxt => Ok(todotxt::export(&export.tasks, &export.status_events)),
+            Format::Markdown => Ok(markdown::export(&export.tasks)),
+        }
+    }
+}
+
+/// What an import did. A conflict is a task that differs from the local copy
+/// while the local copy is the newer one: it is kept and reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub conflicts: Vec<String>,
+}
+
+impl ImportSummary {
+    pub fn describe(&self) -> String {
+        format!(
+            "Imported {} new and {} updated tasks, {} unchanged, {} conflicts",
+            self.added,
+            self.updated,
+            self.unchanged,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// Whether two copies of a task say the same thing, whatever the order of
+/// their tags and dependencies.
+fn same_task(a: &Task, b: &Task) -> bool {
+    let normalized = |task: &Task| {
+        let mut task = task.clone();
+        task.tags.sort();
+        task.dependencies.sort();
+        serde_json::to_value(task).ok()
+    };
+    normalized(a) == normalized(b)
+}
+
+impl AppState {
+    /// The tasks `ids` and their subtasks, or every task when `None`, with
+    /// their status history and time entries.
+    pub fn export_document(&self, ids: Option<&[Uuid]>) -> Result<Export> {
+        let tasks = match ids {
+            Some(ids) => self.collect_subtrees(ids)?,
+            None => self.storage.get_tasks(None)?,
+        };
+        let included: HashSet<Uuid> = tasks.iter().map(|t| t.id).collect();
+        let events = self.storage.status_events(None)?.into_iter().filter(|e| included.contains(&e.task_id)).collect();
+        let entries = self.storage.time_entries(None)?.into_iter().filter(|e| included.contains(&e.task_id)).collect();
+        Ok(Export::new(tasks, events, entries))
+    }
+
+    /// `:export [format] [path]`: the targeted tasks when there is a range,
+    /// a Visual selection or marks, otherwise the current view for Markdown
+    /// and the whole database for the other formats. Without a path the
+    /// text goes to the clipboard.
+    pub fn export_file(&mut self, args: &str) -> Result<()> {
+        let (format, path) = Format::split_args(args);
+        let targeted = self.command_targets.is_some()
+            || matches!(self.mode, Mode::Visual | Mode::VisualLine)
+            || !self.marked_tasks.is_empty();
+        let ids = if targeted {
+            Some(self.selected_ids())
+        } else if format == Format::Markdown {
+            Some(self.tasks.iter().map(|t| t.id).collect())
+        } else {
+            None
+        };
+        let export = self.export_document(ids.as_deref())?;
+        let text = format.render(&export)?;
+        self.end_visual();
+        if path.is_empty() {
+            self.clipboard.set_text(&text)?;
+            self.info(format!("Copied {} tasks to the clipboard", export.tasks.len()));
+        } else {
+            std::fs::write(path, text)?;
+            self.info(format!("Exported {} tasks to {}", export.tasks.len(), path));
+        }
+        Ok(())
+    }
+
+    /// Reads `text` in `format` and merges it with `import_document`.
+    /// Formats without a task tree keep the place of tasks already here and
+    /// append new ones.
+    pub fn import_text(&mut self, format: Format, text: &str) -> Result<ImportSummary> {
+        let mut export = match format {
+            Format::Json => return self.import_document(Export::from_json(text)?),
+            Format::Taskwarrior => taskwarrior::import(text)?,
+            Format::TodoTxt => self.todotxt_document(text)?,
+            Format::Markdown => self.markdown_document(text)?,
+        };
+        self.place_imported(&mut export.tasks)?;
+        self.import_document(export)
+    }
+
+    /// Turns todo.txt lines into an import. A line finds its task by its
+    /// `uuid:` key, or else by its title. What todo.txt cannot say (the
+    /// description, the time of day of dates, Doing and Archived) is kept
+    /// from the task, and a line that changes the task counts as newer.
+    pub fn todotxt_document(&self, text: &str) -> Result<Export> {
+        let now = Utc::now();
+        let local = self.storage.get_tasks(None)?;
+        let mut export = Export::new(Vec::new(), Vec::new(), Vec::new());
+        let mut claimed = HashSet::new();
+        for (mut task, completed) in todotxt::parse(text, now) {
+            if task.id.is_nil() {
+                let same_title = local.iter().find(|t| t.title == task.title && !claimed.contains(&t.id));
+                task.id = same_title.map(|t| t.id).unwrap_or_else(Uuid::new_v4);
+            }
+            claimed.insert(task.id);
+            match local.iter().find(|t| t.id == task.id) {
+                Some(current) => {
+                    task.description = current.description.clone();
+                    task.created_at = current.created_at;
+                    task.parent_id = current.parent_id;
+                    task.position = current.position;
+                    if task.due_date.map(|d| d.date_naive()) == current.due_date.map(|d| d.date_naive()) {
+                        task.due_date = current.due_date;
+                    }
+                    task.status = match (current.status, task.status) {
+                        (TaskStatus::Doing, TaskStatus::Todo) => TaskStatus::Doing,
+                        (TaskStatus::Archived, TaskStatus::Done) => TaskStatus::Archived,
+                        (_, status) => status,
+                    };
+                    task.updated_at = current.updated_at;
+                    if !same_task(current, &task) {
+                        task.updated_at = now;
+                    }
+                }
+                None if task.status == TaskStatus::Done => export.status_events.push(StatusEvent {
+                    id: 0,
+                    task_id: task.id,
+                    from: None,
+                    to: TaskStatus::Done,
+                    at: completed.unwrap_or(now),
+                }),
+                None => {}
+            }
+            export.tasks.push(task);
+        }
+        Ok(export)
+    }
+
+    /// Turns Markdown checklists into an import. An item finds the task with
+    /// its title in its project; a found task only takes the item's check
+    /// mark and description, and counts as newer when they change it. New
+    /// items checked off are Done.
+    pub fn markdown_document(&self, text: &str) -> Result<Export> {
+        let now = Utc::now();
+        let local = self.storage.get_tasks(None)?;
+        let mut export = Export::new(Vec::new(), Vec::new(), Vec::new());
+        // The id each parsed item ends up with, for the items nested in it
+        let mut ids: HashMap<Uuid, Uuid> = HashMap::new();
+        let mut claimed = HashSet::new();
+        for mut task in markdown::parse(text, now) {
+            task.parent_id = task.parent_id.and_then(|p| ids.get(&p).copied());
+            let found = local
+                .iter()
+                .find(|t| t.title == task.title && t.project == task.project && !claimed.contains(&t.id));
+            let Some(current) = found else {
+                ids.insert(task.id, task.id);
+                export.tasks.push(task);
+                continue;
+            };
+            ids.insert(task.id, current.id);
+            claimed.insert(current.id);
+            let mut merged = current.clone();
+            merged.status = match (current.status, task.status) {
+                (TaskStatus::Done | TaskStatus::Archived, TaskStatus::Todo) => TaskStatus::Todo,
+                (TaskStatus::Todo | TaskStatus::Doing, TaskStatus::Done) => TaskStatus::Done,
+                (status, _) => status,
+            };
+            if task.description.is_some() {
+                merged.description = task.description;
+            }
+            if !same_task(current, &merged) {
+                merged.updated_at = now;
+            }
+            export.tasks.push(merged);
+        }
+        Ok(export)
+    }
+
+    /// Keeps the parent and position of tasks that exist and gives new ones
+    /// positions after their existing siblings.
+    pub fn place_imported(&mut self, tasks: &mut [Task]) -> Result<()> {
+        let mut new: BTreeMap<Option<Uuid>, Vec<usize>> = BTreeMap::new();
+        for (i, task) in tasks.iter_mut().enumerate() {
+            match self.storage.get_task(task.id)? {
+                Some(current) => {
+                    task.parent_id = current.parent_id;
+                    task.position = current.position;
+                }
+                None => new.entry(task.parent_id).or_default().push(i),
+            }
+        }
+        for (parent, indices) in new {
+            let last = self.siblings(parent)?.last().map(|t| t.id);
+            let slots = self.slots_after(parent, last, indices.len())?;
+            for (i, slot) in indices.into_iter().zip(slots) {
+                tasks[i].position = slot;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges an export into the database by task UUID: new tasks are added,
+    /// changed ones take the newer copy. Task changes are one undo step;
+    /// status events and finished time entries missing here are added.
+    pub fn import_document(&mut self, export: Export) -> Result<ImportSummary> {
+        let local: HashMap<Uuid, Task> = self.storage.get_tasks(None)?.into_iter().map(|t| (t.id, t)).collect();
+        let known: HashSet<Uuid> = local.keys().chain(export.tasks.iter().map(|t| &t.id)).copied().collect();
+        let with_history: HashSet<Uuid> = export.status_events.iter().map(|e| e.task_id).collect();
+
+        let mut summary = ImportSummary::default();
+        self.with_history_group(|state| {
+            for mut task in export.tasks {
+                // Links to tasks that are neither here nor in the export are dropped
+                task.parent_id = task.parent_id.filter(|p| known.contains(p));
+                task.dependencies.retain(|d| known.contains(d));
+                match local.get(&task.id) {
+                    None => {
+                        state.storage.push_snapshot(HistoryStack::Undo, task.id, None)?;
+                        summary.added += 1;
+                    }
+                    Some(current) if same_task(current, &task) => {
+                        summary.unchanged += 1;
+                        continue;
+                    }
+                    Some(current) if task.updated_at > current.updated_at => {
+                        state.storage.push_history(current)?;
+                        summary.updated += 1;
+                    }
+                    Some(current) => {
+                        summary.conflicts.push(current.title.clone());
+                        continue;
+                    }
+                }
+                if with_history.contains(&task.id) {
+                    state.storage.import_task(&task)?;
+                } else {
+                    state.storage.save_task(&task)?;
+                }
+            }
+            Ok(())
+        })?;
+
+        let events: HashSet<(Uuid, String, i64)> = self
+            .storage
+            .status_events(None)?
+            .into_iter()
+            .map(|e| (e.task_id, e.to.to_string(), e.at.timestamp_micros()))
+            .collect();
+        for event in export.status_events.iter().filter(|e| known.contains(&e.task_id)) {
+            if !events.contains(&(event.task_id, event.to.to_string(), event.at.timestamp_micros())) {
+                self.storage.add_status_event(event)?;
+            }
+        }
+        let entries: HashSet<(Uuid, i64)> =
+            self.storage.time_entries(None)?.into_iter().map(|e| (e.task_id, e.start.timestamp_micros())).collect();
+        for entry in export.time_entries.iter().filter(|e| e.end.is_some() && known.contains(&e.task_id)) {
+            if !entries.contains(&(entry.task_id, entry.start.timestamp_micros())) {
+                self.storage.save_time_entry(&TimeEntry { id: 0, ..entry.clone() })?;
+            }
+        }
+
+        if summary.added + summary.updated > 0 {
+            self.storage.clear_redo()?;
+            self.write_todotxt()?;
+        }
+        self.refresh_timer()?;
+        self.reload_tasks()?;
+        Ok(summary)
+    }
+
+    /// `:import [format] <path>`: merges a file, reporting each conflict.
+    pub fn import_file(&mut self, args: &str) -> Result<()> {
+        let (format, path) = Format::split_args(args);
+        let summary = self.import_text(format, &std::fs::read_to_string(path)?)?;
+        for title in &summary.conflicts {
+            self.warn(format!("Conflict: kept the newer local copy of {}", title));
+        }
+        self.info(summary.describe());
+        Ok(())
+    }
+}
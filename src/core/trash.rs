@@ -0,0 +1,163 @@
+use crate::core::state::{AppState, Mode};
+use crate::domain::trash::TrashedTask;
+use crate::error::{Result, TaskVimError};
+use crate::storage::sqlite::HistoryStack;
+use chrono::{DateTime, Duration, Utc};
+use crossterm::event::{KeyCode, KeyEvent};
+use uuid::Uuid;
+
+/// The deleted tasks listed by `:trash`.
+#[derive(Debug, Clone)]
+pub struct TrashView {
+    pub entries: Vec<TrashedTask>,
+    pub selected: usize,
+}
+
+/// `id` and the tasks under it that were deleted with it, parents first.
+fn with_trashed_subtasks(entries: &[TrashedTask], id: Uuid) -> Vec<TrashedTask> {
+    let Some(root) = entries.iter().find(|e| e.task.id == id) else {
+        return Vec::new();
+    };
+    let mut restored = vec![root.clone()];
+    let mut i = 0;
+    while i < restored.len() {
+        let parent = restored[i].task.id;
+        restored.extend(
+            entries
+                .iter()
+                .filter(|e| e.batch == root.batch && e.task.parent_id == Some(parent))
+                .cloned(),
+        );
+        i += 1;
+    }
+    restored
+}
+
+impl AppState {
+    /// Purges what has been in the trash longer than `set.trash_days`.
+    /// Returns how many tasks.
+    pub fn expire_trash(&mut self, now: DateTime<Utc>) -> Result<usize> {
+        match self.config.trash_days {
+            Some(days) => self.storage.expire_trash(now - Duration::days(days as i64)),
+            None => Ok(0),
+        }
+    }
+
+    /// `:trash`: browses the deleted tasks.
+    pub fn open_trash(&mut self) -> Result<()> {
+        self.trash_view = Some(TrashView { entries: Vec::new(), selected: 0 });
+        self.reload_trash()?;
+        self.mode = Mode::Trash;
+        Ok(())
+    }
+
+    fn reload_trash(&mut self) -> Result<()> {
+        let entries = self.storage.trashed_tasks()?;
+        if let Some(view) = self.trash_view.as_mut() {
+            view.selected = view.selected.min(entries.len().saturating_sub(1));
+            view.entries = entries;
+        }
+        Ok(())
+    }
+
+    fn selected_trashed(&self) -> Option<Uuid> {
+        let view = self.trash_view.as_ref()?;
+        view.entries.get(view.selected).map(|e| e.task.id)
+    }
+
+    /// Puts a deleted task back where it was, with the subtasks deleted
+    /// along with it. A task whose parent is gone goes back to the top level.
+    /// Undoing it sends the tasks back to the trash.
+    /// Returns how many tasks were restored.
+    pub fn restore_trashed(&mut self, id: Uuid) -> Result<usize> {
+        let entries = self.storage.trashed_tasks()?;
+        let restored = with_trashed_subtasks(&entries, id);
+        self.with_history_group(|state| {
+            for entry in &restored {
+                let mut task = entry.task.clone();
+                if let Some(parent) = task.parent_id {
+                    let with_parent = restored.iter().any(|e| e.task.id == parent);
+                    if !with_parent && state.storage.get_task(parent)?.is_none() {
+                        task.parent_id = None;
+                    }
+                }
+                state.storage.push_snapshot(HistoryStack::Undo, task.id, None)?;
+                state.storage.save_task(&task)?;
+            }
+            Ok(())
+        })?;
+        if !restored.is_empty() {
+            self.storage.clear_redo()?;
+            self.write_todotxt()?;
+        }
+        self.reload_tasks()?;
+        Ok(restored.len())
+    }
+
+    /// Deletes a task in the trash for good, with the subtasks deleted along
+    /// with it.
+    pub fn purge_trashed(&mut self, id: Uuid) -> Result<usize> {
+        let entries = self.storage.trashed_tasks()?;
+        let purged = with_trashed_subtasks(&entries, id);
+        for entry in &purged {
+            self.storage.purge_trashed(entry.task.id)?;
+        }
+        Ok(purged.len())
+    }
+
+    /// `:trash empty`
+    pub fn empty_trash(&mut self) -> Result<usize> {
+        let entries = self.storage.trashed_tasks()?;
+        for entry in &entries {
+            self.storage.purge_trashed(entry.task.id)?;
+        }
+        Ok(entries.len())
+    }
+
+    pub fn handle_trash_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(view) = self.trash_view.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        let last = view.entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => view.selected = (view.selected + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('G') => view.selected = last,
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('r') | KeyCode::Enter => {
+                if let Some(id) = self.selected_trashed() {
+                    let restored = self.restore_trashed(id)?;
+                    self.reload_trash()?;
+                    self.info(format!("Restored {} tasks", restored));
+                }
+            }
+            KeyCode::Char('D') => {
+                if let Some(id) = self.selected_trashed() {
+                    let purged = self.purge_trashed(id)?;
+                    self.reload_trash()?;
+                    self.info(format!("Purged {} tasks", purged));
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.trash_view = None;
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `:trash` and `:trash empty`.
+    pub fn trash_command(&mut self, args: &str) -> Result<()> {
+        match args {
+            "" => self.open_trash(),
+            "empty" => {
+                let purged = self.empty_trash()?;
+                self.info(format!("Purged {} tasks", purged));
+                Ok(())
+            }
+            _ => Err(TaskVimError::Validation(format!("Invalid trash argument: {} (empty)", args))),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::core::state::AppState;
+use crate::domain::{Task, TaskStatus};
+use crate::error::Result;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Where a task sits in the subtask tree, computed on every reload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeNode {
+    pub depth: usize,
+    pub has_children: bool,
+    pub folded: bool,
+    /// Finished and total descendants, for the `3/5` progress rollup.
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Orders `tasks` depth-first so every subtask follows its parent, keeping
+/// the incoming order among siblings. Descendants of `folded` tasks are left
+/// out of the result. Tasks whose parent is not in `tasks` (e.g. filtered
+/// out) are shown as roots.
+pub fn build_tree(tasks: Vec<Task>, folded: &HashSet<Uuid>) -> (Vec<Task>, HashMap<Uuid, TreeNode>) {
+    let ids: HashSet<Uuid> = tasks.iter().map(|t| t.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<usize>> = HashMap::new();
+    for (i, task) in tasks.iter().enumerate() {
+        let parent = task.parent_id.filter(|p| ids.contains(p) && *p != task.id);
+        children.entry(parent).or_default().push(i);
+    }
+
+    let mut nodes: HashMap<Uuid, TreeNode> = HashMap::new();
+    let mut order = Vec::with_capacity(tasks.len());
+    let mut visited = vec![false; tasks.len()];
+
+    // Roots first; anything left unvisited afterwards is part of a parent
+    // cycle and gets promoted to a root rather than silently disappearing.
+    let mut roots = children.get(&None).cloned().unwrap_or_default();
+    roots.reverse();
+    let mut stack: Vec<(usize, usize)> = roots.into_iter().map(|i| (i, 0)).collect();
+    loop {
+        while let Some((i, depth)) = stack.pop() {
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            order.push((i, depth));
+            if let Some(kids) = children.get(&Some(tasks[i].id)) {
+                for &k in kids.iter().rev() {
+                    stack.push((k, depth + 1));
+                }
+            }
+        }
+        match visited.iter().position(|v| !v) {
+            Some(i) => stack.push((i, 0)),
+            None => break,
+        }
+    }
+
+    // Roll progress up from the leaves: walk the depth-first order backwards
+    // so every child is finished before its parent.
+    let mut rollup: HashMap<Uuid, (usize, usize)> = HashMap::new();
+    for &(i, _) in order.iter().rev() {
+        let task = &tasks[i];
+        let (done, total) = rollup.get(&task.id).copied().unwrap_or((0, 0));
+        let finished = matches!(task.status, TaskStatus::Done | TaskStatus::Archived) as usize;
+        if let Some(parent) = task.parent_id.filter(|p| ids.contains(p) && *p != task.id) {
+            let entry = rollup.entry(parent).or_insert((0, 0));
+            entry.0 += done + finished;
+            entry.1 += total + 1;
+        }
+    }
+
+    let mut result = Vec::with_capacity(order.len());
+    let mut tasks: Vec<Option<Task>> = tasks.into_iter().map(Some).collect();
+    let mut hidden_below: Option<usize> = None;
+    for (i, depth) in order {
+        if let Some(d) = hidden_below {
+            if depth > d {
+                continue;
+            }
+            hidden_below = None;
+        }
+        let task = tasks[i].take().expect("each task is visited once");
+        let (done, total) = rollup.get(&task.id).copied().unwrap_or((0, 0));
+        let is_folded = total > 0 && folded.contains(&task.id);
+        if is_folded {
+            hidden_below = Some(depth);
+        }
+        nodes.insert(task.id, TreeNode {
+            depth,
+            has_children: total > 0,
+            folded: is_folded,
+            done,
+            total,
+        });
+        result.push(task);
+    }
+
+    (result, nodes)
+}
+
+impl AppState {
+    /// All descendants of `id` across the whole database, parents before children.
+    pub fn descendant_ids(&self, id: Uuid) -> Result<Vec<Uuid>> {
+        let all = self.storage.get_tasks(None)?;
+        let mut result = Vec::new();
+        let mut frontier = vec![id];
+        while let Some(parent) = frontier.pop() {
+            for task in all.iter().filter(|t| t.parent_id == Some(parent)) {
+                if task.id != id && !result.contains(&task.id) {
+                    result.push(task.id);
+                    frontier.push(task.id);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// `>>`: makes the selected task the last child of the sibling above it.
+    pub fn indent_selected(&mut self) -> Result<()> {
+        let Some(mut task) = self.tasks.get(self.selected_index).cloned() else {
+            return Ok(());
+        };
+        let new_parent = self.tasks[..self.selected_index]
+            .iter()
+            .rev()
+            .find(|t| t.parent_id == task.parent_id)
+            .map(|t| t.id);
+        let Some(new_parent) = new_parent else {
+            return Ok(());
+        };
+
+        let last_child = self.siblings(Some(new_parent))?.last().map(|t| t.id);
+
+        self.with_history_group(|state| {
+            state.storage.push_history(&task)?;
+            task.parent_id = Some(new_parent);
+            task.position = state.slots_after(Some(new_parent), last_child, 1)?[0];
+            state.storage.save_task(&task)
+        })?;
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+        self.folded_tasks.remove(&new_parent);
+        self.run_hook("on_task_update", Some(&task));
+        self.reload_tasks()?;
+        self.select_task(task.id);
+        Ok(())
+    }
+
+    /// `<<`: moves the selected task out of its parent, right below it.
+    pub fn outdent_selected(&mut self) -> Result<()> {
+        let Some(mut task) = self.tasks.get(self.selected_index).cloned() else {
+            return Ok(());
+        };
+        let Some(parent) = task.parent_id.and_then(|p| self.storage.get_task(p).ok().flatten()) else {
+            return Ok(());
+        };
+
+        self.with_history_group(|state| {
+            state.storage.push_history(&task)?;
+            task.parent_id = parent.parent_id;
+            task.position = state.slots_after(parent.parent_id, Some(parent.id), 1)?[0];
+            state.storage.save_task(&task)
+        })?;
+        self.storage.clear_redo()?;
+        self.write_todotxt()?;
+
+        self.run_hook("on_task_update", Some(&task));
+        self.reload_tasks()?;
+        self.select_task(task.id);
+        Ok(())
+    }
+
+    /// Folds or unfolds the selected task's subtree. Returns false when the
+    /// task has no subtasks.
+    pub fn toggle_fold(&mut self) -> Result<bool> {
+        let Some(task) = self.tasks.get(self.selected_index) else {
+            return Ok(false);
+        };
+        if !self.tree.get(&task.id).map(|n| n.has_children).unwrap_or(false) {
+            return Ok(false);
+        }
+        let id = task.id;
+        if !self.folded_tasks.remove(&id) {
+            self.folded_tasks.insert(id);
+        }
+        self.reload_tasks()?;
+        self.select_task(id);
+        Ok(true)
+    }
+
+    /// Moves the selection to task `id` if it is currently displayed.
+    pub fn select_task(&mut self, id: Uuid) {
+        if let Some(idx) = self.tasks.iter().position(|t| t.id == id) {
+            self.selected_index = idx;
+        }
+    }
+}
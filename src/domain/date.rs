@@ -0,0 +1,30 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+
+/// Parses a user-entered date: `YYYY-MM-DD`, `today`, `tomorrow`,
+/// `yesterday` or an offset from today such as `+3d`, `-2w` or `1m`
+/// (days, weeks, months of 30 days). Dates resolve to midnight UTC.
+pub fn parse_date(input: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let input = input.trim().to_lowercase();
+    let today = now.date_naive();
+    let day = match input.as_str() {
+        "today" => today,
+        "tomorrow" => today + Duration::days(1),
+        "yesterday" => today - Duration::days(1),
+        s => match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => today + parse_offset(s)?,
+        },
+    };
+    Some(Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0)?))
+}
+
+fn parse_offset(s: &str) -> Option<Duration> {
+    let unit = s.chars().last()?;
+    let amount: i64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'd' => Some(Duration::days(amount)),
+        'w' => Some(Duration::weeks(amount)),
+        'm' => Some(Duration::days(amount * 30)),
+        _ => None,
+    }
+}
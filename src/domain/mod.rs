@@ -1,25 +1,26 @@
 pub mod query;
+pub mod date;
+pub mod modify;
+pub mod time;
+pub mod timeline;
+pub mod trash;
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TaskStatus {
+    #[default]
     Todo,
     Doing,
     Done,
     Archived,
 }
 
-impl Default for TaskStatus {
-    fn default() -> Self {
-        Self::Todo
-    }
-}
-
 impl std::fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
@@ -58,6 +59,8 @@ pub struct Task {
     pub recurrence_rule: Option<String>,
     pub dependencies: Vec<Uuid>,
     pub position: i32,
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
 }
 
 impl Task {
@@ -77,6 +80,28 @@ impl Task {
             recurrence_rule: None,
             dependencies: Vec::new(),
             position: 0,
+            parent_id: None,
+        }
+    }
+
+    /// A Taskwarrior-style urgency score: higher means more pressing.
+    pub fn urgency(&self, now: DateTime<Utc>) -> f64 {
+        if matches!(self.status, TaskStatus::Done | TaskStatus::Archived) {
+            return 0.0;
+        }
+
+        let mut score = self.priority as f64 * 1.5;
+        if self.status == TaskStatus::Doing {
+            score += 4.0;
+        }
+        if let Some(due) = self.due_date {
+            // Ramps from 0 two weeks out to 12 once the task is overdue
+            let days_left = (due - now).num_hours() as f64 / 24.0;
+            score += (12.0 * (14.0 - days_left) / 14.0).clamp(0.0, 12.0);
         }
+        let age_days = (now - self.created_at).num_days() as f64;
+        score += (age_days / 365.0).min(1.0) * 2.0;
+        score += (self.tags.len() as f64 * 0.5).min(1.5);
+        score
     }
 }
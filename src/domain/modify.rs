@@ -0,0 +1,174 @@
+use crate::domain::date::parse_date;
+use crate::domain::{Task, TaskStatus};
+use crate::error::{Result, TaskVimError};
+use chrono::{DateTime, Utc};
+
+/// One change from a `:modify` spec.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Modification {
+    Title(String),
+    Description(Option<String>),
+    Status(TaskStatus),
+    Priority(i32),
+    Project(Option<String>),
+    AddTag(String),
+    RemoveTag(String),
+    /// `tags=a,b` replaces all tags.
+    Tags(Vec<String>),
+    Due(Option<DateTime<Utc>>),
+    Recur(Option<String>),
+}
+
+fn invalid(msg: String) -> TaskVimError {
+    TaskVimError::Validation(msg)
+}
+
+/// Splits on whitespace, keeping double-quoted parts together:
+/// `description:"call the bank"` is one word.
+fn split_words(input: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            }
+            c => {
+                word.push(c);
+                started = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(invalid(format!("Unterminated quote: {}", input)));
+    }
+    if started {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Whether `rule` is a recurrence TaskVim understands: `daily`, `weekly`,
+/// `monthly`, `yearly`, `weekdays`, or a count and unit such as `3d`, `2w`,
+/// `6m` or `1y`.
+pub fn is_valid_recurrence(rule: &str) -> bool {
+    if matches!(rule, "daily" | "weekly" | "monthly" | "yearly" | "weekdays") {
+        return true;
+    }
+    let Some(unit) = rule.chars().last() else {
+        return false;
+    };
+    let count = &rule[..rule.len() - unit.len_utf8()];
+    matches!(unit, 'd' | 'w' | 'm' | 'y') && count.parse::<u32>().map(|n| n > 0).unwrap_or(false)
+}
+
+fn valid_tag(tag: &str, word: &str) -> Result<String> {
+    if tag.is_empty() || tag.contains(',') {
+        return Err(invalid(format!("Invalid tag: {}", word)));
+    }
+    Ok(tag.to_string())
+}
+
+/// Parses a spec like `project=Work +tag -old due:tomorrow priority:5`.
+/// Fields take `field=value` or `field:value`; an empty value clears the
+/// field. Every word is validated before anything is applied.
+pub fn parse_modifications(input: &str, now: DateTime<Utc>) -> Result<Vec<Modification>> {
+    let mut mods = Vec::new();
+    for word in split_words(input)? {
+        if let Some(tag) = word.strip_prefix('+') {
+            mods.push(Modification::AddTag(valid_tag(tag, &word)?));
+            continue;
+        }
+        if let Some(tag) = word.strip_prefix('-') {
+            mods.push(Modification::RemoveTag(valid_tag(tag, &word)?));
+            continue;
+        }
+        let Some(split) = word.find(['=', ':']) else {
+            return Err(invalid(format!("Expected field=value, +tag or -tag: {}", word)));
+        };
+        let (field, value) = (&word[..split], word[split + 1..].trim());
+        let optional = (!value.is_empty()).then(|| value.to_string());
+        let modification = match field {
+            "title" if value.is_empty() => return Err(invalid("Title cannot be empty".to_string())),
+            "title" => Modification::Title(value.to_string()),
+            "description" | "desc" => Modification::Description(optional),
+            "status" => Modification::Status(match value.to_lowercase().as_str() {
+                "todo" => TaskStatus::Todo,
+                "doing" => TaskStatus::Doing,
+                "done" => TaskStatus::Done,
+                "archived" => TaskStatus::Archived,
+                _ => return Err(invalid(format!("Invalid status: {} (todo, doing, done, archived)", value))),
+            }),
+            "priority" | "pri" => Modification::Priority(
+                value
+                    .parse()
+                    .ok()
+                    .filter(|p| (1..=5).contains(p))
+                    .ok_or_else(|| invalid(format!("Invalid priority: {} (1-5)", value)))?,
+            ),
+            "project" | "proj" => Modification::Project(optional),
+            "tags" | "tag" => Modification::Tags(
+                value
+                    .split(',')
+                    .filter(|t| !t.is_empty())
+                    .map(|t| valid_tag(t, &word))
+                    .collect::<Result<_>>()?,
+            ),
+            "due" => Modification::Due(match value {
+                "" | "none" => None,
+                _ => Some(parse_date(value, now).ok_or_else(|| invalid(format!("Invalid date: {}", value)))?),
+            }),
+            "recur" | "recurrence" => match value {
+                "" | "none" => Modification::Recur(None),
+                _ if is_valid_recurrence(value) => Modification::Recur(Some(value.to_string())),
+                _ => return Err(invalid(format!("Invalid recurrence: {} (daily, weekly, monthly, yearly, weekdays or e.g. 3d, 2w)", value))),
+            },
+            _ => return Err(invalid(format!("Unknown field: {}", field))),
+        };
+        mods.push(modification);
+    }
+    if mods.is_empty() {
+        return Err(invalid("Nothing to modify".to_string()));
+    }
+    Ok(mods)
+}
+
+impl Modification {
+    /// Applies the change to `task`. Returns whether anything changed.
+    pub fn apply(&self, task: &mut Task) -> bool {
+        fn set<T: PartialEq + Clone>(field: &mut T, value: &T) -> bool {
+            let changed = field != value;
+            *field = value.clone();
+            changed
+        }
+        match self {
+            Modification::Title(title) => set(&mut task.title, title),
+            Modification::Description(description) => set(&mut task.description, description),
+            Modification::Status(status) => set(&mut task.status, status),
+            Modification::Priority(priority) => set(&mut task.priority, priority),
+            Modification::Project(project) => set(&mut task.project, project),
+            Modification::AddTag(tag) if !task.tags.contains(tag) => {
+                task.tags.push(tag.clone());
+                true
+            }
+            Modification::AddTag(_) => false,
+            Modification::RemoveTag(tag) => {
+                let before = task.tags.len();
+                task.tags.retain(|t| t != tag);
+                task.tags.len() != before
+            }
+            Modification::Tags(tags) => set(&mut task.tags, tags),
+            Modification::Due(due) => set(&mut task.due_date, due),
+            Modification::Recur(rule) => set(&mut task.recurrence_rule, rule),
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::domain::date::parse_date;
 use crate::error::{Result, TaskVimError};
+use chrono::Utc;
 
 #[derive(Debug, PartialEq)]
 pub enum Operator {
@@ -38,6 +40,18 @@ impl Operator {
     }
 }
 
+/// The filter DSL operators. Two-character operators must be tried before
+/// their one-character prefixes.
+pub const OPERATORS: [&str; 7] = [">=", "<=", "!=", "=", ">", "<", "contains"];
+
+/// The fields the filter DSL understands. `done` (alias `completed`) and
+/// `started` are derived from the status events: when the task last moved
+/// to Done and first moved to Doing.
+pub const FIELDS: [&str; 8] = ["status", "priority", "project", "due", "created", "done", "started", "tag"];
+
+const COMPLETED_AT: &str = "(SELECT MAX(e.at) FROM status_events e WHERE e.task_id = tasks.id AND e.to_status = 'Done')";
+const STARTED_AT: &str = "(SELECT MIN(e.at) FROM status_events e WHERE e.task_id = tasks.id AND e.to_status = 'Doing')";
+
 #[derive(Debug)]
 pub struct Filter {
     pub field: String,
@@ -53,23 +67,10 @@ impl Filter {
         // This is a naive parser.
         
         for part in input.split_whitespace() {
-            let (op_str, _op_len, op_enum) = if part.contains(">=") {
-                (">=", 2, Operator::Gte)
-            } else if part.contains("<=") {
-                ("<=", 2, Operator::Lte)
-            } else if part.contains("!=") {
-                ("!=", 2, Operator::Neq)
-            } else if part.contains("=") {
-                ("=", 1, Operator::Eq)
-            } else if part.contains(">") {
-                (">", 1, Operator::Gt)
-            } else if part.contains("<") {
-                ("<", 1, Operator::Lt)
-            } else if part.contains("contains") {
-                ("contains", 8, Operator::Contains)
-            } else {
+            let Some(op_str) = OPERATORS.iter().find(|op| part.contains(*op)) else {
                 continue; // Skip invalid parts
             };
+            let op_enum = Operator::from_str(op_str).expect("operator table entry");
 
             let parts: Vec<&str> = part.splitn(2, op_str).collect();
             if parts.len() != 2 {
@@ -90,22 +91,56 @@ impl Filter {
     }
 
     pub fn to_sql_condition(&self) -> Result<(String, String)> {
+        if self.field == "tag" {
+            return self.tag_sql_condition();
+        }
+
         let col = match self.field.as_str() {
             "status" => "status",
             "priority" => "priority",
             "project" => "project",
             "due" => "due_date",
             "created" => "created_at",
-            "tag" => return Err(TaskVimError::Validation("Tag filtering not yet implemented in SQL gen".into())),
+            "done" | "completed" => COMPLETED_AT,
+            "started" => STARTED_AT,
              _ => return Err(TaskVimError::Validation(format!("Unknown field: {}", self.field))),
         };
+        let is_date = matches!(self.field.as_str(), "due" | "created" | "done" | "completed" | "started");
 
         let val = if self.operator == Operator::Contains {
             format!("%{}%", self.value)
+        } else if let (true, Some(date)) = (is_date, parse_date(&self.value, Utc::now())) {
+            // Dates are stored as RFC 3339, so `done>=-7d` compares as text
+            date.to_rfc3339()
         } else {
             self.value.clone()
         };
 
         Ok((format!("{} {} ?", col, self.operator.to_sql()), val))
     }
+
+    /// Whether a field holding `text` passes the condition, ignoring ASCII
+    /// case.
+    pub fn matches_text(&self, text: &str) -> bool {
+        let (text, value) = (text.to_ascii_lowercase(), self.value.to_ascii_lowercase());
+        match self.operator {
+            Operator::Eq => text == value,
+            Operator::Neq => text != value,
+            Operator::Gt => text > value,
+            Operator::Lt => text < value,
+            Operator::Gte => text >= value,
+            Operator::Lte => text <= value,
+            Operator::Contains => text.contains(&value),
+        }
+    }
+
+    fn tag_sql_condition(&self) -> Result<(String, String)> {
+        const TAGGED: &str = "SELECT tt.task_id FROM task_tags tt JOIN tags t ON t.id = tt.tag_id WHERE t.name";
+        match self.operator {
+            Operator::Eq => Ok((format!("id IN ({} = ?)", TAGGED), self.value.clone())),
+            Operator::Neq => Ok((format!("id NOT IN ({} = ?)", TAGGED), self.value.clone())),
+            Operator::Contains => Ok((format!("id IN ({} LIKE ?)", TAGGED), format!("%{}%", self.value))),
+            _ => Err(TaskVimError::Validation(format!("Operator {} is not supported for tags", self.operator.to_sql()))),
+        }
+    }
 }
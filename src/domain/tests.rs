@@ -29,4 +29,100 @@ mod tests {
         assert_eq!(sql, "priority > ?");
         assert_eq!(val, "2");
     }
+
+    #[test]
+    fn test_tag_filter_sql_generation() {
+        let f = Filter::parse("tag=work").unwrap().remove(0);
+        let (sql, val) = f.to_sql_condition().unwrap();
+        assert!(sql.starts_with("id IN ("));
+        assert_eq!(val, "work");
+
+        let f = Filter::parse("tag>work").unwrap().remove(0);
+        assert!(f.to_sql_condition().is_err());
+    }
+
+    #[test]
+    fn test_date_parsing() {
+        use crate::domain::date::parse_date;
+        use chrono::{TimeZone, Utc};
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+        assert_eq!(parse_date("2024-03-01", now), Some(day(1)));
+        assert_eq!(parse_date("today", now), Some(day(10)));
+        assert_eq!(parse_date("Tomorrow", now), Some(day(11)));
+        assert_eq!(parse_date("+3d", now), Some(day(13)));
+        assert_eq!(parse_date("-1w", now), Some(day(3)));
+        assert_eq!(parse_date("soon", now), None);
+        assert_eq!(parse_date("3x", now), None);
+    }
+
+    #[test]
+    fn test_modification_parsing() {
+        use crate::domain::modify::{is_valid_recurrence, parse_modifications, Modification};
+        use crate::domain::{Task, TaskStatus};
+        use crate::error::TaskVimError;
+        use chrono::{TimeZone, Utc};
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let mods = parse_modifications(r#"project=Work +home -old due:tomorrow priority:5 recur:weekly description:"Call the bank""#, now).unwrap();
+        assert_eq!(mods, vec![
+            Modification::Project(Some("Work".to_string())),
+            Modification::AddTag("home".to_string()),
+            Modification::RemoveTag("old".to_string()),
+            Modification::Due(Some(Utc.with_ymd_and_hms(2024, 3, 11, 0, 0, 0).unwrap())),
+            Modification::Priority(5),
+            Modification::Recur(Some("weekly".to_string())),
+            Modification::Description(Some("Call the bank".to_string())),
+        ]);
+        assert_eq!(parse_modifications("project= due:none", now).unwrap(), vec![Modification::Project(None), Modification::Due(None)]);
+
+        let error = |spec| match parse_modifications(spec, now) {
+            Err(TaskVimError::Validation(msg)) => msg,
+            other => panic!("expected a validation error, got {:?}", other),
+        };
+        assert!(error("priority:9").starts_with("Invalid priority: 9"));
+        assert!(error("status=later").starts_with("Invalid status: later"));
+        assert_eq!(error("due:someday"), "Invalid date: someday");
+        assert!(error("recur:often").starts_with("Invalid recurrence: often"));
+        assert_eq!(error("colour=red"), "Unknown field: colour");
+        assert_eq!(error("title="), "Title cannot be empty");
+        assert!(error("urgent").starts_with("Expected field=value"));
+        assert!(is_valid_recurrence("3d") && is_valid_recurrence("weekdays") && !is_valid_recurrence("0w"));
+
+        let mut task = Task::new("Report".to_string());
+        task.tags = vec!["old".to_string()];
+        let changed: Vec<bool> = mods.iter().map(|m| m.apply(&mut task)).collect();
+        assert!(changed.iter().all(|&c| c));
+        assert_eq!(task.tags, vec!["home"]);
+        assert!(!Modification::Status(TaskStatus::Todo).apply(&mut task));
+    }
+
+    #[test]
+    fn test_duration_parsing() {
+        use crate::domain::time::{format_duration, parse_duration};
+        use chrono::Duration;
+
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1.5h"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1:30"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("45s"), Some(Duration::seconds(45)));
+        assert_eq!(parse_duration("90"), None);
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("1:75"), None);
+        assert_eq!(format_duration(Duration::minutes(125)), "2h 05m");
+        assert_eq!(format_duration(Duration::seconds(750)), "12m 30s");
+    }
+
+    #[test]
+    fn test_derived_date_filters() {
+        let f = Filter::parse("done>=-7d").unwrap().remove(0);
+        let (sql, val) = f.to_sql_condition().unwrap();
+        assert!(sql.contains("status_events") && sql.ends_with(">= ?"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&val).is_ok());
+
+        let f = Filter::parse("started<2024-05-01").unwrap().remove(0);
+        assert_eq!(f.to_sql_condition().unwrap().1, "2024-05-01T00:00:00+00:00");
+    }
 }
@@ -0,0 +1,50 @@
+use crate::domain::TaskStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// One status change of a task. `from` is `None` when the task was created
+/// (or for changes from before events were recorded).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusEvent {
+    #[serde(skip)]
+    pub id: i64,
+    pub task_id: Uuid,
+    pub from: Option<TaskStatus>,
+    pub to: TaskStatus,
+    pub at: DateTime<Utc>,
+}
+
+/// The completions in `events` (in the order they were recorded): moving to
+/// Done completes a task, reopening it to Todo or Doing takes the last
+/// completion back, and archiving keeps it.
+pub fn completions(events: &[StatusEvent]) -> Vec<(Uuid, DateTime<Utc>)> {
+    let mut done: HashMap<Uuid, Vec<DateTime<Utc>>> = HashMap::new();
+    let mut is_done: HashMap<Uuid, bool> = HashMap::new();
+    for event in events {
+        let was_done = event.from.map(|s| s == TaskStatus::Done).unwrap_or(is_done.get(&event.task_id).copied().unwrap_or(false));
+        match event.to {
+            TaskStatus::Done if !was_done => done.entry(event.task_id).or_default().push(event.at),
+            TaskStatus::Todo | TaskStatus::Doing if was_done => {
+                done.entry(event.task_id).or_default().pop();
+            }
+            _ => {}
+        }
+        is_done.insert(event.task_id, event.to == TaskStatus::Done);
+    }
+    let mut completions: Vec<(Uuid, DateTime<Utc>)> =
+        done.into_iter().flat_map(|(id, times)| times.into_iter().map(move |at| (id, at))).collect();
+    completions.sort_by_key(|(_, at)| *at);
+    completions
+}
+
+/// When the task first moved to Doing.
+pub fn started_at(events: &[StatusEvent], task_id: Uuid) -> Option<DateTime<Utc>> {
+    events.iter().filter(|e| e.task_id == task_id && e.to == TaskStatus::Doing).map(|e| e.at).min()
+}
+
+/// When the task last moved to Done.
+pub fn completed_at(events: &[StatusEvent], task_id: Uuid) -> Option<DateTime<Utc>> {
+    events.iter().filter(|e| e.task_id == task_id && e.to == TaskStatus::Done).map(|e| e.at).max()
+}
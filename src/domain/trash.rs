@@ -0,0 +1,12 @@
+use crate::domain::Task;
+use chrono::{DateTime, Utc};
+
+/// A deleted task waiting in the trash, with its tags, parent and
+/// dependencies. The storage also keeps the tasks that depended on it.
+#[derive(Debug, Clone)]
+pub struct TrashedTask {
+    pub task: Task,
+    pub deleted_at: DateTime<Utc>,
+    /// Tasks deleted together (a task with its subtasks) share a batch.
+    pub batch: i64,
+}
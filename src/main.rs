@@ -4,6 +4,7 @@ mod storage;
 mod core;
 mod ui;
 mod config;
+mod cli;
 
 use crate::storage::SqliteStorage;
 use crate::core::AppState;
@@ -11,18 +12,36 @@ use crate::ui::Tui;
 use crate::config::LuaConfig;
 use crate::error::Result;
 
-use std::sync::Arc;
+use chrono::Utc;
+use std::rc::Rc;
 
 fn main() -> Result<()> {
     // Initialize Lua config
-    let lua_config = Arc::new(LuaConfig::new()?);
-    let _ = lua_config.load_user_config();
+    let lua_config = Rc::new(LuaConfig::new()?);
+    let config_result = lua_config.load_user_config();
 
     // Initialize storage
     let storage = SqliteStorage::new("taskvim.db")?;
     
     // Initialize app state
-    let mut state = AppState::new(storage, Arc::clone(&lua_config))?;
+    let mut state = AppState::new(storage, Rc::clone(&lua_config))?;
+    if let Err(err) = config_result {
+        state.error(format!("init.lua: {}", err));
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::run(&args, &mut state)? {
+        return Ok(());
+    }
+
+    // Apply the archive policy (see `set.archive`)
+    if state.config.archive.on_startup {
+        let (archived, purged) = state.collect_garbage(Utc::now())?;
+        if archived + purged > 0 {
+            state.info(format!("Archived {} and purged {} tasks", archived, purged));
+        }
+    }
+    state.expire_trash(Utc::now())?;
     
     // Initialize and run TUI
     let mut tui = Tui::new()?;
@@ -0,0 +1,59 @@
+use crate::domain::time::TimeEntry;
+use crate::domain::timeline::StatusEvent;
+use crate::domain::Task;
+use crate::error::{Result, TaskVimError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The version of the export format written by `:export`. Bump it when the
+/// format changes in a way older versions cannot read.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// A JSON dump of tasks (with their tags, dependencies and subtasks), their
+/// status history and their time entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Export {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub status_events: Vec<StatusEvent>,
+    #[serde(default)]
+    pub time_entries: Vec<TimeEntry>,
+}
+
+impl Export {
+    pub fn new(tasks: Vec<Task>, status_events: Vec<StatusEvent>, time_entries: Vec<TimeEntry>) -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            exported_at: Utc::now(),
+            tasks,
+            status_events,
+            time_entries,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads an export of this or an older version. A bare list of tasks, as
+    /// `:export` wrote before the format was versioned, reads as version 0.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(text)?;
+        if value.is_array() {
+            return Ok(Self {
+                version: 0,
+                ..Self::new(serde_json::from_value(value)?, Vec::new(), Vec::new())
+            });
+        }
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+        if version > EXPORT_VERSION as u64 {
+            return Err(TaskVimError::Validation(format!(
+                "Export version {} is newer than this taskvim reads ({})",
+                version, EXPORT_VERSION
+            )));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::domain::{Task, TaskStatus};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// A list item: `- `, `* `, `+ ` or a number followed by `. ` or `) `.
+/// Returns what follows the marker.
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(rest);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))
+}
+
+/// The checkbox of a list item and the text after it.
+fn checkbox(item: &str) -> Option<(bool, &str)> {
+    let done = match item.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &item[3..];
+    (rest.is_empty() || rest.starts_with(' ')).then(|| (done, rest.trim()))
+}
+
+fn indent_of(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Reads the checklists of a Markdown file. The heading above an item is
+/// its project, checklists nested in an item are its subtasks and other
+/// text nested in it is its description. Everything else is skipped.
+pub fn parse(text: &str, now: DateTime<Utc>) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut project: Option<String> = None;
+    // The open items: their indent and index in `tasks`
+    let mut open: Vec<(usize, usize)> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let indent = indent_of(line);
+        if indent == 0 && trimmed.starts_with('#') {
+            let heading = trimmed.trim_start_matches('#').trim().trim_end_matches('#').trim();
+            project = (!heading.is_empty()).then(|| heading.to_string());
+            open.clear();
+            continue;
+        }
+        while open.last().map(|&(i, _)| i >= indent).unwrap_or(false) {
+            open.pop();
+        }
+        let item = list_item(trimmed);
+        if let Some((done, title)) = item.and_then(checkbox) {
+            let mut task = Task::new(title.to_string());
+            task.created_at = now;
+            task.updated_at = now;
+            task.project = project.clone();
+            task.parent_id = open.last().map(|&(_, i)| tasks[i].id);
+            if done {
+                task.status = TaskStatus::Done;
+            }
+            open.push((indent, tasks.len()));
+            tasks.push(task);
+        } else if let Some(&(_, i)) = open.last() {
+            let note = item.unwrap_or(trimmed);
+            let description = tasks[i].description.get_or_insert_with(String::new);
+            if !description.is_empty() {
+                description.push('\n');
+            }
+            description.push_str(note);
+        } else if indent == 0 {
+            // A paragraph ends the list
+            open.clear();
+        }
+    }
+    tasks
+}
+
+/// Writes tasks as Markdown checklists under a heading per project, with
+/// subtasks nested under their parent and descriptions below the title.
+/// Subtasks stay with their parent whatever their own project.
+pub fn export(tasks: &[Task]) -> String {
+    let ids: HashSet<Uuid> = tasks.iter().map(|t| t.id).collect();
+    let roots: Vec<&Task> = tasks.iter().filter(|t| t.parent_id.map(|p| !ids.contains(&p)).unwrap_or(true)).collect();
+    let mut projects: Vec<Option<&str>> = Vec::new();
+    for root in &roots {
+        if !projects.contains(&root.project.as_deref()) {
+            projects.push(root.project.as_deref());
+        }
+    }
+    // Tasks without a project come first, without a heading
+    projects.sort_by_key(|p| p.is_some());
+
+    let mut out = String::new();
+    for project in projects {
+        if let Some(project) = project {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("## {}\n\n", project));
+        }
+        for root in roots.iter().filter(|t| t.project.as_deref() == project) {
+            write_item(&mut out, tasks, root, 0);
+        }
+    }
+    out
+}
+
+fn write_item(out: &mut String, tasks: &[Task], task: &Task, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let mark = if matches!(task.status, TaskStatus::Done | TaskStatus::Archived) { 'x' } else { ' ' };
+    out.push_str(&format!("{}- [{}] {}\n", indent, mark, task.title));
+    for line in task.description.as_deref().unwrap_or("").lines().filter(|l| !l.trim().is_empty()) {
+        out.push_str(&format!("{}  {}\n", indent, line.trim()));
+    }
+    for child in tasks.iter().filter(|t| t.parent_id == Some(task.id)) {
+        write_item(out, tasks, child, depth + 1);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod json;
+pub mod markdown;
 pub mod sqlite;
+pub mod taskwarrior;
+pub mod todotxt;
 
 pub use sqlite::SqliteStorage;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
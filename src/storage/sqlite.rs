@@ -1,18 +1,41 @@
 use crate::domain::{Task, TaskStatus};
 use crate::domain::query::Filter;
+use crate::domain::time::TimeEntry;
+use crate::domain::timeline::StatusEvent;
+use crate::domain::trash::TrashedTask;
 use crate::error::Result;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// The two snapshot stacks used by undo and redo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStack {
+    Undo,
+    Redo,
+}
+
+impl HistoryStack {
+    fn table(&self) -> &'static str {
+        match self {
+            HistoryStack::Undo => "history",
+            HistoryStack::Redo => "redo_history",
+        }
+    }
+}
 
 pub struct SqliteStorage {
     conn: Connection,
+    history_group: Cell<Option<i64>>,
+    history_depth: Cell<usize>,
 }
 
 impl SqliteStorage {
     pub fn new(path: &str) -> Result<Self> {
         let conn = Connection::open(path)?;
-        let storage = Self { conn };
+        let storage = Self { conn, history_group: Cell::new(None), history_depth: Cell::new(0) };
         storage.init_schema()?;
         Ok(storage)
     }
@@ -30,10 +53,12 @@ impl SqliteStorage {
                 updated_at TEXT NOT NULL,
                 project TEXT,
                 recurrence_rule TEXT,
-                position INTEGER NOT NULL
+                position INTEGER NOT NULL,
+                parent_id TEXT
             )",
             [],
         )?;
+        self.ensure_column("tasks", "parent_id", "TEXT")?;
 
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS tags (
@@ -85,20 +110,107 @@ impl SqliteStorage {
             [],
         )?;
 
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS registers (
+                name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY (name, kind)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS command_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                command TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS time_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id TEXT NOT NULL,
+                start TEXT NOT NULL,
+                end TEXT
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS pomodoros (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id TEXT NOT NULL,
+                completed_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS status_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id TEXT NOT NULL,
+                from_status TEXT,
+                to_status TEXT NOT NULL,
+                at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.seed_status_events()?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS trash (
+                id TEXT PRIMARY KEY,
+                task TEXT NOT NULL,
+                dependents TEXT NOT NULL,
+                deleted_at TEXT NOT NULL,
+                batch INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        self.ensure_column("history", "group_id", "INTEGER")?;
+        self.ensure_column("redo_history", "group_id", "INTEGER")?;
+
         // Indexes
         self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)", [])?;
         self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)", [])?;
         self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)", [])?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_time_entries_task ON time_entries(task_id)", [])?;
         self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project)", [])?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tasks_parent ON tasks(parent_id)", [])?;
+
+        Ok(())
+    }
 
+    /// Adds `column` to databases created before it existed. New columns are
+    /// always appended, so positional reads in `get_tasks` stay valid.
+    fn ensure_column(&self, table: &str, column: &str, decl: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .iter()
+            .any(|name| name == column);
+        if !exists {
+            self.conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+        }
         Ok(())
     }
 
     pub fn save_task(&self, task: &Task) -> Result<()> {
+        self.log_status_change(task)?;
+        self.import_task(task)
+    }
+
+    /// Saves a task without recording a status event, for imports that
+    /// bring the task's events along.
+    pub fn import_task(&self, task: &Task) -> Result<()> {
         self.conn.execute(
             "INSERT OR REPLACE INTO tasks (
-                id, title, description, status, priority, due_date, created_at, updated_at, project, recurrence_rule, position
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                id, title, description, status, priority, due_date, created_at, updated_at, project, recurrence_rule, position, parent_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 task.id.to_string(),
                 task.title,
@@ -111,6 +223,7 @@ impl SqliteStorage {
                 task.project,
                 task.recurrence_rule,
                 task.position,
+                task.parent_id.map(|p| p.to_string()),
             ],
         )?;
 
@@ -137,6 +250,7 @@ impl SqliteStorage {
                 params![task.id.to_string(), dep_id.to_string() as String],
             )?;
         }
+        self.untrash(task.id)?;
 
         Ok(())
     }
@@ -162,8 +276,11 @@ impl SqliteStorage {
         }
         
         sql.push_str(" ORDER BY position ASC, created_at DESC");
+        self.query_tasks(&sql, params)
+    }
 
-        let mut stmt = self.conn.prepare(&sql)?;
+    fn query_tasks(&self, sql: &str, params: Vec<Box<dyn rusqlite::ToSql>>) -> Result<Vec<Task>> {
+        let mut stmt = self.conn.prepare(sql)?;
         
         let task_iter = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
             let id_str: String = row.get(0)?;
@@ -173,6 +290,7 @@ impl SqliteStorage {
             let created_at_str: String = row.get(6)?;
             let updated_at_str: String = row.get(7)?;
             let due_date_str: Option<String> = row.get(5)?;
+            let parent_str: Option<String> = row.get(11)?;
 
             Ok(Task {
                 id,
@@ -186,6 +304,7 @@ impl SqliteStorage {
                 project: row.get(8)?,
                 recurrence_rule: row.get(9)?,
                 position: row.get(10)?,
+                parent_id: parent_str.and_then(|s| Uuid::parse_str(&s).ok()),
                 tags: Vec::new(),
                 dependencies: Vec::new(),
             })
@@ -222,71 +341,408 @@ impl SqliteStorage {
         Ok(tasks)
     }
 
+    /// Number of tasks per project, sorted by project name. Archived tasks
+    /// count with `include_archived` only.
+    pub fn project_counts(&self, include_archived: bool) -> Result<Vec<(String, usize)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT project, COUNT(*) FROM tasks WHERE project IS NOT NULL AND (?1 OR status != 'Archived') GROUP BY project ORDER BY project"
+        )?;
+        let rows = stmt.query_map([include_archived], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Number of tasks per tag, sorted by tag name. Unused tags are omitted,
+    /// and Archived tasks count with `include_archived` only.
+    pub fn tag_counts(&self, include_archived: bool) -> Result<Vec<(String, usize)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.name, COUNT(*) FROM tags t JOIN task_tags tt ON t.id = tt.tag_id JOIN tasks ON tasks.id = tt.task_id \
+             WHERE ?1 OR tasks.status != 'Archived' GROUP BY t.name ORDER BY t.name"
+        )?;
+        let rows = stmt.query_map([include_archived], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Deletes a task for good, with its tags and dependency edges.
     pub fn delete_task(&self, id: Uuid) -> Result<()> {
-        self.conn.execute("DELETE FROM tasks WHERE id = ?", [id.to_string()])?;
+        let id = id.to_string();
+        self.conn.execute("DELETE FROM tasks WHERE id = ?", [&id])?;
+        self.conn.execute("DELETE FROM task_tags WHERE task_id = ?", [&id])?;
+        self.conn.execute("DELETE FROM dependencies WHERE task_id = ?1 OR depends_on = ?1", [&id])?;
         Ok(())
     }
 
-    pub fn push_history(&self, task: &Task) -> Result<()> {
-        let snapshot = serde_json::to_string(task)?;
+    /// A new batch number for `trash_task`.
+    pub fn next_trash_batch(&self) -> Result<i64> {
+        Ok(self.conn.query_row("SELECT COALESCE(MAX(batch), 0) + 1 FROM trash", [], |row| row.get(0))?)
+    }
+
+    /// Moves a task to the trash: it is kept with its tags, dependencies and
+    /// the tasks depending on it, and deleted from the task list.
+    pub fn trash_task(&self, task: &Task, batch: i64, at: DateTime<Utc>) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT task_id FROM dependencies WHERE depends_on = ?")?;
+        let dependents = stmt
+            .query_map([task.id.to_string()], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
         self.conn.execute(
-            "INSERT INTO history (task_id, snapshot, timestamp) VALUES (?, ?, ?)",
-            params![task.id.to_string(), snapshot, Utc::now().to_rfc3339()],
+            "INSERT OR REPLACE INTO trash (id, task, dependents, deleted_at, batch) VALUES (?, ?, ?, ?, ?)",
+            params![task.id.to_string(), serde_json::to_string(task)?, serde_json::to_string(&dependents)?, at.to_rfc3339(), batch],
         )?;
+        self.delete_task(task.id)
+    }
+
+    /// The trash, most recently deleted first.
+    pub fn trashed_tasks(&self) -> Result<Vec<TrashedTask>> {
+        let mut stmt = self.conn.prepare("SELECT task, deleted_at, batch FROM trash ORDER BY batch DESC, rowid")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(task, deleted_at, batch)| {
+                Some(TrashedTask {
+                    task: serde_json::from_str(&task).ok()?,
+                    deleted_at: DateTime::parse_from_rfc3339(&deleted_at).ok()?.with_timezone(&Utc),
+                    batch,
+                })
+            })
+            .collect())
+    }
+
+    /// A saved task is no longer in the trash, whether it was restored from
+    /// there or by undoing its deletion. The tasks that depended on it and
+    /// still exist get their dependency back.
+    fn untrash(&self, id: Uuid) -> Result<()> {
+        let dependents: Option<String> = self
+            .conn
+            .query_row("SELECT dependents FROM trash WHERE id = ?", [id.to_string()], |row| row.get(0))
+            .optional()?;
+        let Some(dependents) = dependents else {
+            return Ok(());
+        };
+        let dependents: Vec<String> = serde_json::from_str(&dependents).unwrap_or_default();
+        for dependent in dependents {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO dependencies (task_id, depends_on) SELECT id, ? FROM tasks WHERE id = ?",
+                params![id.to_string(), dependent],
+            )?;
+        }
+        self.conn.execute("DELETE FROM trash WHERE id = ?", [id.to_string()])?;
         Ok(())
     }
 
-    pub fn get_latest_history(&self) -> Result<Option<(i64, Task)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, snapshot FROM history ORDER BY id DESC LIMIT 1"
+    /// Removes a task from the trash for good.
+    pub fn purge_trashed(&self, id: Uuid) -> Result<()> {
+        self.conn.execute("DELETE FROM trash WHERE id = ?", [id.to_string()])?;
+        Ok(())
+    }
+
+    /// Purges what was deleted before `before`. Returns how many tasks.
+    pub fn expire_trash(&self, before: DateTime<Utc>) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM trash WHERE deleted_at < ?", [before.to_rfc3339()])?)
+    }
+
+    pub fn get_task(&self, id: Uuid) -> Result<Option<Task>> {
+        let tasks = self.query_tasks("SELECT * FROM tasks WHERE id = ?", vec![Box::new(id.to_string())])?;
+        Ok(tasks.into_iter().next())
+    }
+
+    /// Groups every snapshot pushed until `end_history_group` into a single
+    /// undo step. Groups nest: snapshots stay in the outermost group until it
+    /// ends.
+    pub fn begin_history_group(&self) -> Result<()> {
+        if self.history_depth.get() == 0 {
+            let next = self.next_group_id()?;
+            self.history_group.set(Some(next));
+        }
+        self.history_depth.set(self.history_depth.get() + 1);
+        Ok(())
+    }
+
+    pub fn end_history_group(&self) {
+        let depth = self.history_depth.get().saturating_sub(1);
+        self.history_depth.set(depth);
+        if depth == 0 {
+            self.history_group.set(None);
+        }
+    }
+
+    fn next_group_id(&self) -> Result<i64> {
+        let max: Option<i64> = self.conn.query_row(
+            "SELECT MAX(g) FROM (SELECT MAX(group_id) AS g FROM history UNION ALL SELECT MAX(group_id) FROM redo_history)",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(max.unwrap_or(0) + 1)
+    }
+
+    /// Records the state of task `id` on `stack`. `None` means the task did
+    /// not exist, so reverting the entry moves it to the trash.
+    pub fn push_snapshot(&self, stack: HistoryStack, id: Uuid, task: Option<&Task>) -> Result<()> {
+        let snapshot = serde_json::to_string(&task)?;
+        let group = match self.history_group.get() {
+            Some(group) => group,
+            None => self.next_group_id()?,
+        };
+        self.conn.execute(
+            &format!("INSERT INTO {} (task_id, snapshot, timestamp, group_id) VALUES (?, ?, ?, ?)", stack.table()),
+            params![id.to_string(), snapshot, Utc::now().to_rfc3339(), group],
         )?;
-        let mut rows = stmt.query([])?;
-        if let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let snapshot: String = row.get(1)?;
-            let task: Task = serde_json::from_str(&snapshot)?;
-            Ok(Some((id, task)))
-        } else {
-            Ok(None)
+        Ok(())
+    }
+
+    pub fn push_history(&self, task: &Task) -> Result<()> {
+        self.push_snapshot(HistoryStack::Undo, task.id, Some(task))
+    }
+
+    /// Removes the most recent group from `stack` and returns its snapshots
+    /// in the order they were pushed.
+    pub fn take_latest_group(&self, stack: HistoryStack) -> Result<Vec<(Uuid, Option<Task>)>> {
+        let table = stack.table();
+        let latest: Option<(i64, Option<i64>)> = self.conn
+            .query_row(
+                &format!("SELECT id, group_id FROM {} ORDER BY id DESC LIMIT 1", table),
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        // Rows written before grouping existed have no group and stand alone
+        let (condition, key) = match latest {
+            None => return Ok(Vec::new()),
+            Some((_, Some(group))) => ("group_id = ?", group),
+            Some((id, None)) => ("id = ?", id),
+        };
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT task_id, snapshot FROM {} WHERE {} ORDER BY id ASC", table, condition
+        ))?;
+        let rows = stmt
+            .query_map([key], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut entries = Vec::new();
+        for (id_str, snapshot) in rows {
+            let id = Uuid::parse_str(&id_str).map_err(|e| crate::error::TaskVimError::Internal(e.to_string()))?;
+            let task: Option<Task> = serde_json::from_str(&snapshot)?;
+            entries.push((id, task));
+        }
+
+        self.conn.execute(&format!("DELETE FROM {} WHERE {}", table, condition), [key])?;
+        Ok(entries)
+    }
+
+    /// Drops the undo and redo entries of task `id`, and those that put
+    /// another task under it, so no undo can bring the task back.
+    pub fn forget_history(&self, id: Uuid) -> Result<()> {
+        for stack in [HistoryStack::Undo, HistoryStack::Redo] {
+            self.conn.execute(
+                &format!("DELETE FROM {} WHERE task_id = ?1 OR json_extract(snapshot, '$.parent_id') = ?1", stack.table()),
+                [id.to_string()],
+            )?;
         }
+        Ok(())
+    }
+
+    pub fn clear_redo(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM redo_history", [])?;
+        Ok(())
+    }
+
+    /// Persists register `name` so it survives restarts. `kind` separates
+    /// yanked tasks (`tasks`) from recorded macros (`macro`).
+    pub fn save_register(&self, name: char, kind: &str, content: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO registers (name, kind, content) VALUES (?, ?, ?)",
+            params![name.to_string(), kind, content],
+        )?;
+        Ok(())
+    }
+
+    /// All saved registers as `(name, kind, content)`.
+    pub fn load_registers(&self) -> Result<Vec<(char, String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT name, kind, content FROM registers")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(name, kind, content)| Some((name.chars().next()?, kind, content)))
+            .collect())
     }
 
-    pub fn delete_history_entry(&self, id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM history WHERE id = ?", [id])?;
+    /// Appends `command` to the ex command history, dropping an earlier copy
+    /// and all but the `limit` most recent entries.
+    pub fn add_command_history(&self, command: &str, limit: usize) -> Result<()> {
+        self.conn.execute("DELETE FROM command_history WHERE command = ?", params![command])?;
+        self.conn.execute("INSERT INTO command_history (command) VALUES (?)", params![command])?;
+        self.conn.execute(
+            "DELETE FROM command_history WHERE id NOT IN (SELECT id FROM command_history ORDER BY id DESC LIMIT ?)",
+            params![limit as i64],
+        )?;
         Ok(())
     }
 
-    pub fn push_redo(&self, task: &Task) -> Result<()> {
-        let snapshot = serde_json::to_string(task)?;
+    /// The ex command history, oldest first.
+    pub fn load_command_history(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT command FROM command_history ORDER BY id")?;
+        let rows = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(rows)
+    }
+
+    /// Starts a timer on `task_id`. Only one timer runs at a time, so a
+    /// running one is stopped first.
+    pub fn start_time_entry(&self, task_id: Uuid, at: DateTime<Utc>) -> Result<i64> {
+        self.stop_time_entries(at)?;
+        self.conn.execute(
+            "INSERT INTO time_entries (task_id, start) VALUES (?, ?)",
+            params![task_id.to_string(), at.to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Stops the running timer. Returns how many entries were closed.
+    pub fn stop_time_entries(&self, at: DateTime<Utc>) -> Result<usize> {
+        Ok(self.conn.execute("UPDATE time_entries SET end = ? WHERE end IS NULL", params![at.to_rfc3339()])?)
+    }
+
+    /// Inserts a finished entry, or updates it when `entry.id` exists.
+    pub fn save_time_entry(&self, entry: &TimeEntry) -> Result<i64> {
+        let end = entry.end.map(|e| e.to_rfc3339());
+        if entry.id > 0 {
+            self.conn.execute(
+                "UPDATE time_entries SET task_id = ?, start = ?, end = ? WHERE id = ?",
+                params![entry.task_id.to_string(), entry.start.to_rfc3339(), end, entry.id],
+            )?;
+            return Ok(entry.id);
+        }
         self.conn.execute(
-            "INSERT INTO redo_history (task_id, snapshot, timestamp) VALUES (?, ?, ?)",
-            params![task.id.to_string(), snapshot, Utc::now().to_rfc3339()],
+            "INSERT INTO time_entries (task_id, start, end) VALUES (?, ?, ?)",
+            params![entry.task_id.to_string(), entry.start.to_rfc3339(), end],
         )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn delete_time_entry(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM time_entries WHERE id = ?", params![id])?;
         Ok(())
     }
 
-    pub fn get_latest_redo(&self) -> Result<Option<(i64, Task)>> {
+    /// Time entries oldest first, of one task or of all tasks.
+    pub fn time_entries(&self, task_id: Option<Uuid>) -> Result<Vec<TimeEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, snapshot FROM redo_history ORDER BY id DESC LIMIT 1"
+            "SELECT id, task_id, start, end FROM time_entries WHERE ?1 IS NULL OR task_id = ?1 ORDER BY start, id",
         )?;
-        let mut rows = stmt.query([])?;
-        if let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let snapshot: String = row.get(1)?;
-            let task: Task = serde_json::from_str(&snapshot)?;
-            Ok(Some((id, task)))
-        } else {
-            Ok(None)
+        let rows = stmt
+            .query_map(params![task_id.map(|id| id.to_string())], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, Option<String>>(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let parse = |s: &str| DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&Utc));
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, task_id, start, end)| {
+                Some(TimeEntry {
+                    id,
+                    task_id: Uuid::parse_str(&task_id).ok()?,
+                    start: parse(&start)?,
+                    end: end.as_deref().and_then(parse),
+                })
+            })
+            .collect())
+    }
+
+    /// The entry whose timer is running, if any.
+    pub fn running_time_entry(&self) -> Result<Option<TimeEntry>> {
+        Ok(self.time_entries(None)?.into_iter().find(|e| e.end.is_none()))
+    }
+
+    /// Databases from before status events start with the tasks already done.
+    fn seed_status_events(&self) -> Result<()> {
+        let events: i64 = self.conn.query_row("SELECT COUNT(*) FROM status_events", [], |row| row.get(0))?;
+        if events == 0 {
+            self.conn.execute(
+                "INSERT INTO status_events (task_id, to_status, at) SELECT id, 'Done', updated_at FROM tasks WHERE status = 'Done'",
+                [],
+            )?;
         }
+        Ok(())
     }
 
-    pub fn delete_redo_entry(&self, id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM redo_history WHERE id = ?", [id])?;
+    /// Records a status change of a task about to be saved, or its initial
+    /// status when it is new.
+    fn log_status_change(&self, task: &Task) -> Result<()> {
+        let previous: Option<String> = self
+            .conn
+            .query_row("SELECT status FROM tasks WHERE id = ?", [task.id.to_string()], |row| row.get(0))
+            .optional()?;
+        if previous.as_deref() == Some(task.status.to_string().as_str()) {
+            return Ok(());
+        }
+        self.conn.execute(
+            "INSERT INTO status_events (task_id, from_status, to_status, at) VALUES (?, ?, ?, ?)",
+            params![task.id.to_string(), previous, task.status.to_string(), Utc::now().to_rfc3339()],
+        )?;
         Ok(())
     }
 
-    pub fn clear_redo(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM redo_history", [])?;
+    /// Status changes in the order they happened, of one task or of all
+    /// tasks, including deleted ones.
+    pub fn status_events(&self, task_id: Option<Uuid>) -> Result<Vec<StatusEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, task_id, from_status, to_status, at FROM status_events WHERE ?1 IS NULL OR task_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![task_id.map(|id| id.to_string())], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, task_id, from, to, at)| {
+                Some(StatusEvent {
+                    id,
+                    task_id: Uuid::parse_str(&task_id).ok()?,
+                    from: from.map(TaskStatus::from),
+                    to: TaskStatus::from(to),
+                    at: DateTime::parse_from_rfc3339(&at).ok()?.with_timezone(&Utc),
+                })
+            })
+            .collect())
+    }
+
+    /// Records an imported status change.
+    pub fn add_status_event(&self, event: &StatusEvent) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO status_events (task_id, from_status, to_status, at) VALUES (?, ?, ?, ?)",
+            params![event.task_id.to_string(), event.from.map(|s| s.to_string()), event.to.to_string(), event.at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Records a finished work period on `task_id`.
+    pub fn add_pomodoro(&self, task_id: Uuid, at: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO pomodoros (task_id, completed_at) VALUES (?, ?)",
+            params![task_id.to_string(), at.to_rfc3339()],
+        )?;
         Ok(())
     }
+
+    /// How many pomodoros each task has finished.
+    pub fn pomodoro_counts(&self) -> Result<HashMap<Uuid, usize>> {
+        let mut stmt = self.conn.prepare("SELECT task_id, COUNT(*) FROM pomodoros GROUP BY task_id")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, count)| Some((Uuid::parse_str(&id).ok()?, count as usize)))
+            .collect())
+    }
 }
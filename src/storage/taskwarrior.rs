@@ -0,0 +1,190 @@
+use crate::domain::modify::is_valid_recurrence;
+use crate::domain::timeline::{completed_at, started_at, StatusEvent};
+use crate::domain::{Task, TaskStatus};
+use crate::error::{Result, TaskVimError};
+use crate::storage::json::Export;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How Taskwarrior writes dates: `20240501T120000Z`.
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// One task of `task export`. Fields taskvim has no use for (`id`,
+/// `urgency`, user defined attributes) are ignored on import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskwarriorTask {
+    pub uuid: Uuid,
+    pub description: String,
+    pub status: String,
+    pub entry: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// A list of UUIDs, or a comma separated string before Taskwarrior 2.6.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recur: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub entry: String,
+    pub description: String,
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, DATE_FORMAT)
+        .map(|d| d.and_utc())
+        .ok()
+        .or_else(|| DateTime::parse_from_rfc3339(value).ok().map(|d| d.with_timezone(&Utc)))
+}
+
+fn format_date(at: DateTime<Utc>) -> String {
+    at.format(DATE_FORMAT).to_string()
+}
+
+/// H, M and L are priorities 5, 4 and 2; no priority is the default 3.
+fn priority_from(letter: Option<&str>) -> i32 {
+    match letter {
+        Some("H") => 5,
+        Some("M") => 4,
+        Some("L") => 2,
+        _ => 3,
+    }
+}
+
+fn priority_letter(priority: i32) -> Option<String> {
+    match priority {
+        5.. => Some("H".to_string()),
+        4 => Some("M".to_string()),
+        3 => None,
+        _ => Some("L".to_string()),
+    }
+}
+
+fn depends(value: &Option<serde_json::Value>) -> Vec<Uuid> {
+    match value {
+        Some(serde_json::Value::Array(ids)) => ids.iter().filter_map(|id| Uuid::parse_str(id.as_str()?).ok()).collect(),
+        Some(serde_json::Value::String(ids)) => ids.split(',').filter_map(|id| Uuid::parse_str(id.trim()).ok()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl TaskwarriorTask {
+    /// The task and the status changes its dates imply, or `None` for
+    /// deleted tasks and recurrence templates (their pending instances carry
+    /// the recurrence).
+    pub fn to_task(&self) -> Result<Option<(Task, Vec<StatusEvent>)>> {
+        let date = |field: &str, value: &str| {
+            parse_date(value).ok_or_else(|| TaskVimError::Validation(format!("Invalid {} date: {}", field, value)))
+        };
+        let optional_date = |field: &str, value: &Option<String>| value.as_deref().map(|v| date(field, v)).transpose();
+        let status = match self.status.as_str() {
+            "pending" | "waiting" if self.start.is_some() => TaskStatus::Doing,
+            "pending" | "waiting" => TaskStatus::Todo,
+            "completed" => TaskStatus::Done,
+            "deleted" | "recurring" => return Ok(None),
+            other => return Err(TaskVimError::Validation(format!("Unknown Taskwarrior status: {}", other))),
+        };
+        let entry = date("entry", &self.entry)?;
+        let start = optional_date("start", &self.start)?;
+        let end = optional_date("end", &self.end)?;
+        let notes: Vec<&str> = self.annotations.iter().map(|a| a.description.as_str()).collect();
+
+        let task = Task {
+            id: self.uuid,
+            title: self.description.clone(),
+            description: (!notes.is_empty()).then(|| notes.join("\n")),
+            status,
+            priority: priority_from(self.priority.as_deref()),
+            due_date: optional_date("due", &self.due)?,
+            created_at: entry,
+            updated_at: optional_date("modified", &self.modified)?.unwrap_or(entry),
+            tags: self.tags.clone(),
+            project: self.project.clone(),
+            recurrence_rule: self.recur.clone().filter(|r| is_valid_recurrence(r)),
+            dependencies: depends(&self.depends),
+            position: 0,
+            parent_id: None,
+        };
+
+        let event = |from: Option<TaskStatus>, to: TaskStatus, at: DateTime<Utc>| StatusEvent { id: 0, task_id: task.id, from, to, at };
+        let mut events = vec![event(None, TaskStatus::Todo, entry)];
+        if let Some(start) = start {
+            events.push(event(Some(TaskStatus::Todo), TaskStatus::Doing, start));
+        }
+        if status == TaskStatus::Done {
+            let from = if start.is_some() { TaskStatus::Doing } else { TaskStatus::Todo };
+            events.push(event(Some(from), TaskStatus::Done, end.unwrap_or(task.updated_at)));
+        }
+        Ok(Some((task, events)))
+    }
+
+    /// A task as Taskwarrior sees it: Archived tasks are completed ones, and
+    /// subtasks and positions have no equivalent.
+    pub fn from_task(task: &Task, events: &[StatusEvent]) -> Self {
+        let (status, start, end) = match task.status {
+            TaskStatus::Todo => ("pending", None, None),
+            TaskStatus::Doing => ("pending", Some(started_at(events, task.id).unwrap_or(task.updated_at)), None),
+            TaskStatus::Done | TaskStatus::Archived => ("completed", None, Some(completed_at(events, task.id).unwrap_or(task.updated_at))),
+        };
+        let mut tags = task.tags.clone();
+        tags.sort();
+        let notes = task.description.as_deref().unwrap_or("");
+        Self {
+            uuid: task.id,
+            description: task.title.clone(),
+            status: status.to_string(),
+            entry: format_date(task.created_at),
+            modified: Some(format_date(task.updated_at)),
+            start: start.map(format_date),
+            end: end.map(format_date),
+            due: task.due_date.map(format_date),
+            project: task.project.clone(),
+            tags,
+            priority: priority_letter(task.priority),
+            depends: (!task.dependencies.is_empty())
+                .then(|| serde_json::Value::from(task.dependencies.iter().map(|d| d.to_string()).collect::<Vec<_>>())),
+            recur: task.recurrence_rule.clone(),
+            annotations: notes
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| Annotation { entry: format_date(task.updated_at), description: line.to_string() })
+                .collect(),
+        }
+    }
+}
+
+/// Reads the output of `task export` as an import of taskvim tasks.
+pub fn import(text: &str) -> Result<Export> {
+    let tasks: Vec<TaskwarriorTask> = serde_json::from_str(text)?;
+    let mut export = Export::new(Vec::new(), Vec::new(), Vec::new());
+    for tw in &tasks {
+        if let Some((task, events)) = tw.to_task()? {
+            export.tasks.push(task);
+            export.status_events.extend(events);
+        }
+    }
+    Ok(export)
+}
+
+/// Writes tasks in the format of `task export`, for `task import`.
+pub fn export(tasks: &[Task], events: &[StatusEvent]) -> Result<String> {
+    let tasks: Vec<TaskwarriorTask> = tasks.iter().map(|t| TaskwarriorTask::from_task(t, events)).collect();
+    Ok(serde_json::to_string_pretty(&tasks)?)
+}
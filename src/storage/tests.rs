@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use crate::domain::TaskStatus;
+    use crate::storage::taskwarrior::{self, TaskwarriorTask};
+    use crate::storage::{markdown, todotxt};
+    use chrono::{TimeZone, Utc};
+
+    /// A `task export` with a pending, a started and a completed task, a deleted
+    /// one and a recurrence template with its pending instance.
+    const TASKWARRIOR_SAMPLE: &str = r#"[
+  {"id":1,"description":"Write report","entry":"20240301T090000Z","modified":"20240302T100000Z","status":"pending","uuid":"5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01","project":"Work","tags":["office","urgent"],"priority":"H","due":"20240310T170000Z","annotations":[{"entry":"20240302T100000Z","description":"Ask Sam for numbers"}],"urgency":12.3},
+  {"id":2,"description":"Review PR","entry":"20240301T091500Z","modified":"20240303T080000Z","start":"20240303T080000Z","status":"pending","uuid":"6d2c8b63-4f4c-4d55-8e52-4c1f5c0a7b02","priority":"L","depends":["5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01"]},
+  {"id":0,"description":"Book flights","end":"20240305T120000Z","entry":"20240301T100000Z","modified":"20240305T120000Z","status":"completed","uuid":"7e3d9c74-5a5d-4e66-9f63-5d2a6d1b8c03","project":"Travel","priority":"M"},
+  {"id":0,"description":"Old idea","end":"20240304T120000Z","entry":"20240301T100000Z","modified":"20240304T120000Z","status":"deleted","uuid":"8f4ead85-6b6e-4f77-a074-6e3b7e2c9d04"},
+  {"id":0,"description":"Water plants","entry":"20240301T080000Z","modified":"20240301T080000Z","status":"recurring","recur":"weekly","due":"20240302T080000Z","uuid":"9a5fbe96-7c7f-4088-b185-7f4c8f3dae05"},
+  {"id":3,"description":"Water plants","entry":"20240301T080000Z","modified":"20240301T080000Z","status":"pending","recur":"weekly","due":"20240302T080000Z","parent":"9a5fbe96-7c7f-4088-b185-7f4c8f3dae05","uuid":"ab60cfa7-8d80-4199-8296-804d904ebf06"}
+]"#;
+
+    #[test]
+    fn test_taskwarrior_import_mapping() {
+        let export = taskwarrior::import(TASKWARRIOR_SAMPLE).unwrap();
+        let titles: Vec<&str> = export.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Write report", "Review PR", "Book flights", "Water plants"]);
+
+        let report = &export.tasks[0];
+        assert_eq!((report.status, report.priority), (TaskStatus::Todo, 5));
+        assert_eq!(report.project.as_deref(), Some("Work"));
+        assert_eq!(report.tags, vec!["office", "urgent"]);
+        assert_eq!(report.description.as_deref(), Some("Ask Sam for numbers"));
+        assert_eq!(report.due_date.unwrap().to_rfc3339(), "2024-03-10T17:00:00+00:00");
+
+        let review = &export.tasks[1];
+        assert_eq!((review.status, review.priority), (TaskStatus::Doing, 2));
+        assert_eq!(review.dependencies, vec![report.id]);
+        assert_eq!((export.tasks[2].status, export.tasks[2].priority), (TaskStatus::Done, 4));
+        assert_eq!(export.tasks[3].recurrence_rule.as_deref(), Some("weekly"));
+
+        // Completion and start dates become status events
+        let done = export.status_events.iter().find(|e| e.task_id == export.tasks[2].id && e.to == TaskStatus::Done).unwrap();
+        assert_eq!(done.at.to_rfc3339(), "2024-03-05T12:00:00+00:00");
+        assert!(export.status_events.iter().any(|e| e.task_id == review.id && e.to == TaskStatus::Doing));
+
+        // Dependencies as written before Taskwarrior 2.6
+        let old = TASKWARRIOR_SAMPLE.replace(
+            r#"["5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01"]"#,
+            r#""5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01""#,
+        );
+        assert_eq!(taskwarrior::import(&old).unwrap().tasks[1].dependencies, vec![report.id]);
+        assert!(taskwarrior::import(r#"[{"uuid":"5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01","description":"x","status":"pending","entry":"yesterday"}]"#).is_err());
+    }
+
+    #[test]
+    fn test_taskwarrior_round_trip() {
+        let original: Vec<TaskwarriorTask> = serde_json::from_str(TASKWARRIOR_SAMPLE).unwrap();
+        let export = taskwarrior::import(TASKWARRIOR_SAMPLE).unwrap();
+        let written: Vec<TaskwarriorTask> = serde_json::from_str(&taskwarrior::export(&export.tasks, &export.status_events).unwrap()).unwrap();
+
+        // Everything but deleted tasks and recurrence templates comes back as it was
+        let expected: Vec<&TaskwarriorTask> = original.iter().filter(|t| t.status != "deleted" && t.status != "recurring").collect();
+        assert_eq!(written.len(), expected.len());
+        for (written, expected) in written.iter().zip(expected) {
+            assert_eq!(written, expected);
+        }
+
+        // And reading it again gives the same tasks
+        let again = taskwarrior::import(&taskwarrior::export(&export.tasks, &export.status_events).unwrap()).unwrap();
+        let json = |tasks: &[crate::domain::Task]| serde_json::to_value(tasks).unwrap();
+        assert_eq!(json(&again.tasks), json(&export.tasks));
+    }
+
+    #[test]
+    fn test_todotxt_lines() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        let (task, completed) = todotxt::parse_line("(A) 2024-03-01 Call the bank +Finance @phone key:value due:2024-03-10 +Later", now).unwrap();
+        assert_eq!((task.title.as_str(), task.priority), ("Call the bank key:value", 5));
+        assert_eq!(task.project.as_deref(), Some("Finance"));
+        assert_eq!(task.tags, vec!["phone", "Later"]);
+        assert_eq!(task.created_at.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert_eq!(task.due_date.unwrap().to_rfc3339(), "2024-03-10T00:00:00+00:00");
+        assert!(task.id.is_nil() && completed.is_none());
+
+        let (task, completed) = todotxt::parse_line("x 2024-03-05 Book flights rec:+1y", now).unwrap();
+        assert_eq!((task.status, task.priority, task.created_at), (TaskStatus::Done, 3, now));
+        assert_eq!(task.recurrence_rule.as_deref(), Some("1y"));
+        assert_eq!(completed.unwrap().to_rfc3339(), "2024-03-05T00:00:00+00:00");
+        assert!(todotxt::parse_line("   ", now).is_none());
+    }
+
+    #[test]
+    fn test_todotxt_round_trip() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        let sample = "\
+(A) 2024-03-01 Call the bank +Finance @phone due:2024-03-10 uuid:5c1b7a52-3e3b-4c44-9d41-3b0e4b9f6a01
+x 2024-03-05 (B) 2024-03-01 Book flights +Travel @online @work rec:1y uuid:7e3d9c74-5a5d-4e66-9f63-5d2a6d1b8c03
+(C) 2024-03-02 Water plants uuid:ab60cfa7-8d80-4199-8296-804d904ebf06
+(D) 2024-03-02 Someday maybe uuid:8f4ead85-6b6e-4f77-a074-6e3b7e2c9d04
+2024-03-04 Plain task uuid:9a5fbe96-7c7f-4088-b185-7f4c8f3dae05
+";
+        for line in sample.lines() {
+            let (task, completed) = todotxt::parse_line(line, now).unwrap();
+            assert_eq!(todotxt::format_line(&task, completed), line);
+        }
+    }
+
+    #[test]
+    fn test_markdown_checklists() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        let notes = "\
+# Weekly sync
+
+Attendees: Sam, Alex
+
+- [ ] Send minutes
+- [x] Book room
+  - second floor
+- [ ] Prepare demo
+  - [ ] Slides
+    Use the new template
+  - [X] Recording
+
+Some closing remark.
+
+- plain bullet, not a task
+
+## Travel ##
+
+1. [ ] Book flights
+";
+        let tasks = markdown::parse(notes, now);
+        let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["Send minutes", "Book room", "Prepare demo", "Slides", "Recording", "Book flights"]);
+        assert_eq!(tasks[0].project.as_deref(), Some("Weekly sync"));
+        assert_eq!(tasks[5].project.as_deref(), Some("Travel"));
+        assert_eq!(tasks[1].status, TaskStatus::Done);
+        assert_eq!(tasks[1].description.as_deref(), Some("second floor"));
+        assert_eq!(tasks[3].description.as_deref(), Some("Use the new template"));
+        assert_eq!((tasks[3].parent_id, tasks[4].parent_id), (Some(tasks[2].id), Some(tasks[2].id)));
+        assert_eq!(tasks[4].status, TaskStatus::Done);
+
+        // Written back as checklists per project, and read again the same way
+        let written = markdown::export(&tasks);
+        assert_eq!(
+            written,
+            "\
+## Weekly sync
+
+- [ ] Send minutes
+- [x] Book room
+  second floor
+- [ ] Prepare demo
+  - [ ] Slides
+    Use the new template
+  - [x] Recording
+
+## Travel
+
+- [ ] Book flights
+"
+        );
+        let again = markdown::parse(&written, now);
+        let fields = |tasks: &[crate::domain::Task]| -> Vec<_> {
+            tasks.iter().map(|t| (t.title.clone(), t.status, t.project.clone(), t.description.clone(), t.parent_id.is_some())).collect()
+        };
+        assert_eq!(fields(&again), fields(&tasks));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::domain::modify::is_valid_recurrence;
+use crate::domain::timeline::{completed_at, StatusEvent};
+use crate::domain::{Task, TaskStatus};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use uuid::Uuid;
+
+/// The key taskvim adds to each line so it finds the task again.
+pub const ID_KEY: &str = "uuid";
+
+fn parse_day(word: &str) -> Option<DateTime<Utc>> {
+    let day = NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()?;
+    Some(Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0)?))
+}
+
+fn format_day(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d").to_string()
+}
+
+/// A, B and C are priorities 5, 4 and 2, any later letter is 1; no letter
+/// is the default 3.
+fn priority_from(letter: char) -> i32 {
+    match letter {
+        'A' => 5,
+        'B' => 4,
+        'C' => 2,
+        _ => 1,
+    }
+}
+
+fn priority_letter(priority: i32) -> Option<char> {
+    match priority {
+        5.. => Some('A'),
+        4 => Some('B'),
+        3 => None,
+        2 => Some('C'),
+        _ => Some('D'),
+    }
+}
+
+/// One line of a todo.txt file: the task and, for completed lines, the
+/// completion date. A line without a `uuid:` key gets the nil UUID.
+pub fn parse_line(line: &str, now: DateTime<Utc>) -> Option<(Task, Option<DateTime<Utc>>)> {
+    let mut words = line.split_whitespace().peekable();
+    words.peek()?;
+    let mut task = Task::new(String::new());
+    task.id = Uuid::nil();
+    task.created_at = now;
+    task.updated_at = now;
+
+    let mut completed = None;
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.status = TaskStatus::Done;
+        completed = words.peek().and_then(|w| parse_day(w));
+        if completed.is_some() {
+            words.next();
+        }
+    }
+    if let Some(letter) = words.peek().and_then(|w| w.strip_prefix('(')?.strip_suffix(')')) {
+        let mut chars = letter.chars();
+        if let (Some(letter @ 'A'..='Z'), None) = (chars.next(), chars.next()) {
+            task.priority = priority_from(letter);
+            words.next();
+        }
+    }
+    if let Some(created) = words.peek().and_then(|w| parse_day(w)) {
+        task.created_at = created;
+        words.next();
+    }
+
+    let mut title = Vec::new();
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            if task.project.is_none() {
+                task.project = Some(project.to_string());
+            } else {
+                task.tags.push(project.to_string());
+            }
+            continue;
+        }
+        if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            task.tags.push(context.to_string());
+            continue;
+        }
+        // Keys taskvim knows become fields, anything else stays in the title
+        let known = match word.split_once(':') {
+            Some(("due", value)) => parse_day(value).map(|due| task.due_date = Some(due)),
+            Some(("rec", value)) => {
+                let rule = value.trim_start_matches('+');
+                is_valid_recurrence(rule).then(|| task.recurrence_rule = Some(rule.to_string()))
+            }
+            Some((ID_KEY, value)) => Uuid::parse_str(value).ok().map(|id| task.id = id),
+            _ => None,
+        };
+        if known.is_none() {
+            title.push(word);
+        }
+    }
+    task.title = title.join(" ");
+    Some((task, completed))
+}
+
+/// A task as a todo.txt line. Done and Archived tasks are completed lines;
+/// the project comes first among the `+` words and tags are `@` contexts.
+pub fn format_line(task: &Task, completed: Option<DateTime<Utc>>) -> String {
+    let mut words = Vec::new();
+    if matches!(task.status, TaskStatus::Done | TaskStatus::Archived) {
+        words.push("x".to_string());
+        words.push(format_day(completed.unwrap_or(task.updated_at)));
+    }
+    if let Some(letter) = priority_letter(task.priority) {
+        words.push(format!("({})", letter));
+    }
+    words.push(format_day(task.created_at));
+    words.push(task.title.clone());
+    if let Some(project) = &task.project {
+        words.push(format!("+{}", project));
+    }
+    let mut tags = task.tags.clone();
+    tags.sort();
+    words.extend(tags.iter().map(|t| format!("@{}", t)));
+    if let Some(due) = task.due_date {
+        words.push(format!("due:{}", format_day(due)));
+    }
+    if let Some(rule) = &task.recurrence_rule {
+        words.push(format!("rec:{}", rule));
+    }
+    words.push(format!("{}:{}", ID_KEY, task.id));
+    words.join(" ")
+}
+
+/// Reads the lines of a todo.txt file, skipping blank ones.
+pub fn parse(text: &str, now: DateTime<Utc>) -> Vec<(Task, Option<DateTime<Utc>>)> {
+    text.lines().filter_map(|line| parse_line(line, now)).collect()
+}
+
+/// Writes tasks as a todo.txt file, one line each.
+pub fn export(tasks: &[Task], events: &[StatusEvent]) -> String {
+    tasks.iter().map(|t| format_line(t, completed_at(events, t.id)) + "\n").collect()
+}
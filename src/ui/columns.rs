@@ -0,0 +1,386 @@
+use crate::config::lua::{ColumnSpec, Config};
+use crate::core::tree::TreeNode;
+use crate::domain::{Task, TaskStatus};
+use crate::ui::theme::Theme;
+use chrono::{DateTime, Utc};
+use ratatui::layout::Constraint;
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use regex::Regex;
+use std::str::FromStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Wrapped cells never grow a row beyond this many lines.
+pub const MAX_WRAP_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Id,
+    Status,
+    Priority,
+    Title,
+    Description,
+    Project,
+    Tags,
+    Due,
+    Created,
+    Updated,
+    Age,
+    Urgency,
+    Recurrence,
+    Position,
+}
+
+impl FromStr for ColumnKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(ColumnKind::Id),
+            "status" => Ok(ColumnKind::Status),
+            "priority" => Ok(ColumnKind::Priority),
+            "title" => Ok(ColumnKind::Title),
+            "description" => Ok(ColumnKind::Description),
+            "project" => Ok(ColumnKind::Project),
+            "tags" => Ok(ColumnKind::Tags),
+            "due" => Ok(ColumnKind::Due),
+            "created" => Ok(ColumnKind::Created),
+            "updated" => Ok(ColumnKind::Updated),
+            "age" => Ok(ColumnKind::Age),
+            "urgency" => Ok(ColumnKind::Urgency),
+            "recur" | "recurrence" => Ok(ColumnKind::Recurrence),
+            "position" => Ok(ColumnKind::Position),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ColumnKind {
+    pub fn header(&self) -> &'static str {
+        match self {
+            ColumnKind::Id => "ID",
+            ColumnKind::Status => "Status",
+            ColumnKind::Priority => "Priority",
+            ColumnKind::Title => "Title",
+            ColumnKind::Description => "Description",
+            ColumnKind::Project => "Project",
+            ColumnKind::Tags => "Tags",
+            ColumnKind::Due => "Due",
+            ColumnKind::Created => "Created",
+            ColumnKind::Updated => "Updated",
+            ColumnKind::Age => "Age",
+            ColumnKind::Urgency => "Urgency",
+            ColumnKind::Recurrence => "Recur",
+            ColumnKind::Position => "Pos",
+        }
+    }
+
+    fn default_width(&self) -> Constraint {
+        match self {
+            ColumnKind::Title | ColumnKind::Description => Constraint::Fill(1),
+            ColumnKind::Project | ColumnKind::Tags => Constraint::Percentage(15),
+            ColumnKind::Due | ColumnKind::Created | ColumnKind::Updated => Constraint::Length(10),
+            _ => Constraint::Length(8),
+        }
+    }
+
+    /// The styled pieces making up a cell. Most columns are a single span;
+    /// tags get one span per tag so each can use its own highlight group.
+    pub fn segments(&self, task: &Task, node: Option<&TreeNode>, theme: &Theme, now: DateTime<Utc>) -> Vec<(String, Style)> {
+        let normal = theme.get("Normal");
+        let date = |d: Option<DateTime<Utc>>| d.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+
+        match self {
+            ColumnKind::Id => vec![(task.id.to_string()[..8].to_string(), theme.get("Id"))],
+            ColumnKind::Status => vec![(task.status.to_string(), theme.status(task.status))],
+            ColumnKind::Priority => vec![(task.priority.to_string(), theme.priority(task.priority))],
+            ColumnKind::Title => {
+                let node = node.cloned().unwrap_or_default();
+                let marker = match (node.has_children, node.folded) {
+                    (true, true) => "▸ ",
+                    (true, false) => "▾ ",
+                    _ => "",
+                };
+                let mut segments = vec![(format!("{}{}{}", "  ".repeat(node.depth), marker, task.title), normal)];
+                if node.has_children {
+                    segments.push((format!(" ({}/{})", node.done, node.total), theme.get("Progress")));
+                }
+                segments
+            }
+            ColumnKind::Description => vec![(task.description.clone().unwrap_or_default(), normal)],
+            ColumnKind::Project => vec![(task.project.clone().unwrap_or_else(|| "-".to_string()), theme.get("Project"))],
+            ColumnKind::Tags => {
+                let mut segments = Vec::new();
+                for (i, tag) in task.tags.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { " " };
+                    segments.push((format!("{}+{}", sep, tag), theme.tag(tag)));
+                }
+                segments
+            }
+            ColumnKind::Due => {
+                let style = match task.due_date {
+                    Some(due) if due < now && !matches!(task.status, TaskStatus::Done | TaskStatus::Archived) => theme.get("Overdue"),
+                    _ => normal,
+                };
+                vec![(date(task.due_date), style)]
+            }
+            ColumnKind::Created => vec![(date(Some(task.created_at)), normal)],
+            ColumnKind::Updated => vec![(date(Some(task.updated_at)), normal)],
+            ColumnKind::Age => vec![(format_age(now - task.created_at), normal)],
+            ColumnKind::Urgency => vec![(format!("{:.1}", task.urgency(now)), normal)],
+            ColumnKind::Recurrence => vec![(task.recurrence_rule.clone().unwrap_or_default(), normal)],
+            ColumnKind::Position => vec![(task.position.to_string(), normal)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub kind: ColumnKind,
+    pub header: String,
+    pub width: Constraint,
+    pub wrap: bool,
+}
+
+impl Column {
+    pub fn from_spec(spec: &ColumnSpec) -> Option<Self> {
+        let kind = ColumnKind::from_str(&spec.field).ok()?;
+        let width = match &spec.width {
+            Some(w) => parse_width(w)?,
+            None => kind.default_width(),
+        };
+        Some(Self {
+            kind,
+            header: spec.title.clone().unwrap_or_else(|| kind.header().to_string()),
+            width,
+            wrap: spec.wrap,
+        })
+    }
+}
+
+/// Widths: `12` (cells), `30%`, `>=12` (at least 12 cells) or `*` (fill the rest).
+pub fn parse_width(s: &str) -> Option<Constraint> {
+    let s = s.trim();
+    if s == "*" {
+        Some(Constraint::Fill(1))
+    } else if let Some(p) = s.strip_suffix('%') {
+        p.parse().ok().filter(|p| *p <= 100).map(Constraint::Percentage)
+    } else if let Some(n) = s.strip_prefix(">=") {
+        n.parse().ok().map(Constraint::Min)
+    } else {
+        s.parse().ok().map(Constraint::Length)
+    }
+}
+
+/// The columns for `view`, falling back to the default column set.
+pub fn columns_for(config: &Config, view: Option<&str>) -> Vec<Column> {
+    let specs = view
+        .and_then(|v| config.view_columns.get(v))
+        .unwrap_or(&config.columns);
+    specs.iter().filter_map(Column::from_spec).collect()
+}
+
+fn format_age(age: chrono::Duration) -> String {
+    if age.num_days() >= 7 {
+        format!("{}w", age.num_weeks())
+    } else if age.num_days() >= 1 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() >= 1 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+/// Cuts `s` to at most `width` terminal cells, ending in `…` when shortened.
+pub fn truncate(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width - 1 {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// Word-wraps styled segments into lines of at most `width` cells. Words
+/// wider than a line (long URLs, CJK runs without spaces) are broken at
+/// character level. Separating spaces take the style of the preceding word.
+fn wrap_segments(segments: &[(String, Style)], width: usize) -> Vec<Vec<(String, Style)>> {
+    let mut lines: Vec<Vec<(char, Style)>> = Vec::new();
+    if width == 0 {
+        return Vec::new();
+    }
+
+    // Words are split across segment boundaries, so a word made of several
+    // styled pieces (e.g. a highlighted search match) stays whole
+    let mut words: Vec<Vec<(char, Style)>> = Vec::new();
+    let mut word = Vec::new();
+    for (text, style) in segments {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else {
+                word.push((c, *style));
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut line: Vec<(char, Style)> = Vec::new();
+    let mut used = 0;
+    for word in words {
+        let word_width: usize = word.iter().map(|(c, _)| c.width().unwrap_or(0)).sum();
+        let sep = if line.is_empty() { 0 } else { 1 };
+        if used + sep + word_width <= width {
+            if sep == 1 {
+                let prev = line.last().map(|(_, s)| *s).unwrap_or(word[0].1);
+                line.push((' ', prev));
+            }
+            line.extend(word);
+            used += sep + word_width;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            used = 0;
+        }
+        for (c, style) in word {
+            let w = c.width().unwrap_or(0);
+            if used + w > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                used = 0;
+            }
+            line.push((c, style));
+            used += w;
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines.into_iter().map(merge_runs).collect()
+}
+
+/// Joins consecutive characters of the same style back into segments.
+fn merge_runs(chars: Vec<(char, Style)>) -> Vec<(String, Style)> {
+    let mut runs: Vec<(String, Style)> = Vec::new();
+    for (c, style) in chars {
+        match runs.last_mut() {
+            Some((text, s)) if *s == style => text.push(c),
+            _ => runs.push((c.to_string(), style)),
+        }
+    }
+    runs
+}
+
+/// Splits `segments` so the text matching `regex` gets `style` patched on
+/// top of its own style.
+pub fn highlight_matches(segments: Vec<(String, Style)>, regex: &Regex, style: Style) -> Vec<(String, Style)> {
+    let mut out = Vec::new();
+    for (text, base) in segments {
+        let mut last = 0;
+        for m in regex.find_iter(&text).filter(|m| !m.is_empty()) {
+            if m.start() > last {
+                out.push((text[last..m.start()].to_string(), base));
+            }
+            out.push((m.as_str().to_string(), base.patch(style)));
+            last = m.end();
+        }
+        if last < text.len() {
+            out.push((text[last..].to_string(), base));
+        }
+    }
+    out
+}
+
+/// Lays `segments` out in `width` cells. Non-wrapping columns are truncated
+/// to one line; wrapping columns flow over up to `MAX_WRAP_LINES` lines with
+/// any leading indentation (subtask depth) repeated as a hanging indent.
+pub fn fit(segments: Vec<(String, Style)>, width: usize, wrap_text: bool) -> Vec<Line<'static>> {
+    if !wrap_text {
+        return vec![truncate_segments(segments, width)];
+    }
+
+    let indent: String = segments
+        .first()
+        .map(|(text, _)| text.chars().take_while(|c| *c == ' ').collect())
+        .unwrap_or_default();
+    let indent_width = indent.len().min(width);
+    let mut lines = wrap_segments(&segments, width - indent_width);
+
+    if lines.len() > MAX_WRAP_LINES {
+        let rest: Vec<(String, Style)> = lines
+            .split_off(MAX_WRAP_LINES - 1)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                let sep = (i > 0).then(|| (" ".to_string(), Style::default()));
+                sep.into_iter().chain(line)
+            })
+            .collect();
+        let last = truncate_segments(rest, width - indent_width);
+        lines.push(last.spans.into_iter().map(|s| (s.content.into_owned(), s.style)).collect());
+    }
+
+    let indent_style = segments.first().map(|(_, s)| *s).unwrap_or_default();
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut spans = vec![Span::styled(indent[..indent_width].to_string(), indent_style)];
+            spans.extend(line.into_iter().map(|(text, style)| Span::styled(text, style)));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn truncate_segments(segments: Vec<(String, Style)>, width: usize) -> Line<'static> {
+    let total: usize = segments.iter().map(|(text, _)| text.width()).sum();
+    if total <= width {
+        return Line::from(segments.into_iter().map(|(text, style)| Span::styled(text, style)).collect::<Vec<_>>());
+    }
+    if width == 0 {
+        return Line::default();
+    }
+
+    // Keep whole segments while they fit, leaving a cell for the ellipsis
+    let mut spans = Vec::new();
+    let mut remaining = width - 1;
+    for (text, style) in segments {
+        let text_width = text.width();
+        if text_width <= remaining {
+            remaining -= text_width;
+            spans.push(Span::styled(text, style));
+            continue;
+        }
+        let mut piece = String::new();
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if w > remaining {
+                break;
+            }
+            remaining -= w;
+            piece.push(c);
+        }
+        piece.push('…');
+        spans.push(Span::styled(piece, style));
+        return Line::from(spans);
+    }
+    Line::from(spans)
+}
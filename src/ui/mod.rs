@@ -1,3 +1,8 @@
 pub mod tui;
+pub mod theme;
+pub mod columns;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
 
 pub use tui::Tui;
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::lua::LuaConfig;
+    use crate::domain::TaskStatus;
+    use crate::ui::theme::Theme;
+    use ratatui::style::{Color, Modifier};
+
+    #[test]
+    fn test_theme_highlight_overrides() {
+        let lua_config = LuaConfig::new().unwrap();
+        lua_config.run_code(r##"
+        set.theme("gruvbox")
+        highlight("StatusDone", { fg = "green", bold = true })
+        highlight("Tag.urgent", { fg = "#ff0000" })
+    "##).unwrap();
+        let theme = Theme::from_config(&lua_config.get_config());
+
+        let done = theme.status(TaskStatus::Done);
+        assert_eq!(done.fg, Some(Color::Green));
+        assert!(done.add_modifier.contains(Modifier::BOLD));
+
+        assert_eq!(theme.tag("urgent").fg, Some(Color::Rgb(0xff, 0, 0)));
+        assert_eq!(theme.tag("other"), theme.get("Tag"));
+        // Unknown groups fall back to Normal
+        assert_eq!(theme.get("NoSuchGroup"), theme.get("Normal"));
+    }
+
+    #[test]
+    fn test_truncate_and_wrap_wide_text() {
+        use crate::ui::columns::{fit, truncate};
+        use ratatui::style::Style;
+        use unicode_width::UnicodeWidthStr;
+
+        let wrap = |s: &str, width| -> Vec<String> {
+            fit(vec![(s.to_string(), Style::default())], width, true)
+                .iter()
+                .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+                .collect()
+        };
+
+        // Each CJK character is two cells wide
+        assert_eq!(truncate("日本語のタスク", 7), "日本語…");
+        assert_eq!(truncate("short", 10), "short");
+        assert!(truncate("日本語のタスク", 6).width() <= 6);
+
+        assert_eq!(wrap("fix the login page", 8), vec!["fix the", "login", "page"]);
+        // Indentation is repeated on continuation lines
+        assert_eq!(wrap("    fix the login", 11), vec!["    fix the", "    login"]);
+        // Overlong cells are capped and end in an ellipsis
+        assert_eq!(wrap("a b c d e f g h", 3), vec!["a b", "c d", "e …"]);
+        let lines = wrap("日本語のタスク", 6);
+        assert_eq!(lines, vec!["日本語", "のタス", "ク"]);
+        assert!(lines.iter().all(|l| l.width() <= 6));
+    }
+
+    #[test]
+    fn test_lua_columns_config() {
+        use crate::ui::columns::{columns_for, ColumnKind};
+        use ratatui::layout::Constraint;
+
+        let lua_config = LuaConfig::new().unwrap();
+        lua_config.run_code(r#"
+        columns({ "status", { "title", width = "60%", wrap = true }, { "due", width = 12 } })
+        columns("review", { "urgency", "age", "title" })
+    "#).unwrap();
+        assert!(lua_config.run_code(r#"columns({ "nonsense" })"#).is_err());
+
+        let config = lua_config.get_config();
+        let default = columns_for(&config, None);
+        assert_eq!(default.iter().map(|c| c.kind).collect::<Vec<_>>(), vec![ColumnKind::Status, ColumnKind::Title, ColumnKind::Due]);
+        assert_eq!(default[1].width, Constraint::Percentage(60));
+        assert!(default[1].wrap);
+        assert_eq!(default[2].width, Constraint::Length(12));
+
+        let review = columns_for(&config, Some("review"));
+        assert_eq!(review[0].kind, ColumnKind::Urgency);
+        // Unknown views use the default set
+        assert_eq!(columns_for(&config, Some("missing")).len(), 3);
+    }
+
+    #[test]
+    fn test_search_highlight_survives_wrapping() {
+        use crate::core::search::compile_pattern;
+        use crate::ui::columns::{fit, highlight_matches};
+        use ratatui::style::Style;
+
+        let hl = Style::default().bg(Color::Yellow);
+        let regex = compile_pattern("milk").unwrap();
+        let segments = highlight_matches(vec![("buy oatmilk today".to_string(), Style::default())], &regex, hl);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1], ("milk".to_string(), hl));
+
+        // The highlighted piece stays glued to the rest of its word
+        let lines = fit(segments, 8, true);
+        let text: Vec<String> = lines.iter().map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect()).collect();
+        assert_eq!(text, vec!["buy", "oatmilk", "today"]);
+        assert!(lines[1].spans.iter().any(|s| s.content == "milk" && s.style == hl));
+    }
+}
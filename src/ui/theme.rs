@@ -0,0 +1,207 @@
+use crate::config::lua::{Config, Highlight};
+use crate::domain::TaskStatus;
+use ratatui::style::{Color, Modifier, Style};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A resolved set of highlight groups. Widgets look styles up by group name
+/// (e.g. `StatusDone`, `Priority5`, `Tag.work`) instead of hard-coding colors.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    groups: HashMap<String, Style>,
+}
+
+struct Palette {
+    fg: Color,
+    bg: Color,
+    accent: Color,
+    header_bg: Color,
+    selection_bg: Color,
+    selection_fg: Color,
+    muted: Color,
+    red: Color,
+    orange: Color,
+    yellow: Color,
+    green: Color,
+    blue: Color,
+    magenta: Color,
+}
+
+impl Palette {
+    fn named(name: &str) -> Self {
+        match name {
+            "light" => Self {
+                fg: Color::Black,
+                bg: Color::Reset,
+                accent: Color::Blue,
+                header_bg: Color::Gray,
+                selection_bg: Color::Gray,
+                selection_fg: Color::Black,
+                muted: Color::DarkGray,
+                red: Color::Red,
+                orange: Color::Rgb(0xaf, 0x5f, 0x00),
+                yellow: Color::Rgb(0x87, 0x87, 0x00),
+                green: Color::Green,
+                blue: Color::Blue,
+                magenta: Color::Magenta,
+            },
+            "solarized" => Self {
+                fg: Color::Rgb(0x83, 0x94, 0x96),
+                bg: Color::Rgb(0x00, 0x2b, 0x36),
+                accent: Color::Rgb(0x2a, 0xa1, 0x98),
+                header_bg: Color::Rgb(0x07, 0x36, 0x42),
+                selection_bg: Color::Rgb(0x07, 0x36, 0x42),
+                selection_fg: Color::Rgb(0xb5, 0x89, 0x00),
+                muted: Color::Rgb(0x58, 0x6e, 0x75),
+                red: Color::Rgb(0xdc, 0x32, 0x2f),
+                orange: Color::Rgb(0xcb, 0x4b, 0x16),
+                yellow: Color::Rgb(0xb5, 0x89, 0x00),
+                green: Color::Rgb(0x85, 0x99, 0x00),
+                blue: Color::Rgb(0x26, 0x8b, 0xd2),
+                magenta: Color::Rgb(0xd3, 0x36, 0x82),
+            },
+            "gruvbox" => Self {
+                fg: Color::Rgb(0xeb, 0xdb, 0xb2),
+                bg: Color::Rgb(0x28, 0x28, 0x28),
+                accent: Color::Rgb(0x8e, 0xc0, 0x7c),
+                header_bg: Color::Rgb(0x3c, 0x38, 0x36),
+                selection_bg: Color::Rgb(0x50, 0x49, 0x45),
+                selection_fg: Color::Rgb(0xfa, 0xbd, 0x2f),
+                muted: Color::Rgb(0x92, 0x83, 0x74),
+                red: Color::Rgb(0xfb, 0x49, 0x34),
+                orange: Color::Rgb(0xfe, 0x80, 0x19),
+                yellow: Color::Rgb(0xfa, 0xbd, 0x2f),
+                green: Color::Rgb(0xb8, 0xbb, 0x26),
+                blue: Color::Rgb(0x83, 0xa5, 0x98),
+                magenta: Color::Rgb(0xd3, 0x86, 0x9b),
+            },
+            // "default" and "dark" keep the original TaskVim look
+            _ => Self {
+                fg: Color::Reset,
+                bg: Color::Reset,
+                accent: Color::Cyan,
+                header_bg: Color::Blue,
+                selection_bg: Color::DarkGray,
+                selection_fg: Color::Yellow,
+                muted: Color::DarkGray,
+                red: Color::Red,
+                orange: Color::LightRed,
+                yellow: Color::Yellow,
+                green: Color::Green,
+                blue: Color::Blue,
+                magenta: Color::Magenta,
+            },
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Self {
+        let p = Palette::named(name);
+        let mut groups = HashMap::new();
+        let base = Style::default().fg(p.fg).bg(p.bg);
+
+        groups.insert("Normal".to_string(), base);
+        groups.insert("Border".to_string(), base.fg(p.muted));
+        groups.insert("Title".to_string(), base.fg(p.accent).add_modifier(Modifier::BOLD));
+        groups.insert("Header".to_string(), Style::default().fg(p.accent).bg(p.header_bg).add_modifier(Modifier::BOLD));
+        groups.insert("Selection".to_string(), Style::default().bg(p.selection_bg).fg(p.selection_fg).add_modifier(Modifier::BOLD));
+        groups.insert("StatusLine".to_string(), base);
+        groups.insert("Search".to_string(), Style::default().bg(p.yellow).fg(p.bg).add_modifier(Modifier::BOLD));
+        groups.insert("PickerMatch".to_string(), base.fg(p.orange).add_modifier(Modifier::BOLD));
+        groups.insert("Muted".to_string(), base.fg(p.muted));
+        groups.insert("Marked".to_string(), base.fg(p.yellow).add_modifier(Modifier::BOLD));
+        groups.insert("InfoMsg".to_string(), base.fg(p.accent));
+        groups.insert("WarningMsg".to_string(), base.fg(p.orange).add_modifier(Modifier::BOLD));
+        groups.insert("ErrorMsg".to_string(), base.fg(p.red).add_modifier(Modifier::BOLD));
+        groups.insert("Recording".to_string(), base.fg(p.red).add_modifier(Modifier::BOLD));
+        groups.insert("Timer".to_string(), base.fg(p.green).add_modifier(Modifier::BOLD));
+        groups.insert("Id".to_string(), base.fg(p.muted));
+        groups.insert("Project".to_string(), base.fg(p.blue));
+        groups.insert("Tag".to_string(), base.fg(p.magenta));
+        groups.insert("Overdue".to_string(), base.fg(p.red));
+        groups.insert("Progress".to_string(), base.fg(p.muted));
+        groups.insert("Sidebar".to_string(), base);
+        groups.insert("SidebarHeader".to_string(), base.fg(p.muted).add_modifier(Modifier::BOLD));
+        groups.insert("SidebarActive".to_string(), base.fg(p.accent).add_modifier(Modifier::BOLD));
+
+        groups.insert("StatusTodo".to_string(), base);
+        groups.insert("StatusDoing".to_string(), base.fg(p.yellow).add_modifier(Modifier::BOLD));
+        groups.insert("StatusDone".to_string(), base.fg(p.green));
+        groups.insert("StatusArchived".to_string(), base.fg(p.muted));
+
+        groups.insert("Priority1".to_string(), base.fg(p.muted));
+        groups.insert("Priority2".to_string(), base);
+        groups.insert("Priority3".to_string(), base);
+        groups.insert("Priority4".to_string(), base.fg(p.orange));
+        groups.insert("Priority5".to_string(), base.fg(p.red).add_modifier(Modifier::BOLD));
+
+        Self { groups }
+    }
+
+    /// Builds the theme selected by `set.theme()` and layers the Lua
+    /// `highlight()` overrides on top of it.
+    pub fn from_config(config: &Config) -> Self {
+        let mut theme = Self::builtin(&config.theme);
+        for (group, hl) in &config.highlights {
+            theme.set(group, hl);
+        }
+        theme
+    }
+
+    pub fn set(&mut self, group: &str, hl: &Highlight) {
+        self.groups.insert(group.to_string(), highlight_to_style(hl));
+    }
+
+    /// Returns the style for `group`, falling back to `Normal` for unknown groups.
+    pub fn get(&self, group: &str) -> Style {
+        self.groups
+            .get(group)
+            .or_else(|| self.groups.get("Normal"))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn status(&self, status: TaskStatus) -> Style {
+        self.get(&format!("Status{}", status))
+    }
+
+    pub fn priority(&self, priority: i32) -> Style {
+        self.get(&format!("Priority{}", priority.clamp(1, 5)))
+    }
+
+    /// Tags use `Tag.<name>` when defined and the generic `Tag` group otherwise.
+    pub fn tag(&self, tag: &str) -> Style {
+        self.groups
+            .get(&format!("Tag.{}", tag))
+            .copied()
+            .unwrap_or_else(|| self.get("Tag"))
+    }
+}
+
+fn highlight_to_style(hl: &Highlight) -> Style {
+    let mut style = Style::default();
+    if let Some(fg) = hl.fg.as_deref().and_then(parse_color) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = hl.bg.as_deref().and_then(parse_color) {
+        style = style.bg(bg);
+    }
+    let modifiers = [
+        (hl.bold, Modifier::BOLD),
+        (hl.italic, Modifier::ITALIC),
+        (hl.underline, Modifier::UNDERLINED),
+        (hl.reversed, Modifier::REVERSED),
+    ];
+    for (enabled, modifier) in modifiers {
+        if enabled {
+            style = style.add_modifier(modifier);
+        }
+    }
+    style
+}
+
+/// Accepts color names (`red`, `darkgray`), `#rrggbb` and 256-color indexes.
+pub fn parse_color(s: &str) -> Option<Color> {
+    Color::from_str(s).ok()
+}
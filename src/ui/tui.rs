@@ -1,18 +1,31 @@
 use crate::core::{AppState, Mode};
+use crate::core::archive::ArchiveView;
+use crate::core::trash::TrashView;
+use crate::core::picker::{Picker, PickerItem};
+use crate::core::search::SearchKind;
+use crate::core::timer::{TimeEntriesView, TimeReport};
+use crate::core::pomodoro::format_countdown;
+use crate::core::sidebar::{Focus, SidebarItem};
+use crate::domain::time::format_duration;
+use crate::domain::timeline;
 use crate::error::Result;
+use crate::ui::columns::{columns_for, fit, highlight_matches, truncate, ColumnKind};
+use crate::ui::theme::Theme;
+use chrono::Utc;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Table, Row, Cell},
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span, Text},
+    widgets::{BarChart, Block, Borders, Clear, List, ListItem, Paragraph, Sparkline, Table, TableState, Row, Cell, Wrap},
     Terminal,
 };
-use std::io;
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthStr;
 
 pub struct Tui {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
@@ -30,166 +43,29 @@ impl Tui {
 
     pub fn run(&mut self, state: &mut AppState) -> Result<()> {
         while state.running {
+            if let Err(err) = state.tick_pomodoro(Utc::now()) {
+                state.report_error(&err);
+            }
+            if let Err(err) = state.sync_todotxt() {
+                state.report_error(&err);
+            }
+            if std::mem::take(&mut state.bell) {
+                let backend = self.terminal.backend_mut();
+                backend.write_all(b"\x07")?;
+                backend.flush()?;
+            }
             self.terminal.draw(|f| ui(f, state))?;
 
-            if event::poll(std::time::Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    // Record macro if active
-                    if let Some(reg) = state.macro_recording {
-                        if key.code != KeyCode::Char('q') {
-                            state.macros.entry(reg).or_default().push(key);
-                        }
-                    }
-
-                    // Try to get action from keymap first (Normal, Visual, Stats)
-                    if let Some(action) = state.config.keymap.get_action(state.mode, key) {
-                        state.handle_action(action)?;
-                        if key.code != KeyCode::Char('g') {
-                            state.pending_g = false;
-                        }
-                        continue;
-                    }
-
-                    // Fallback to manual handling for Insert/Command or special keys
-                    match state.mode {
-                        Mode::Normal => match key.code {
-                            KeyCode::Char('g') => {
-                                if state.pending_g {
-                                    state.move_to_top();
-                                    state.pending_g = false;
-                                } else {
-                                    state.pending_g = true;
-                                }
-                            }
-                            KeyCode::Char('t') if state.pending_g => {
-                                state.handle_action(crate::core::actions::Action::NextProject)?;
-                                state.pending_g = false;
-                            }
-                            KeyCode::Char('T') if state.pending_g => {
-                                state.handle_action(crate::core::actions::Action::PrevProject)?;
-                                state.pending_g = false;
-                            }
-                            KeyCode::Char('z') => {
-                                state.pending_z = true;
-                            }
-                            KeyCode::Char('a') if state.pending_z => {
-                                state.handle_action(crate::core::actions::Action::ToggleCollapse)?;
-                                state.pending_z = false;
-                            }
-                            KeyCode::Char('y') => {
-                                if state.pending_y {
-                                    state.handle_action(crate::core::actions::Action::Yank)?;
-                                    state.pending_y = false;
-                                } else {
-                                    state.pending_y = true;
-                                }
-                            }
-                            KeyCode::Char('q') => {
-                                if let Some(_) = state.macro_recording {
-                                    state.macro_recording = None;
-                                } else if state.pending_q {
-                                    state.pending_q = false;
-                                } else {
-                                    state.pending_q = true;
-                                }
-                            }
-                            KeyCode::Char(c) if state.pending_q => {
-                                state.macro_recording = Some(c);
-                                state.macros.insert(c, Vec::new());
-                                state.pending_q = false;
-                            }
-                            KeyCode::Char('@') => {
-                                state.pending_at = true;
-                            }
-                            KeyCode::Char(c) if state.pending_at => {
-                                state.play_macro(c)?;
-                                state.pending_at = false;
-                            }
-                            _ => { 
-                                state.pending_g = false; 
-                                state.pending_z = false;
-                                state.pending_y = false;
-                                state.pending_q = false;
-                                state.pending_at = false;
-                            }
-                        },
-                        Mode::Visual => match key.code {
-                            KeyCode::Char('g') => {
-                                if state.pending_g {
-                                    state.move_to_top();
-                                    state.pending_g = false;
-                                } else {
-                                    state.pending_g = true;
-                                }
-                            }
-                            _ => { state.pending_g = false; }
-                        },
-                        Mode::Insert => match key.code {
-                            KeyCode::Esc => {
-                                state.mode = Mode::Normal;
-                                state.editing_task_id = None;
-                            }
-                            KeyCode::Enter => {
-                                if !state.command_buffer.is_empty() {
-                                    match state.insert_action {
-                                        crate::core::state::InsertAction::AddEnd => state.add_task(state.command_buffer.clone())?,
-                                        crate::core::state::InsertAction::AddBelow => state.add_task_below(state.command_buffer.clone())?,
-                                        crate::core::state::InsertAction::AddAbove => state.add_task_above(state.command_buffer.clone())?,
-                                        crate::core::state::InsertAction::Edit => state.commit_edit()?,
-                                    }
-                                    state.command_buffer.clear();
-                                    state.mode = Mode::Normal;
-                                }
-                            }
-                            KeyCode::Char(c) => state.command_buffer.push(c),
-                            KeyCode::Backspace => {
-                                state.command_buffer.pop();
-                            }
-                            _ => {}
-                        },
-                        Mode::Search => match key.code {
-                            KeyCode::Esc => {
-                                state.mode = Mode::Normal;
-                                state.search_query = None;
-                                state.reload_tasks()?;
-                            }
-                            KeyCode::Enter => {
-                                state.search_query = Some(state.command_buffer.clone());
-                                state.reload_tasks()?;
-                                state.mode = Mode::Normal;
-                            }
-                            KeyCode::Char(c) => {
-                                state.command_buffer.push(c);
-                                state.search_query = Some(state.command_buffer.clone());
-                                state.reload_tasks()?;
-                            }
-                            KeyCode::Backspace => {
-                                state.command_buffer.pop();
-                                if state.command_buffer.is_empty() {
-                                    state.search_query = None;
-                                } else {
-                                    state.search_query = Some(state.command_buffer.clone());
-                                }
-                                state.reload_tasks()?;
-                            }
-                            _ => {}
-                        },
-                        Mode::Command => match key.code {
-                            KeyCode::Esc => state.mode = Mode::Normal,
-                            KeyCode::Enter => {
-                                let cmd = state.command_buffer.clone();
-                                state.execute_command(&cmd)?;
-                                state.command_buffer.clear();
-                                state.mode = Mode::Normal;
-                            }
-                            KeyCode::Char(c) => state.command_buffer.push(c),
-                            KeyCode::Backspace => {
-                                state.command_buffer.pop();
-                            }
-                            _ => {}
-                        },
-                        _ => {}
-                    }
+            // Keys that came in while the clipboard waited on the terminal go first
+            let event = match state.clipboard.take_pending_event() {
+                Some(event) => Some(event),
+                None if event::poll(std::time::Duration::from_millis(100))? => Some(event::read()?),
+                None => None,
+            };
+            if let Some(Event::Key(key)) = event {
+                // A failed key or command is reported, never fatal
+                if let Err(err) = state.handle_key(key) {
+                    state.report_error(&err);
                 }
             }
         }
@@ -210,111 +86,626 @@ impl Drop for Tui {
     }
 }
 
+const SIDEBAR_WIDTH: u16 = 24;
+
 fn ui(f: &mut ratatui::Frame, state: &AppState) {
+    let theme = &state.theme;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .style(theme.get("Normal"));
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(f.size());
 
-    match state.mode {
-        Mode::Stats => {
-            let total = state.tasks.len();
-            let done = state.tasks.iter().filter(|t| t.status == crate::domain::TaskStatus::Done).count();
-            let todo = state.tasks.iter().filter(|t| t.status == crate::domain::TaskStatus::Todo).count();
-            let doing = state.tasks.iter().filter(|t| t.status == crate::domain::TaskStatus::Doing).count();
-            let archived = state.tasks.iter().filter(|t| t.status == crate::domain::TaskStatus::Archived).count();
-            
-            let completion_rate = if total > 0 {
-                (done as f64 / total as f64) * 100.0
-            } else {
-                0.0
-            };
+    let main_area = if state.config.show_sidebar {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(0)])
+            .split(chunks[0]);
+        render_sidebar(f, state, theme, block.clone(), columns[0]);
+        columns[1]
+    } else {
+        chunks[0]
+    };
 
-            let stats_text = vec![
-                ListItem::new(format!("Total Tasks: {}", total)),
-                ListItem::new(format!("Todo: {}", todo)),
-                ListItem::new(format!("Doing: {}", doing)),
-                ListItem::new(format!("Done: {}", done)),
-                ListItem::new(format!("Archived: {}", archived)),
-                ListItem::new(format!("Completion Rate: {:.1}%", completion_rate)),
-            ];
-
-            let stats_list = List::new(stats_text)
-                .block(Block::default().borders(Borders::ALL).title(" Statistics "));
-            
-            f.render_widget(stats_list, chunks[0]);
-        }
+    match state.mode {
+        Mode::Stats => render_stats(f, state, theme, block, main_area),
         _ => {
-            let header_cells = ["ID", "Status", "Priority", "Title", "Project"]
+            let columns = columns_for(&state.config, state.view.as_deref());
+            let widths: Vec<Constraint> = columns.iter().map(|c| c.width).collect();
+
+            // Resolve the widths the same way Table does so text can be
+            // truncated or wrapped to the space each column actually gets.
+            let inner_width = main_area.width.saturating_sub(2);
+            let cell_widths: Vec<usize> = Layout::horizontal(widths.clone())
+                .spacing(1)
+                .split(Rect::new(0, 0, inner_width, 1))
                 .iter()
-                .map(|h| Cell::from(*h).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+                .map(|r| r.width as usize)
+                .collect();
+
+            let header_cells = columns.iter().zip(&cell_widths).map(|(c, w)| Cell::from(truncate(&c.header, *w)));
             let header = Row::new(header_cells)
-                .style(Style::default().bg(Color::Blue))
+                .style(theme.get("Header"))
                 .height(1)
                 .bottom_margin(1);
 
+            let now = Utc::now();
+            let highlight = state.search.as_ref().and_then(|s| s.title_highlight());
             let rows = state.tasks.iter().enumerate().map(|(i, task)| {
-                let is_selected = if state.mode == Mode::Visual {
-                    if let Some(anchor) = state.selection_anchor {
-                        let start = anchor.min(state.selected_index);
-                        let end = anchor.max(state.selected_index);
-                        i >= start && i <= end
-                    } else {
-                        i == state.selected_index
-                    }
-                } else {
-                    i == state.selected_index
-                };
+                let is_selected = i == state.selected_index || state.in_visual_range(i);
+                let is_marked = state.marked_tasks.contains(&task.id);
 
                 let style = if is_selected {
-                    Style::default().bg(Color::DarkGray).fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    theme.get("Selection")
+                } else if is_marked {
+                    theme.get("Marked")
                 } else {
-                    Style::default()
+                    theme.get("Normal")
                 };
-                
-                let id_short = &task.id.to_string()[..8];
-                let status = task.status.to_string();
-                let priority = task.priority.to_string();
-                let title = task.title.clone();
-                let project = task.project.clone().unwrap_or_else(|| "-".to_string());
-
-                Row::new(vec![
-                    Cell::from(id_short.to_string()),
-                    Cell::from(status),
-                    Cell::from(priority),
-                    Cell::from(title),
-                    Cell::from(project),
-                ]).style(style)
+
+                let mut height = 1;
+                let cells: Vec<Cell> = columns.iter().zip(&cell_widths).map(|(column, width)| {
+                    let mut segments = column.kind.segments(task, state.tree.get(&task.id), theme, now);
+                    // Cell styles are patched on top of the row style, so keep
+                    // the selection readable by dropping per-cell colors.
+                    if is_selected || is_marked {
+                        for segment in segments.iter_mut() {
+                            segment.1 = style;
+                        }
+                    }
+                    if let (ColumnKind::Title, Some(regex)) = (column.kind, highlight) {
+                        segments = highlight_matches(segments, regex, theme.get("Search"));
+                    }
+                    let lines = fit(segments, *width, column.wrap);
+                    height = height.max(lines.len() as u16);
+                    Cell::from(Text::from(lines))
+                }).collect();
+
+                Row::new(cells).style(style).height(height)
             });
 
-            let task_table = Table::new(
-                rows,
-                [
-                    Constraint::Length(10),
-                    Constraint::Length(10),
-                    Constraint::Length(10),
-                    Constraint::Percentage(50),
-                    Constraint::Percentage(20),
-                ],
-            )
-            .header(header)
-            .block(Block::default().borders(Borders::ALL).title(" TaskVim "));
+            let mut table_state = TableState::default().with_selected(Some(state.selected_index));
+            let title = match &state.view {
+                Some(view) => format!(" TaskVim [{}] ", view),
+                None => " TaskVim ".to_string(),
+            };
+            let task_table = Table::new(rows, widths)
+                .header(header)
+                .block(block.title(title));
             
-            f.render_widget(task_table, chunks[0]);
+            f.render_stateful_widget(task_table, main_area, &mut table_state);
         }
     }
 
     let status_bar = match state.mode {
         Mode::Normal => {
             let recording = state.macro_recording.map(|c| format!(" recording @{}", c)).unwrap_or_default();
-            Paragraph::new(format!("-- NORMAL --{}", recording))
+            let marks = match state.marked_tasks.len() {
+                0 => String::new(),
+                n => format!(" {} marked", n),
+            };
+            let search = state.search.as_ref().map(|s| {
+                let (dir, pattern) = match (s.kind, s.backward) {
+                    (SearchKind::Text, false) => ('/', s.pattern.clone()),
+                    (SearchKind::Text, true) => ('?', s.pattern.clone()),
+                    (SearchKind::Project, false) => ('*', s.pattern.clone()),
+                    (SearchKind::Project, true) => ('#', s.pattern.clone()),
+                };
+                format!(" {}{}", dir, pattern)
+            }).unwrap_or_default();
+            let timer = state.running_timer.as_ref().map(|(entry, title)| {
+                format!(" [{} {}]", truncate(title, 30), format_duration(entry.duration(Utc::now())))
+            }).unwrap_or_default();
+            let pomodoro = state.pomodoro.as_ref().map(|p| {
+                format!(" [{} {} {}]", p.phase.label(), format_countdown(p.remaining(Utc::now())), truncate(&p.title, 20))
+            }).unwrap_or_default();
+            Paragraph::new(Line::from(vec![
+                Span::raw("-- NORMAL --"),
+                Span::raw(search),
+                Span::styled(marks, theme.get("Marked")),
+                Span::styled(recording, theme.get("Recording")),
+                Span::styled(timer, theme.get("Timer")),
+                Span::styled(pomodoro, theme.get("Timer")),
+            ]))
         },
         Mode::Insert => Paragraph::new(format!("-- INSERT -- {}", state.command_buffer)),
-        Mode::Command => Paragraph::new(format!(":{}", state.command_buffer)),
+        Mode::Command => {
+            let range = if state.command_targets.is_some() { "'<,'>" } else { "" };
+            Paragraph::new(format!(":{}{}", range, state.command_buffer))
+        }
         Mode::Visual => Paragraph::new("-- VISUAL --"),
+        Mode::VisualLine => Paragraph::new("-- VISUAL LINE --"),
         Mode::Stats => Paragraph::new("-- STATS --"),
-        Mode::Search => Paragraph::new(format!("/{}", state.command_buffer)),
+        Mode::Search => {
+            let prompt = if state.search_backward { '?' } else { '/' };
+            Paragraph::new(format!("{}{}", prompt, state.command_buffer))
+        }
         Mode::Filter => Paragraph::new(format!("-- FILTER -- {}", state.command_buffer)),
+        Mode::Picker => Paragraph::new("-- FIND --"),
+        Mode::CommandWindow => Paragraph::new("-- COMMAND HISTORY --"),
+        Mode::Messages => Paragraph::new("-- MESSAGES --"),
+        Mode::TimeEntries => Paragraph::new("-- TIME ENTRIES -- j/k move  e edit  d delete  a log  q close"),
+        Mode::Report => Paragraph::new("-- REPORT -- p project  t tag  d day  T task  q close"),
+        Mode::Detail => Paragraph::new("-- TASK -- j/k scroll  q close"),
+        Mode::Archive => Paragraph::new("-- ARCHIVE -- r restore  D purge  q close"),
+        Mode::Trash => Paragraph::new("-- TRASH -- r restore  D purge  q close"),
+    };
+    // A message replaces the mode line until the next key, except while typing
+    let status_bar = match (&state.message, state.mode) {
+        (Some(message), Mode::Normal | Mode::Visual | Mode::VisualLine | Mode::Stats | Mode::TimeEntries | Mode::Report | Mode::Detail | Mode::Archive | Mode::Trash) => {
+            Paragraph::new(Span::styled(message.text.clone(), theme.get(message.level.highlight_group())))
+        }
+        _ => status_bar,
+    };
+    f.render_widget(status_bar.style(theme.get("StatusLine")), chunks[1]);
+
+    if state.mode == Mode::Command {
+        let range = if state.command_targets.is_some() { 5 } else { 0 };
+        let before: String = state.command_buffer.chars().take(state.command_cursor).collect();
+        let x = chunks[1].x + 1 + range + UnicodeWidthStr::width(before.as_str()) as u16;
+        f.set_cursor(x.min(chunks[1].right().saturating_sub(1)), chunks[1].y);
+        render_wildmenu(f, state, theme, chunks[1]);
+    }
+    if state.mode == Mode::CommandWindow {
+        render_command_window(f, state, theme, chunks[0]);
+    }
+    if state.mode == Mode::Messages {
+        render_messages(f, state, theme, chunks[0]);
+    }
+    if let (Mode::TimeEntries, Some(view)) = (state.mode, &state.time_entries_view) {
+        render_time_entries(f, view, theme);
+    }
+    if let (Mode::Report, Some(report)) = (state.mode, &state.report) {
+        render_report(f, report, theme);
+    }
+    if state.mode == Mode::Detail {
+        render_detail(f, state, theme);
+    }
+    if let (Mode::Archive, Some(view)) = (state.mode, &state.archive_view) {
+        render_archive(f, view, theme);
+    }
+    if let (Mode::Trash, Some(view)) = (state.mode, &state.trash_view) {
+        render_trash(f, view, theme);
+    }
+
+    if let (Mode::Picker, Some(picker)) = (state.mode, &state.picker) {
+        render_picker(f, state, picker, theme);
+    }
+}
+
+/// The completion candidates on the line above the status bar, with the one
+/// on the command line highlighted.
+fn render_wildmenu(f: &mut ratatui::Frame, state: &AppState, theme: &Theme, status_area: Rect) {
+    let Some(completion) = &state.completion else {
+        return;
+    };
+    if status_area.y == 0 {
+        return;
+    }
+    let mut spans = Vec::new();
+    for (i, candidate) in completion.candidates.iter().enumerate() {
+        let style = if Some(i) == completion.index { theme.get("Selection") } else { theme.get("StatusLine") };
+        spans.push(Span::styled(candidate.clone(), style));
+        spans.push(Span::styled("  ", theme.get("StatusLine")));
+    }
+    let area = Rect::new(status_area.x, status_area.y - 1, status_area.width, 1);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(Line::from(spans)).style(theme.get("StatusLine")), area);
+}
+
+/// The `q:` window: the command history along the bottom of the task list.
+fn render_command_window(f: &mut ratatui::Frame, state: &AppState, theme: &Theme, main_area: Rect) {
+    let height = (state.command_history.len() as u16 + 2).clamp(3, 12).min(main_area.height);
+    let area = Rect::new(main_area.x, main_area.bottom() - height, main_area.width, height);
+    let visible = height.saturating_sub(2) as usize;
+    let offset = (state.command_window_index + 1).saturating_sub(visible);
+    let items: Vec<ListItem> = state.command_history.iter().enumerate().skip(offset).take(visible).map(|(i, cmd)| {
+        let style = if i == state.command_window_index { theme.get("Selection") } else { theme.get("Normal") };
+        ListItem::new(format!(":{}", cmd)).style(style)
+    }).collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(" Command History ");
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// `:messages`: the message history along the bottom of the task list,
+/// oldest first.
+fn render_messages(f: &mut ratatui::Frame, state: &AppState, theme: &Theme, main_area: Rect) {
+    let height = (state.messages.len() as u16 + 2).clamp(3, 15).min(main_area.height);
+    let area = Rect::new(main_area.x, main_area.bottom() - height, main_area.width, height);
+    let visible = height.saturating_sub(2) as usize;
+    let offset = (state.messages_scroll + 1).saturating_sub(visible);
+    let items: Vec<ListItem> = state.messages.iter().skip(offset).take(visible).map(|message| {
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{} ", message.time.format("%H:%M:%S")), theme.get("Muted")),
+            Span::styled(message.text.clone(), theme.get(message.level.highlight_group())),
+        ]))
+    }).collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(" Messages ");
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// `:stats`: counts, completion charts, project burndowns and tags, all
+/// from the whole database.
+fn render_stats(f: &mut ratatui::Frame, state: &AppState, theme: &Theme, block: Block, area: Rect) {
+    let Some(stats) = &state.stats else {
+        return;
     };
-    f.render_widget(status_bar, chunks[1]);
+    let outer = block.title(" Statistics ");
+    let inner = outer.inner(area);
+    f.render_widget(outer, area);
+    let panel = |title: &str| {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.get("Border"))
+            .title_style(theme.get("Title"))
+            .title(format!(" {} ", title))
+    };
+    let rows = Layout::vertical([Constraint::Length(12), Constraint::Length(5), Constraint::Min(6)]).split(inner);
+    let top = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(rows[0]);
+    let bottom = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).split(rows[2]);
+
+    let lead_time = stats.lead_time.map(|d| match d.num_hours() {
+        hours if hours >= 48 => format!("{:.1} days", d.num_minutes() as f64 / 1440.0),
+        _ => format_duration(d),
+    }).unwrap_or_else(|| "-".to_string());
+    let mut summary = vec![
+        Line::raw(format!("Total Tasks: {}", stats.total)),
+        Line::raw(format!("Todo: {}  Doing: {}  Done: {}  Archived: {}", stats.todo, stats.doing, stats.done, stats.archived)),
+        Line::raw(format!("Completion Rate: {:.1}%", stats.completion_rate())),
+        Line::raw(format!("Average Lead Time: {}", lead_time)),
+        Line::from(vec![
+            Span::raw("Overdue: "),
+            Span::styled(stats.overdue.to_string(), if stats.overdue > 0 { theme.get("Overdue") } else { theme.get("Normal") }),
+            Span::raw(format!("  Due Today: {}", stats.due_today)),
+        ]),
+        Line::raw(format!("Pomodoros: {}", stats.pomodoros.iter().map(|(_, n)| n).sum::<usize>())),
+    ];
+    for (title, count) in stats.pomodoros.iter().take(4) {
+        summary.push(Line::styled(format!("  {:>3}  {}", count, title), theme.get("Muted")));
+    }
+    f.render_widget(Paragraph::new(summary).style(theme.get("Normal")).block(panel("Summary")), top[0]);
+
+    let tag_rows = stats.tags.iter().map(|t| Row::new(vec![
+        Cell::from(Span::styled(t.tag.clone(), theme.tag(&t.tag))),
+        Cell::from(t.open.to_string()),
+        Cell::from(t.done.to_string()),
+    ]));
+    let tags = Table::new(tag_rows, [Constraint::Min(10), Constraint::Length(6), Constraint::Length(6)])
+        .header(Row::new(vec!["Tag", "Open", "Done"]).style(theme.get("Header")))
+        .style(theme.get("Normal"))
+        .block(panel("Tags"));
+    f.render_widget(tags, top[1]);
+
+    let daily_total: u64 = stats.daily.iter().sum();
+    let sparkline = Sparkline::default()
+        .data(&stats.daily)
+        .style(theme.get("Progress"))
+        .block(panel(&format!("Completed per day, last {} days: {}", stats.daily.len(), daily_total)));
+    f.render_widget(sparkline, rows[1]);
+
+    let weekly: Vec<(&str, u64)> = stats.weekly.iter().map(|(label, n)| (label.as_str(), *n)).collect();
+    let chart = BarChart::default()
+        .data(&weekly)
+        .bar_width(5)
+        .bar_gap(1)
+        .bar_style(theme.get("Progress"))
+        .value_style(theme.get("Selection"))
+        .label_style(theme.get("Muted"))
+        .block(panel("Completed per week"));
+    f.render_widget(chart, bottom[0]);
+
+    let burndown_block = panel("Open tasks per project, last 14 days");
+    let burndown_area = burndown_block.inner(bottom[1]);
+    f.render_widget(burndown_block, bottom[1]);
+    let label_width = stats.burndown.iter().map(|(p, _)| p.width()).max().unwrap_or(0).min(16) as u16;
+    for (i, (project, remaining)) in stats.burndown.iter().take(burndown_area.height as usize).enumerate() {
+        let y = burndown_area.y + i as u16;
+        let label = Rect::new(burndown_area.x, y, label_width + 1, 1);
+        let chart = Rect::new(burndown_area.x + label_width + 1, y, burndown_area.width.saturating_sub(label_width + 6), 1);
+        let count = Rect::new(chart.right(), y, 5, 1).intersection(burndown_area);
+        f.render_widget(Paragraph::new(Span::styled(truncate(project, label_width as usize), theme.get("Project"))), label);
+        f.render_widget(Sparkline::default().data(remaining).max(remaining.iter().copied().max().unwrap_or(0).max(1)).style(theme.get("Progress")), chart);
+        f.render_widget(Paragraph::new(format!("{:>4}", remaining.last().unwrap_or(&0))), count);
+    }
+}
+
+/// `:entries`: the time entries of one task, oldest first.
+fn render_time_entries(f: &mut ratatui::Frame, view: &TimeEntriesView, theme: &Theme) {
+    let area = centered_rect(60, 60, f.size());
+    let now = Utc::now();
+    let total = view.entries.iter().fold(chrono::Duration::zero(), |sum, e| sum + e.duration(now));
+    let items: Vec<ListItem> = if view.entries.is_empty() {
+        vec![ListItem::new(Span::styled("No time logged; `a` logs some", theme.get("Muted")))]
+    } else {
+        view.entries.iter().enumerate().map(|(i, entry)| {
+            let start = entry.start.with_timezone(&chrono::Local);
+            let end = match entry.end {
+                Some(end) => end.with_timezone(&chrono::Local).format("%H:%M").to_string(),
+                None => "running".to_string(),
+            };
+            let style = if i == view.selected { theme.get("Selection") } else { theme.get("Normal") };
+            ListItem::new(format!("{}  {} - {:<7}  {:>8}", start.format("%Y-%m-%d"), start.format("%H:%M"), end, format_duration(entry.duration(now)))).style(style)
+        }).collect()
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(format!(" {} ({}) ", truncate(&view.title, 40), format_duration(total)));
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// `:report`: time per group with a bar relative to the largest one.
+fn render_report(f: &mut ratatui::Frame, report: &TimeReport, theme: &Theme) {
+    let area = centered_rect(70, 70, f.size());
+    let label_width = report.rows.iter().map(|(label, _)| label.width()).max().unwrap_or(0).min(30);
+    let max = report.rows.iter().map(|(_, d)| d.num_seconds()).max().unwrap_or(0).max(1);
+    let bar_width = (area.width as usize).saturating_sub(label_width + 16).max(1);
+    let mut lines: Vec<Line> = report.rows.iter().map(|(label, duration)| {
+        let bar = (duration.num_seconds() * bar_width as i64 / max) as usize;
+        Line::from(vec![
+            Span::styled(format!("{:<width$} ", truncate(label, label_width), width = label_width), theme.get("Normal")),
+            Span::styled(format!("{:>8} ", format_duration(*duration)), theme.get("Timer")),
+            Span::styled("█".repeat(bar.max(1)), theme.get("Progress")),
+        ])
+    }).collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("No time logged", theme.get("Muted"))));
+    }
+    lines.push(Line::raw(""));
+    lines.push(Line::from(Span::styled(format!("{:<width$} {:>8}", "Total", format_duration(report.total), width = label_width), theme.get("Header"))));
+    let since = report.since.map(|s| format!(" since {}", s.with_timezone(&chrono::Local).format("%Y-%m-%d"))).unwrap_or_default();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(format!(" Time by {}{} ", report.group.title(), since));
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// A `percent_x` by `percent_y` rect centered in `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+fn render_picker(f: &mut ratatui::Frame, state: &AppState, picker: &Picker, theme: &Theme) {
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"));
+
+    let left = block.clone().title(format!(" {} ({}/{}) ", picker.source.title(), picker.matches.len(), picker.entries.len()));
+    let inner = left.inner(halves[0]);
+    f.render_widget(left, halves[0]);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+    f.render_widget(Paragraph::new(format!("> {}", picker.query)).style(theme.get("Normal")), rows[0]);
+
+    // Keep the selection in view
+    let height = rows[1].height as usize;
+    let offset = (picker.selected + 1).saturating_sub(height);
+    let items: Vec<ListItem> = picker.matches.iter().enumerate().skip(offset).take(height).map(|(i, m)| {
+        let entry = &picker.entries[m.index];
+        let base = if i == picker.selected { theme.get("Selection") } else { theme.get("Normal") };
+        let mut spans: Vec<Span> = entry.label.chars().enumerate().map(|(n, c)| {
+            let style = if m.positions.contains(&n) { base.patch(theme.get("PickerMatch")) } else { base };
+            Span::styled(c.to_string(), style)
+        }).collect();
+        if !entry.detail.is_empty() {
+            spans.push(Span::styled(format!("  {}", entry.detail), theme.get("Muted")));
+        }
+        ListItem::new(Line::from(spans))
+    }).collect();
+    f.render_widget(List::new(items), rows[1]);
+
+    let preview = picker_preview(state, picker, theme);
+    let right = block.title(" Preview ");
+    f.render_widget(Paragraph::new(preview).wrap(Wrap { trim: false }).block(right), halves[1]);
+}
+
+/// The fields, description and status timeline of a task, for the detail
+/// view and the finder preview.
+fn task_detail_lines(state: &AppState, id: uuid::Uuid, theme: &Theme) -> Vec<Line<'static>> {
+    let Ok(Some(task)) = state.storage.get_task(id) else {
+        return Vec::new();
+    };
+    let field = |name: &str, value: String| Line::from(vec![
+        Span::styled(format!("{:<10}", name), theme.get("Muted")),
+        Span::raw(value),
+    ]);
+    let local = |at: chrono::DateTime<Utc>| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+    let subtasks = state.storage.get_tasks(None).map(|all| all.iter().filter(|t| t.parent_id == Some(task.id)).count()).unwrap_or(0);
+    let events = state.storage.status_events(Some(id)).unwrap_or_default();
+    let spent = state.storage.time_entries(Some(id)).unwrap_or_default().iter().fold(chrono::Duration::zero(), |sum, e| sum + e.duration(Utc::now()));
+    let mut lines = vec![
+        Line::styled(task.title.clone(), theme.get("Title")),
+        Line::raw(""),
+        field("Status", format!("{:?}", task.status)),
+        field("Priority", task.priority.to_string()),
+        field("Project", task.project.clone().unwrap_or_default()),
+        field("Tags", task.tags.iter().map(|t| format!("+{}", t)).collect::<Vec<_>>().join(" ")),
+        field("Due", task.due_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default()),
+        field("Created", local(task.created_at)),
+        field("Started", timeline::started_at(&events, id).map(local).unwrap_or_default()),
+        field("Completed", timeline::completed_at(&events, id).map(local).unwrap_or_default()),
+        field("Time", if spent > chrono::Duration::zero() { format_duration(spent) } else { String::new() }),
+        field("Subtasks", subtasks.to_string()),
+    ];
+    if let Some(description) = &task.description {
+        lines.push(Line::raw(""));
+        lines.extend(description.lines().map(|l| Line::raw(l.to_string())));
+    }
+    if !events.is_empty() {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled("Timeline", theme.get("Header")));
+        for event in &events {
+            let change = match event.from {
+                Some(from) => vec![
+                    Span::styled(from.to_string(), theme.status(from)),
+                    Span::raw(" -> "),
+                    Span::styled(event.to.to_string(), theme.status(event.to)),
+                ],
+                None => vec![Span::styled(event.to.to_string(), theme.status(event.to))],
+            };
+            let mut spans = vec![Span::styled(format!("{}  ", local(event.at)), theme.get("Muted"))];
+            spans.extend(change);
+            lines.push(Line::from(spans));
+        }
+    }
+    lines
+}
+
+/// `:archived`: the Archived tasks, most recently archived first.
+fn render_archive(f: &mut ratatui::Frame, view: &ArchiveView, theme: &Theme) {
+    let area = centered_rect(70, 70, f.size());
+    let width = area.width.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = if view.tasks.is_empty() {
+        vec![ListItem::new(Span::styled("No archived tasks", theme.get("Muted")))]
+    } else {
+        view.tasks.iter().enumerate().map(|(i, task)| {
+            let style = if i == view.selected { theme.get("Selection") } else { theme.get("Normal") };
+            let project = task.project.as_deref().map(|p| format!("  {}", p)).unwrap_or_default();
+            let line = format!("{}  {}{}", task.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d"), task.title, project);
+            ListItem::new(truncate(&line, width)).style(style)
+        }).collect()
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(format!(" Archive ({}) ", view.tasks.len()));
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// `:trash`: the deleted tasks, most recently deleted first, subtasks
+/// indented under the task they were deleted with.
+fn render_trash(f: &mut ratatui::Frame, view: &TrashView, theme: &Theme) {
+    let area = centered_rect(70, 70, f.size());
+    let width = area.width.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = if view.entries.is_empty() {
+        vec![ListItem::new(Span::styled("Trash is empty", theme.get("Muted")))]
+    } else {
+        view.entries.iter().enumerate().map(|(i, entry)| {
+            let style = if i == view.selected { theme.get("Selection") } else { theme.get("Normal") };
+            let nested = entry.task.parent_id.map(|p| view.entries.iter().any(|e| e.batch == entry.batch && e.task.id == p)).unwrap_or(false);
+            let indent = if nested { "  " } else { "" };
+            let project = entry.task.project.as_deref().map(|p| format!("  {}", p)).unwrap_or_default();
+            let line = format!("{}  {}{}{}", entry.deleted_at.with_timezone(&chrono::Local).format("%Y-%m-%d"), indent, entry.task.title, project);
+            ListItem::new(truncate(&line, width)).style(style)
+        }).collect()
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(format!(" Trash ({}) ", view.entries.len()));
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// `Tab` / `:info`: the selected task in a popup.
+fn render_detail(f: &mut ratatui::Frame, state: &AppState, theme: &Theme) {
+    let Some(id) = state.detail_task else {
+        return;
+    };
+    let area = centered_rect(60, 80, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.get("Border"))
+        .title_style(theme.get("Title"))
+        .title(" Task ");
+    f.render_widget(Clear, area);
+    let paragraph = Paragraph::new(task_detail_lines(state, id, theme))
+        .style(theme.get("Normal"))
+        .wrap(Wrap { trim: false })
+        .scroll((state.detail_scroll.min(u16::MAX as usize) as u16, 0))
+        .block(block);
+    f.render_widget(paragraph, area);
+}
+
+fn picker_preview(state: &AppState, picker: &Picker, theme: &Theme) -> Text<'static> {
+    let Some(entry) = picker.selected_entry() else {
+        return Text::raw("No matches");
+    };
+    let field = |name: &str, value: String| Line::from(vec![
+        Span::styled(format!("{:<10}", name), theme.get("Muted")),
+        Span::raw(value),
+    ]);
+    match &entry.item {
+        PickerItem::Task(id) => Text::from(task_detail_lines(state, *id, theme)),
+        PickerItem::Project(_) | PickerItem::Tag(_) => Text::from(vec![
+            Line::styled(entry.label.clone(), theme.get("Title")),
+            Line::raw(""),
+            Line::raw(entry.detail.clone()),
+        ]),
+        PickerItem::View(view) => {
+            let columns = state.config.view_columns.get(view).map(|c| c.iter().map(|c| c.field.as_str()).collect::<Vec<_>>().join(", ")).unwrap_or_default();
+            Text::from(vec![Line::styled(format!(":view {}", view), theme.get("Title")), Line::raw(""), field("Columns", columns)])
+        }
+        PickerItem::Command(cmd) | PickerItem::LuaCommand(cmd) => Text::from(vec![
+            Line::styled(format!(":{}", cmd.trim_end()), theme.get("Title")),
+            Line::raw(""),
+            Line::raw(entry.detail.clone()),
+        ]),
+    }
+}
+
+fn render_sidebar(f: &mut ratatui::Frame, state: &AppState, theme: &Theme, block: Block, area: Rect) {
+    let focused = state.focus == Focus::Sidebar;
+    let active = state.active_sidebar_index();
+    let mut items = Vec::new();
+    let mut section = "";
+
+    for (i, entry) in state.sidebar.iter().enumerate() {
+        let (heading, label) = match &entry.item {
+            SidebarItem::All => ("", "All".to_string()),
+            SidebarItem::Project(p) => ("Projects", p.clone()),
+            SidebarItem::Tag(t) => ("Tags", format!("+{}", t)),
+        };
+        if heading != section {
+            section = heading;
+            items.push(ListItem::new(heading).style(theme.get("SidebarHeader")));
+        }
+
+        let style = if focused && i == state.sidebar_index {
+            theme.get("Selection")
+        } else if Some(i) == active {
+            theme.get("SidebarActive")
+        } else {
+            theme.get("Sidebar")
+        };
+        items.push(ListItem::new(format!(" {} ({})", label, entry.count)).style(style));
+    }
+
+    let border = if focused { theme.get("Title") } else { theme.get("Border") };
+    let list = List::new(items).block(block.title(" Filters ").border_style(border));
+    f.render_widget(list, area);
 }